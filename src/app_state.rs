@@ -3,14 +3,20 @@ use crate::content_type::ContentType;
 use crate::database_manager::DatabaseManager;
 use crate::form_parameters::FormParameters;
 use crate::pagelist::DatabaseCluster;
+use crate::pagelist_entry::PageListEntry;
 use crate::platform::MyResponse;
+use crate::result_cache::{CachedResult, ResultCache};
+use crate::saved_query::SavedQuery;
+use crate::scheduler::ScheduledQuery;
+use crate::snapshot::{Snapshot, SnapshotSelector};
 use anyhow::{Result, anyhow};
 use mysql_async as my;
 use serde_json::Value;
 use std::fs;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 use tokio::sync::Semaphore;
 use wikimisc::mediawiki::api::Api;
+use wikimisc::mediawiki::title::Title;
 use wikimisc::site_matrix::SiteMatrix;
 
 /// Inbound concurrency cap: at most this many `process_form` calls run at
@@ -74,6 +80,8 @@ pub struct AppState {
     main_page: String,
     /// Caps inbound request concurrency. See [`MAX_CONCURRENT_REQUESTS`].
     request_semaphore: Arc<Semaphore>,
+    /// Sorted results being paged through with cursors
+    result_cache: Arc<Mutex<ResultCache>>,
 }
 
 impl Default for AppState {
@@ -85,6 +93,7 @@ impl Default for AppState {
             site_matrix: SiteMatrix::default(),
             main_page: String::default(),
             request_semaphore: Arc::new(Semaphore::new(MAX_CONCURRENT_REQUESTS)),
+            result_cache: Arc::new(Mutex::new(ResultCache::default())),
         }
     }
 }
@@ -125,6 +134,7 @@ impl AppState {
             site_matrix,
             main_page,
             request_semaphore: Arc::new(Semaphore::new(MAX_CONCURRENT_REQUESTS)),
+            result_cache: Arc::new(Mutex::new(ResultCache::default())),
         })
     }
 
@@ -158,6 +168,14 @@ impl AppState {
         self.db_manager.get_restart_code()
     }
 
+    pub fn entity_json_dir(&self) -> Option<&str> {
+        self.db_manager.entity_json_dir()
+    }
+
+    pub const fn max_api_entities(&self) -> Option<usize> {
+        self.db_manager.max_api_entities()
+    }
+
     // ------------------------------------------------------------------
     // Delegating accessors – server / schema name resolution
     // ------------------------------------------------------------------
@@ -216,6 +234,64 @@ impl AppState {
             .await
     }
 
+    // ------------------------------------------------------------------
+    // Delegating accessors – result snapshots
+    // ------------------------------------------------------------------
+
+    pub async fn create_snapshot(&self, psid: u64, wiki: &str, titles: &[Title]) -> Result<u64> {
+        self.db_manager.create_snapshot(psid, wiki, titles).await
+    }
+
+    pub async fn get_latest_snapshot_created(&self, psid: u64) -> Result<Option<(u64, String)>> {
+        self.db_manager.get_latest_snapshot_created(psid).await
+    }
+
+    pub async fn get_snapshot(&self, psid: u64, selector: SnapshotSelector) -> Result<Snapshot> {
+        self.db_manager.get_snapshot(psid, selector).await
+    }
+
+    pub async fn get_scheduled_queries(&self) -> Result<Vec<ScheduledQuery>> {
+        self.db_manager.get_scheduled_queries().await
+    }
+
+    // ------------------------------------------------------------------
+    // Cached results, for pagination
+    // ------------------------------------------------------------------
+
+    pub fn cache_result(&self, wiki: &str, pages: Vec<PageListEntry>) -> Result<Option<u64>> {
+        Ok(self
+            .result_cache
+            .lock()
+            .map_err(|e| anyhow!("{e}"))?
+            .insert(wiki, pages))
+    }
+
+    pub fn get_cached_result(&self, key: u64) -> Result<Option<CachedResult>> {
+        Ok(self
+            .result_cache
+            .lock()
+            .map_err(|e| anyhow!("{e}"))?
+            .get(key))
+    }
+
+    // ------------------------------------------------------------------
+    // Delegating accessors – named saved queries
+    // ------------------------------------------------------------------
+
+    pub async fn get_saved_query(&self, name: &str) -> Result<Option<SavedQuery>> {
+        self.db_manager.get_saved_query(name).await
+    }
+
+    pub async fn list_saved_queries(&self, owner: Option<&str>) -> Result<Vec<SavedQuery>> {
+        self.db_manager.list_saved_queries(owner).await
+    }
+
+    /// Creates or updates a saved query, enforcing the owner tag of an
+    /// existing one. Returns the stored version.
+    pub async fn save_saved_query(&self, update: SavedQuery) -> Result<SavedQuery> {
+        self.db_manager.save_saved_query(update).await
+    }
+
     // ------------------------------------------------------------------
     // Native AppState behaviour – main page / rendering
     // ------------------------------------------------------------------
@@ -263,6 +339,7 @@ impl AppState {
                     s: html.to_string(),
                     content_type: ContentType::HTML,
                     status: 200,
+                    binary: None,
                 }
             }
             Some("json") => {
@@ -273,6 +350,7 @@ impl AppState {
                 s: error,
                 content_type: ContentType::Plain,
                 status: 200,
+                binary: None,
             },
         };
         response.status = status;
@@ -289,12 +367,14 @@ impl AppState {
                     s: text,
                     content_type: ContentType::JSONP,
                     status: 200,
+                    binary: None,
                 }
             }
             None => MyResponse {
                 s: json_string,
                 content_type: ContentType::JSON,
                 status: 200,
+                binary: None,
             },
         }
     }
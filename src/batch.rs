@@ -0,0 +1,340 @@
+//! Batch runner: many queries from one file, in one process.
+//!
+//! All jobs share one `AppState`, and with it the `DatabaseManager`
+//! connection pools, instead of paying the startup and connection cost per
+//! query. Jobs run with bounded concurrency; each result goes to its own
+//! file in the output directory, next to a `summary.json` report.
+//!
+//! Input is either JSONL (one object per line; string values are query
+//! parameters, `psid` may be a number) or TSV (`name<TAB>query`, where
+//! `query` is a PSID or a URL-encoded query string). In both, an optional
+//! `name` names the output file; names that repeat get a `_<line>` suffix,
+//! and `summary` is reserved for the report. Empty lines and lines starting
+//! with `#` are skipped.
+
+use crate::app_state::AppState;
+use crate::command_line::{default_format_to_json, rebase_on_psid};
+use crate::form_parameters::FormParameters;
+use crate::platform::Platform;
+use anyhow::{Result, anyhow};
+use futures::stream::{StreamExt, iter};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+use url::form_urlencoded;
+
+pub const DEFAULT_CONCURRENCY: usize = 4;
+/// Base name of the report file, not available to jobs.
+const SUMMARY_NAME: &str = "summary";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchJob {
+    /// Base name of the output file, without extension.
+    pub name: String,
+    pub params: HashMap<String, String>,
+}
+
+impl BatchJob {
+    /// Parses a batch file; the format is picked by extension (`.tsv`, or
+    /// JSONL for anything else).
+    pub fn from_file(path: &Path) -> Result<Vec<Self>> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Cannot read batch file {}: {e}", path.display()))?;
+        if path.extension().is_some_and(|ext| ext == "tsv") {
+            Self::parse_tsv(&text)
+        } else {
+            Self::parse_jsonl(&text)
+        }
+    }
+
+    pub fn parse_jsonl(text: &str) -> Result<Vec<Self>> {
+        Self::content_lines(text)
+            .map(|(line_number, line)| {
+                let j: Value =
+                    serde_json::from_str(line).map_err(|e| anyhow!("Line {line_number}: {e}"))?;
+                let object = j
+                    .as_object()
+                    .ok_or_else(|| anyhow!("Line {line_number}: Expected a JSON object"))?;
+                let mut params: HashMap<String, String> = object
+                    .iter()
+                    .filter_map(|(k, v)| match v {
+                        Value::String(s) => Some((k.to_owned(), s.to_owned())),
+                        Value::Number(n) => Some((k.to_owned(), n.to_string())),
+                        _ => None,
+                    })
+                    .collect();
+                let name = params.remove("name");
+                Ok((line_number, Self::new(name, params, line_number)))
+            })
+            .collect::<Result<Vec<_>>>()
+            .map(Self::with_unique_names)
+    }
+
+    pub fn parse_tsv(text: &str) -> Result<Vec<Self>> {
+        Self::content_lines(text)
+            .map(|(line_number, line)| {
+                let (name, query) = match line.split_once('\t') {
+                    Some((name, query)) => (Some(name.trim().to_string()), query.trim()),
+                    None => (None, line.trim()),
+                };
+                let params: HashMap<String, String> = if query.parse::<u64>().is_ok() {
+                    HashMap::from([("psid".to_string(), query.to_string())])
+                } else if query.contains('=') {
+                    form_urlencoded::parse(query.as_bytes())
+                        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+                        .collect()
+                } else {
+                    return Err(anyhow!(
+                        "Line {line_number}: Expected a PSID or a query string"
+                    ));
+                };
+                Ok((line_number, Self::new(name, params, line_number)))
+            })
+            .collect::<Result<Vec<_>>>()
+            .map(Self::with_unique_names)
+    }
+
+    fn content_lines(text: &str) -> impl Iterator<Item = (usize, &str)> {
+        text.lines()
+            .enumerate()
+            .map(|(num, line)| (num + 1, line))
+            .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+    }
+
+    fn new(name: Option<String>, params: HashMap<String, String>, line_number: usize) -> Self {
+        let name = name
+            .map(|n| Self::sanitize_name(&n))
+            .filter(|n| Self::is_usable_name(n));
+        let name = match (name, params.get("psid")) {
+            (Some(name), _) => name,
+            (None, Some(psid)) => Self::sanitize_name(&format!("psid_{psid}")),
+            (None, None) => format!("line_{line_number}"),
+        };
+        Self { name, params }
+    }
+
+    fn is_usable_name(name: &str) -> bool {
+        !name.is_empty() && !name.eq_ignore_ascii_case(SUMMARY_NAME)
+    }
+
+    /// Appends `_<line>` to names already taken by an earlier line, so no
+    /// job overwrites the output of another.
+    fn with_unique_names(jobs: Vec<(usize, Self)>) -> Vec<Self> {
+        let mut taken = HashSet::new();
+        jobs.into_iter()
+            .map(|(line_number, mut job)| {
+                while !taken.insert(job.name.to_lowercase()) {
+                    job.name = format!("{}_{line_number}", job.name);
+                }
+                job
+            })
+            .collect()
+    }
+
+    /// Keeps output file names inside the output directory.
+    fn sanitize_name(name: &str) -> String {
+        name.trim()
+            .chars()
+            .map(|c| {
+                if c.is_alphanumeric() || c == '-' || c == '_' || c == '.' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect::<String>()
+            .trim_start_matches('.')
+            .to_string()
+    }
+}
+
+/// Outcome of one job, as written to the summary report.
+#[derive(Debug, Clone)]
+pub struct BatchJobResult {
+    pub name: String,
+    pub seconds: f32,
+    /// Output file and number of results, or the error message.
+    pub outcome: Result<(PathBuf, usize), String>,
+}
+
+impl BatchJobResult {
+    fn as_json(&self) -> Value {
+        match &self.outcome {
+            Ok((path, results)) => json!({
+                "name": self.name,
+                "status": "OK",
+                "seconds": self.seconds,
+                "results": results,
+                "output": path.to_string_lossy(),
+            }),
+            Err(e) => json!({
+                "name": self.name,
+                "status": "ERROR",
+                "seconds": self.seconds,
+                "error": e,
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BatchRunner {
+    app_state: Arc<AppState>,
+    output_dir: PathBuf,
+    concurrency: usize,
+    /// Overrides the `format` of every job, if set.
+    format: Option<String>,
+}
+
+impl BatchRunner {
+    pub fn new(
+        app_state: Arc<AppState>,
+        output_dir: PathBuf,
+        concurrency: usize,
+        format: Option<String>,
+    ) -> Self {
+        Self {
+            app_state,
+            output_dir,
+            concurrency: concurrency.max(1),
+            format,
+        }
+    }
+
+    /// Runs all jobs and writes `summary.json`. Failed jobs are reported,
+    /// not returned as errors.
+    pub async fn run(&self, jobs: Vec<BatchJob>) -> Result<Vec<BatchJobResult>> {
+        std::fs::create_dir_all(&self.output_dir).map_err(|e| {
+            anyhow!(
+                "Cannot create output directory {}: {e}",
+                self.output_dir.display()
+            )
+        })?;
+        let start = Instant::now();
+        let results: Vec<BatchJobResult> = iter(jobs)
+            .map(|job| self.run_job(job))
+            .buffered(self.concurrency)
+            .collect()
+            .await;
+
+        let failed = results.iter().filter(|r| r.outcome.is_err()).count();
+        let summary = json!({
+            "jobs": results.len(),
+            "ok": results.len() - failed,
+            "failed": failed,
+            "seconds": start.elapsed().as_secs_f32(),
+            "results": results.iter().map(BatchJobResult::as_json).collect::<Vec<Value>>(),
+        });
+        let summary_path = self.output_dir.join("summary.json");
+        std::fs::write(&summary_path, serde_json::to_string_pretty(&summary)?)
+            .map_err(|e| anyhow!("Cannot write {}: {e}", summary_path.display()))?;
+        Ok(results)
+    }
+
+    async fn run_job(&self, job: BatchJob) -> BatchJobResult {
+        let start = Instant::now();
+        let outcome = self.run_job_inner(&job).await.map_err(|e| e.to_string());
+        if let Err(e) = &outcome {
+            tracing::warn!("Batch job {} failed: {e}", job.name);
+        }
+        BatchJobResult {
+            name: job.name,
+            seconds: start.elapsed().as_secs_f32(),
+            outcome,
+        }
+    }
+
+    async fn run_job_inner(&self, job: &BatchJob) -> Result<(PathBuf, usize)> {
+        let mut form_parameters = FormParameters::new_from_pairs(job.params.to_owned());
+        if let Some(format) = &self.format {
+            form_parameters
+                .params
+                .insert("format".to_string(), format.to_owned());
+        }
+        rebase_on_psid(&self.app_state, &mut form_parameters).await?;
+        default_format_to_json(&mut form_parameters);
+
+        let mut platform = Platform::new_from_parameters(&form_parameters, self.app_state.clone());
+        platform.run().await?;
+        let results = platform.result().as_ref().map_or(0, |result| result.len());
+        let response = platform.get_response().await?;
+        let path = self.output_dir.join(format!(
+            "{}.{}",
+            job.name,
+            response.content_type.file_extension()
+        ));
+        tokio::fs::write(&path, response.body())
+            .await
+            .map_err(|e| anyhow!("Cannot write {}: {e}", path.display()))?;
+        Ok((path, results))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_jsonl() {
+        let text = r#"
+# nightly worklists
+{"name":"physics","language":"en","categories":"Physics","depth":2}
+{"psid":123,"format":"tsv"}
+{"language":"de"}
+"#;
+        let jobs = BatchJob::parse_jsonl(text).unwrap();
+        assert_eq!(jobs.len(), 3);
+        assert_eq!(jobs[0].name, "physics");
+        assert_eq!(jobs[0].params.get("depth").unwrap(), "2");
+        assert!(!jobs[0].params.contains_key("name"));
+        assert_eq!(jobs[1].name, "psid_123");
+        assert_eq!(jobs[1].params.get("psid").unwrap(), "123");
+        assert_eq!(jobs[2].name, "line_5");
+    }
+
+    #[test]
+    fn test_parse_jsonl_errors() {
+        assert!(BatchJob::parse_jsonl("{not json}").is_err());
+        assert!(BatchJob::parse_jsonl("[1,2]").is_err());
+    }
+
+    #[test]
+    fn test_parse_tsv() {
+        let text = "physics\tlanguage=en&categories=Physics&depth=2\n\n123\nold\t456\n";
+        let jobs = BatchJob::parse_tsv(text).unwrap();
+        assert_eq!(jobs.len(), 3);
+        assert_eq!(jobs[0].name, "physics");
+        assert_eq!(jobs[0].params.get("categories").unwrap(), "Physics");
+        assert_eq!(jobs[1].name, "psid_123");
+        assert_eq!(jobs[2].name, "old");
+        assert_eq!(jobs[2].params.get("psid").unwrap(), "456");
+        assert!(BatchJob::parse_tsv("name\tgarbage").is_err());
+    }
+
+    #[test]
+    fn test_sanitize_name() {
+        assert_eq!(
+            BatchJob::sanitize_name("WikiProject Physics"),
+            "WikiProject_Physics"
+        );
+        assert_eq!(
+            BatchJob::sanitize_name("../../etc/passwd"),
+            "_.._etc_passwd"
+        );
+        assert_eq!(BatchJob::sanitize_name("a.b-c_d"), "a.b-c_d");
+        assert_eq!(BatchJob::sanitize_name("..."), "");
+    }
+
+    #[test]
+    fn test_unusable_and_duplicate_names() {
+        let text = "a\t1\na\t2\n...\t3\nsummary\t4\nSummary\tlanguage=en\na_2\t5\n";
+        let jobs = BatchJob::parse_tsv(text).unwrap();
+        let names: Vec<&str> = jobs.iter().map(|job| job.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["a", "a_2", "psid_3", "psid_4", "line_5", "a_2_6"]
+        );
+    }
+}
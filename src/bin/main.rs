@@ -7,17 +7,19 @@ use std::sync::Arc;
 async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     tracing_subscriber::fmt::init();
 
+    // Subcommands load the config themselves; `validate-config` must not
+    // panic on the very config it is asked to check.
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        command_line_usage(&args).await?;
+        return Ok(());
+    }
+
     let petscan_config = get_petscan_config();
     let app_state = Arc::new(AppState::new_from_config(&petscan_config).await?);
-
-    let args = std::env::args();
-    if args.len() > 1 {
-        command_line_usage(app_state).await?;
-    } else {
-        spawn_shutdown_signal_handler(app_state.clone());
-        let webserver = WebServer::new(app_state, petscan_config);
-        webserver.run().await?;
-    }
+    spawn_shutdown_signal_handler(app_state.clone());
+    let webserver = WebServer::new(app_state, petscan_config);
+    webserver.run().await?;
     Ok(())
 }
 
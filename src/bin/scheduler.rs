@@ -0,0 +1,23 @@
+use petscan_rs::app_state::AppState;
+use petscan_rs::command_line::get_petscan_config;
+use petscan_rs::scheduler::{Schedule, Scheduler};
+use std::sync::Arc;
+
+/// Runs scheduled queries. Takes an optional path to a JSON schedule file;
+/// without it, the schedule is read from the `scheduled_query` table.
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    tracing_subscriber::fmt::init();
+
+    let petscan_config = get_petscan_config();
+    let app_state = Arc::new(AppState::new_from_config(&petscan_config).await?);
+
+    let schedule = match std::env::args().nth(1) {
+        Some(path) => Schedule::from_file(path)?,
+        None => Schedule::from_tool_db(&app_state).await?,
+    };
+    tracing::info!("Scheduling {} queries", schedule.queries.len());
+    let mut scheduler = Scheduler::new(app_state, schedule)?;
+    scheduler.run().await?;
+    Ok(())
+}
@@ -1,25 +1,362 @@
 use crate::app_state::AppState;
+use crate::batch::{BatchJob, BatchRunner, DEFAULT_CONCURRENCY};
 use crate::config::Config;
+use crate::database_manager::DatabaseManager;
 use crate::form_parameters::FormParameters;
 use crate::platform::Platform;
+use crate::saved_query::SavedQuery;
 use anyhow::{Result, anyhow};
+use std::collections::BTreeMap;
 use std::env;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use url::form_urlencoded;
 
-pub async fn command_line_usage(app_state: Arc<AppState>) -> Result<()> {
-    let mut args = std::env::args();
-    let _ = args.next(); // the actual command
-    let argument: String = args
-        .next()
-        .ok_or_else(|| anyhow!("No command line argument provided"))?;
+const USAGE: &str = "Usage:
+  petscan run [QUERY OPTIONS] [--format FORMAT] [--output FILE]
+  petscan explain [QUERY OPTIONS]
+  petscan psid show PSID
+  petscan psid create [QUERY OPTIONS]
+  petscan saved list [--owner OWNER]
+  petscan saved show NAME
+  petscan saved set NAME --psid PSID [--title TITLE] [--description TEXT] [--owner OWNER]
+  petscan batch FILE [--output-dir DIR] [--concurrency N] [--format FORMAT]
+  petscan validate-config [CONFIG_FILE]
+  petscan 'url_encoded_parameters'
 
-    let parameter_pairs = form_urlencoded::parse(argument.as_bytes())
-        .map(|(k, v)| (k.into_owned(), v.into_owned()))
-        .collect();
-    let mut form_parameters = FormParameters::new_from_pairs(parameter_pairs);
+QUERY OPTIONS:
+  --param KEY=VALUE   Set a single query parameter (repeatable)
+  --query STRING      Add URL-encoded query parameters
+  --psid PSID         Start from a stored query; other options override it
 
-    // Never output HTML, pick JSON instead as default
+batch FILE is JSONL (one object of parameters per line) or, for *.tsv,
+name<TAB>PSID-or-query-string lines. Results and summary.json go to DIR
+(default: batch_output).";
+
+/// Query parameters given on the command line, shared by the subcommands
+/// that build a query.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QueryArgs {
+    pub params: Vec<(String, String)>,
+    pub psid: Option<u64>,
+    pub format: Option<String>,
+    pub output: Option<PathBuf>,
+}
+
+impl QueryArgs {
+    fn parse(args: &[String]) -> Result<Self> {
+        let mut ret = Self::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| anyhow!("Missing value for {arg}"))
+            };
+            match arg.as_str() {
+                "--param" | "-p" => {
+                    let pair = value()?;
+                    let (key, val) = pair
+                        .split_once('=')
+                        .ok_or_else(|| anyhow!("Expected KEY=VALUE for --param, got '{pair}'"))?;
+                    ret.params.push((key.to_string(), val.to_string()));
+                }
+                "--query" | "-q" => ret.params.extend(Self::parse_query_string(value()?)),
+                "--psid" => {
+                    let psid = value()?;
+                    ret.psid = Some(
+                        psid.parse::<u64>()
+                            .map_err(|_| anyhow!("Invalid PSID '{psid}'"))?,
+                    );
+                }
+                "--format" | "-f" => ret.format = Some(value()?.to_string()),
+                "--output" | "-o" => ret.output = Some(PathBuf::from(value()?)),
+                other => return Err(anyhow!("Unknown option '{other}'\n\n{USAGE}")),
+            }
+        }
+        Ok(ret)
+    }
+
+    fn parse_query_string(query: &str) -> Vec<(String, String)> {
+        form_urlencoded::parse(query.as_bytes())
+            .map(|(k, v)| (k.into_owned(), v.into_owned()))
+            .collect()
+    }
+
+    /// Builds the form parameters; later `--param`s win over earlier ones,
+    /// and explicit `--psid`/`--format` win over both.
+    pub fn form_parameters(&self) -> FormParameters {
+        let mut pairs: std::collections::HashMap<String, String> =
+            self.params.iter().cloned().collect();
+        if let Some(psid) = self.psid {
+            pairs.insert("psid".to_string(), psid.to_string());
+        }
+        if let Some(format) = &self.format {
+            pairs.insert("format".to_string(), format.to_owned());
+        }
+        FormParameters::new_from_pairs(pairs)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Run a query and write the result to stdout or `--output`.
+    Run(QueryArgs),
+    /// Print which data sources and combination a query would use.
+    Explain(QueryArgs),
+    PsidShow(u64),
+    PsidCreate(QueryArgs),
+    SavedList(Option<String>),
+    SavedShow(String),
+    /// Create or re-point a named saved query.
+    SavedSet(SavedQuery),
+    Batch(BatchArgs),
+    ValidateConfig(Option<PathBuf>),
+    Help,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchArgs {
+    pub input: PathBuf,
+    pub output_dir: PathBuf,
+    pub concurrency: usize,
+    pub format: Option<String>,
+}
+
+impl BatchArgs {
+    fn parse(args: &[String]) -> Result<Self> {
+        let (input_file, options) = args
+            .split_first()
+            .ok_or_else(|| anyhow!("batch needs an input file\n\n{USAGE}"))?;
+        let mut ret = Self {
+            input: PathBuf::from(input_file),
+            output_dir: PathBuf::from("batch_output"),
+            concurrency: DEFAULT_CONCURRENCY,
+            format: None,
+        };
+        let mut options = options.iter();
+        while let Some(arg) = options.next() {
+            let value = options
+                .next()
+                .ok_or_else(|| anyhow!("Missing value for {arg}"))?;
+            match arg.as_str() {
+                "--output-dir" | "-o" => ret.output_dir = PathBuf::from(value),
+                "--concurrency" | "-c" => {
+                    ret.concurrency = value
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|n| *n > 0)
+                        .ok_or_else(|| anyhow!("Invalid concurrency '{value}'"))?;
+                }
+                "--format" | "-f" => ret.format = Some(value.to_string()),
+                other => return Err(anyhow!("Unknown option '{other}'\n\n{USAGE}")),
+            }
+        }
+        Ok(ret)
+    }
+}
+
+impl Command {
+    /// Parses the command line, without the program name. A single
+    /// URL-encoded argument is still accepted as shorthand for `run`.
+    pub fn parse(args: &[String]) -> Result<Self> {
+        let (first, rest) = match args.split_first() {
+            Some((first, rest)) => (first.as_str(), rest),
+            None => return Ok(Self::Help),
+        };
+        match first {
+            "run" => Ok(Self::Run(QueryArgs::parse(rest)?)),
+            "explain" => Ok(Self::Explain(QueryArgs::parse(rest)?)),
+            "psid" => match rest.split_first() {
+                Some((sub, [psid])) if sub == "show" => psid
+                    .parse::<u64>()
+                    .map(Self::PsidShow)
+                    .map_err(|_| anyhow!("Invalid PSID '{psid}'")),
+                Some((sub, rest)) if sub == "create" => {
+                    Ok(Self::PsidCreate(QueryArgs::parse(rest)?))
+                }
+                _ => Err(anyhow!(
+                    "Expected 'psid show PSID' or 'psid create'\n\n{USAGE}"
+                )),
+            },
+            "saved" => Self::parse_saved(rest),
+            "batch" => Ok(Self::Batch(BatchArgs::parse(rest)?)),
+            "validate-config" => match rest {
+                [] => Ok(Self::ValidateConfig(None)),
+                [path] => Ok(Self::ValidateConfig(Some(PathBuf::from(path)))),
+                _ => Err(anyhow!("validate-config takes at most one path\n\n{USAGE}")),
+            },
+            "help" | "--help" | "-h" => Ok(Self::Help),
+            legacy if rest.is_empty() && legacy.contains('=') => Ok(Self::Run(QueryArgs {
+                params: QueryArgs::parse_query_string(legacy),
+                ..Default::default()
+            })),
+            other => Err(anyhow!("Unknown command '{other}'\n\n{USAGE}")),
+        }
+    }
+
+    fn parse_saved(args: &[String]) -> Result<Self> {
+        let (sub, rest) = args.split_first().ok_or_else(|| {
+            anyhow!("Expected 'saved list', 'saved show' or 'saved set'\n\n{USAGE}")
+        })?;
+        match (sub.as_str(), rest) {
+            ("list", []) => Ok(Self::SavedList(None)),
+            ("list", [flag, owner]) if flag == "--owner" => {
+                Ok(Self::SavedList(Some(owner.to_string())))
+            }
+            ("show", [name]) => Ok(Self::SavedShow(name.to_string())),
+            ("set", [name, options @ ..]) => {
+                let mut saved_query = SavedQuery {
+                    name: name.to_string(),
+                    ..Default::default()
+                };
+                let mut psid = None;
+                let mut options = options.iter();
+                while let Some(option) = options.next() {
+                    let value = options
+                        .next()
+                        .ok_or_else(|| anyhow!("Missing value for {option}"))?;
+                    match option.as_str() {
+                        "--psid" => {
+                            psid = Some(
+                                value
+                                    .parse::<u64>()
+                                    .map_err(|_| anyhow!("Invalid PSID '{value}'"))?,
+                            );
+                        }
+                        "--title" => saved_query.title = value.to_string(),
+                        "--description" => saved_query.description = value.to_string(),
+                        "--owner" => saved_query.owner = value.to_string(),
+                        other => return Err(anyhow!("Unknown option '{other}'\n\n{USAGE}")),
+                    }
+                }
+                saved_query.psid = psid.ok_or_else(|| anyhow!("saved set needs --psid"))?;
+                SavedQuery::validate_name(&saved_query.name)?;
+                Ok(Self::SavedSet(saved_query))
+            }
+            _ => Err(anyhow!("Invalid 'saved' command\n\n{USAGE}")),
+        }
+    }
+}
+
+/// Entry point for the binary when called with arguments (program name
+/// excluded).
+pub async fn command_line_usage(args: &[String]) -> Result<()> {
+    let command = Command::parse(args)?;
+    match command {
+        Command::Help => {
+            println!("{USAGE}");
+            return Ok(());
+        }
+        Command::ValidateConfig(path) => return validate_config(path).await,
+        _ => {}
+    }
+
+    let petscan_config = get_petscan_config();
+    let app_state = Arc::new(AppState::new_from_config(&petscan_config).await?);
+    match command {
+        Command::Run(query_args) => run_query(app_state, &query_args).await,
+        Command::Explain(query_args) => {
+            let mut form_parameters = query_args.form_parameters();
+            rebase_on_psid(&app_state, &mut form_parameters).await?;
+            let platform = Platform::new_from_parameters(&form_parameters, app_state);
+            let explanation = platform.explain().await;
+            println!("{}", serde_json::to_string_pretty(&explanation)?);
+            Ok(())
+        }
+        Command::PsidShow(psid) => {
+            let query = app_state.get_query_from_psid(&psid.to_string()).await?;
+            let parameters: BTreeMap<String, String> = FormParameters::outcome_from_query(&query)?
+                .params
+                .into_iter()
+                .collect();
+            let j = json!({"psid": psid, "query": query, "parameters": parameters});
+            println!("{}", serde_json::to_string_pretty(&j)?);
+            Ok(())
+        }
+        Command::PsidCreate(query_args) => {
+            let mut form_parameters = query_args.form_parameters();
+            rebase_on_psid(&app_state, &mut form_parameters).await?;
+            form_parameters.params.remove("psid");
+            let psid = app_state
+                .get_or_create_psid_for_query(&form_parameters.to_string())
+                .await?;
+            println!("{psid}");
+            Ok(())
+        }
+        Command::SavedList(owner) => {
+            let saved_queries = app_state.list_saved_queries(owner.as_deref()).await?;
+            for saved_query in saved_queries {
+                println!(
+                    "{}\t{}\t{}\t{}",
+                    saved_query.name, saved_query.psid, saved_query.owner, saved_query.title
+                );
+            }
+            Ok(())
+        }
+        Command::SavedShow(name) => {
+            let saved_query = app_state
+                .get_saved_query(&name)
+                .await?
+                .ok_or_else(|| anyhow!("No saved query named '{name}'"))?;
+            println!("{}", serde_json::to_string_pretty(&saved_query.as_json())?);
+            Ok(())
+        }
+        Command::SavedSet(update) => {
+            // Make sure the PSID exists before pointing a name at it
+            app_state
+                .get_query_from_psid(&update.psid.to_string())
+                .await?;
+            let saved_query = app_state.save_saved_query(update).await?;
+            println!("{}", serde_json::to_string_pretty(&saved_query.as_json())?);
+            Ok(())
+        }
+        Command::Batch(batch_args) => {
+            let jobs = BatchJob::from_file(&batch_args.input)?;
+            let runner = BatchRunner::new(
+                app_state,
+                batch_args.output_dir.to_owned(),
+                batch_args.concurrency,
+                batch_args.format,
+            );
+            let results = runner.run(jobs).await?;
+            let failed = results.iter().filter(|r| r.outcome.is_err()).count();
+            println!(
+                "{} queries, {failed} failed; see {}",
+                results.len(),
+                batch_args.output_dir.join("summary.json").display()
+            );
+            Ok(())
+        }
+        Command::Help | Command::ValidateConfig(_) => Ok(()),
+    }
+}
+
+async fn run_query(app_state: Arc<AppState>, query_args: &QueryArgs) -> Result<()> {
+    let mut form_parameters = query_args.form_parameters();
+
+    // Load PSID if set
+    rebase_on_psid(&app_state, &mut form_parameters).await?;
+
+    default_format_to_json(&mut form_parameters);
+
+    // If `run()` fails, surface that error directly. Falling through to
+    // `get_response()` would mask the real cause with a generic "No result".
+    let mut platform = Platform::new_from_parameters(&form_parameters, app_state);
+    platform.run().await?;
+    let response = platform.get_response().await?;
+    match &query_args.output {
+        Some(path) => std::fs::write(path, response.body())
+            .map_err(|e| anyhow!("Cannot write {}: {e}", path.display()))?,
+        None if response.binary.is_some() => std::io::stdout().write_all(response.body())?,
+        None => println!("{}", response.s),
+    }
+
+    Ok(())
+}
+
+/// Never output HTML outside the web server, pick JSON instead as default.
+pub fn default_format_to_json(form_parameters: &mut FormParameters) {
     let format: String = match form_parameters.params.get("format") {
         Some(format) => match format.as_str() {
             "html" | "" => "json".into(),
@@ -28,33 +365,52 @@ pub async fn command_line_usage(app_state: Arc<AppState>) -> Result<()> {
         None => "json".into(),
     };
     form_parameters.params.insert("format".into(), format);
+}
 
-    // Load PSID if set
-    if let Some(psid) = form_parameters.params.get("psid")
-        && !psid.trim().is_empty()
+/// Checks that the config file parses, has the keys the tool DB needs, and
+/// that the tool DB is reachable with it.
+async fn validate_config(path: Option<PathBuf>) -> Result<()> {
+    let path = match path {
+        Some(path) => path,
+        None => env::current_dir()?.join("config.json"),
+    };
+    let config = Config::from_file(&path)?;
+    println!("OK: {} parses", path.display());
+
+    let mut problems = vec![];
+    if config.schema.is_empty() {
+        problems.push("no 'schema' key".to_string());
+    }
+    if !Path::new("./html/index.html").exists() {
+        problems.push("./html/index.html not found in the working directory".to_string());
+    }
+    match DatabaseManager::new_from_config(&config)
+        .get_tool_db_connection()
+        .await
     {
-        match app_state.get_query_from_psid(&psid.to_string()).await {
-            Ok(psid_query) => {
-                let psid_params = FormParameters::outcome_from_query(&psid_query)?;
-                form_parameters.rebase(&psid_params);
-            }
-            Err(e) => return Err(e),
-        }
+        Ok(_conn) => println!("OK: tool DB connection"),
+        Err(e) => problems.push(format!("tool DB connection failed: {e}")),
     }
 
-    let mut platform = Platform::new_from_parameters(&form_parameters, app_state.clone());
-
-    // If `run()` fails, surface that error directly. Falling through to
-    // `get_response()` would mask the real cause with a generic "No result".
-    let response = match platform.run().await {
-        Ok(()) => match platform.get_response().await {
-            Ok(response) => response,
-            Err(error) => app_state.render_error(error.to_string(), &form_parameters),
-        },
-        Err(error) => app_state.render_error(error.to_string(), &form_parameters),
-    };
-    println!("{}", json!(response.s).as_str().unwrap_or(&response.s));
+    if problems.is_empty() {
+        return Ok(());
+    }
+    Err(anyhow!("Invalid config:\n  {}", problems.join("\n  ")))
+}
 
+/// If `form_parameters` has a non-empty `psid`, loads the stored query and
+/// patches it in underneath the given parameters.
+pub async fn rebase_on_psid(
+    app_state: &AppState,
+    form_parameters: &mut FormParameters,
+) -> Result<()> {
+    if let Some(psid) = form_parameters.params.get("psid")
+        && !psid.trim().is_empty()
+    {
+        let psid_query = app_state.get_query_from_psid(&psid.to_string()).await?;
+        let psid_params = FormParameters::outcome_from_query(&psid_query)?;
+        form_parameters.rebase(&psid_params);
+    }
     Ok(())
 }
 
@@ -69,3 +425,176 @@ pub fn get_petscan_config() -> Config {
     let path = basedir.to_owned() + "/config.json";
     Config::from_file(&path).expect("config.json load failed")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &[&str]) -> Vec<String> {
+        s.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_legacy_query_string() {
+        let command = Command::parse(&args(&["language=en&categories=Foo%20bar"])).unwrap();
+        let Command::Run(query_args) = command else {
+            panic!("expected Run");
+        };
+        assert_eq!(
+            query_args.params,
+            vec![
+                ("language".to_string(), "en".to_string()),
+                ("categories".to_string(), "Foo bar".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_run_with_options() {
+        let command = Command::parse(&args(&[
+            "run",
+            "--param",
+            "language=en",
+            "--param",
+            "categories=A=B",
+            "--psid",
+            "123",
+            "--format",
+            "tsv",
+            "--output",
+            "/tmp/out.tsv",
+        ]))
+        .unwrap();
+        assert_eq!(
+            command,
+            Command::Run(QueryArgs {
+                params: vec![
+                    ("language".to_string(), "en".to_string()),
+                    ("categories".to_string(), "A=B".to_string())
+                ],
+                psid: Some(123),
+                format: Some("tsv".to_string()),
+                output: Some(PathBuf::from("/tmp/out.tsv")),
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Command::parse(&args(&["run", "--param", "no_equals"])).is_err());
+        assert!(Command::parse(&args(&["run", "--psid", "abc"])).is_err());
+        assert!(Command::parse(&args(&["run", "--format"])).is_err());
+        assert!(Command::parse(&args(&["run", "--bogus"])).is_err());
+        assert!(Command::parse(&args(&["psid", "show"])).is_err());
+        assert!(Command::parse(&args(&["frobnicate"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_psid_and_config_commands() {
+        assert_eq!(
+            Command::parse(&args(&["psid", "show", "42"])).unwrap(),
+            Command::PsidShow(42)
+        );
+        assert_eq!(
+            Command::parse(&args(&["psid", "create", "-p", "language=de"])).unwrap(),
+            Command::PsidCreate(QueryArgs {
+                params: vec![("language".to_string(), "de".to_string())],
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            Command::parse(&args(&["validate-config"])).unwrap(),
+            Command::ValidateConfig(None)
+        );
+        assert_eq!(
+            Command::parse(&args(&["validate-config", "/etc/petscan.json"])).unwrap(),
+            Command::ValidateConfig(Some(PathBuf::from("/etc/petscan.json")))
+        );
+        assert_eq!(Command::parse(&[]).unwrap(), Command::Help);
+    }
+
+    #[test]
+    fn test_parse_saved() {
+        assert_eq!(
+            Command::parse(&args(&["saved", "list"])).unwrap(),
+            Command::SavedList(None)
+        );
+        assert_eq!(
+            Command::parse(&args(&["saved", "list", "--owner", "wpmed"])).unwrap(),
+            Command::SavedList(Some("wpmed".to_string()))
+        );
+        assert_eq!(
+            Command::parse(&args(&["saved", "show", "wpmed-unreferenced"])).unwrap(),
+            Command::SavedShow("wpmed-unreferenced".to_string())
+        );
+        assert_eq!(
+            Command::parse(&args(&[
+                "saved",
+                "set",
+                "wpmed-unreferenced",
+                "--psid",
+                "123",
+                "--title",
+                "Unreferenced medicine articles",
+                "--owner",
+                "wpmed",
+            ]))
+            .unwrap(),
+            Command::SavedSet(SavedQuery {
+                name: "wpmed-unreferenced".to_string(),
+                title: "Unreferenced medicine articles".to_string(),
+                owner: "wpmed".to_string(),
+                psid: 123,
+                ..Default::default()
+            })
+        );
+        assert!(Command::parse(&args(&["saved", "set", "no-psid"])).is_err());
+        assert!(Command::parse(&args(&["saved", "set", "Bad Name", "--psid", "1"])).is_err());
+        assert!(Command::parse(&args(&["saved", "delete", "x"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_batch() {
+        assert_eq!(
+            Command::parse(&args(&["batch", "jobs.jsonl"])).unwrap(),
+            Command::Batch(BatchArgs {
+                input: PathBuf::from("jobs.jsonl"),
+                output_dir: PathBuf::from("batch_output"),
+                concurrency: DEFAULT_CONCURRENCY,
+                format: None,
+            })
+        );
+        assert_eq!(
+            Command::parse(&args(&[
+                "batch", "jobs.tsv", "-o", "/tmp/out", "-c", "8", "-f", "tsv"
+            ]))
+            .unwrap(),
+            Command::Batch(BatchArgs {
+                input: PathBuf::from("jobs.tsv"),
+                output_dir: PathBuf::from("/tmp/out"),
+                concurrency: 8,
+                format: Some("tsv".to_string()),
+            })
+        );
+        assert!(Command::parse(&args(&["batch"])).is_err());
+        assert!(Command::parse(&args(&["batch", "jobs.tsv", "-c", "0"])).is_err());
+    }
+
+    #[test]
+    fn test_form_parameters_explicit_options_win() {
+        let query_args = QueryArgs {
+            params: vec![
+                ("format".to_string(), "csv".to_string()),
+                ("depth".to_string(), "1".to_string()),
+                ("depth".to_string(), "3".to_string()),
+            ],
+            psid: Some(7),
+            format: Some("json".to_string()),
+            output: None,
+        };
+        let form_parameters = query_args.form_parameters();
+        assert_eq!(form_parameters.params.get("format").unwrap(), "json");
+        assert_eq!(form_parameters.params.get("depth").unwrap(), "3");
+        assert_eq!(form_parameters.params.get("psid").unwrap(), "7");
+    }
+}
@@ -36,6 +36,14 @@ pub struct Config {
     /// Local-dev SSH-tunnel port overrides, keyed by wiki (e.g. `enwiki`) or
     /// the literal `x3` for the term-store cluster.
     pub port_mapping: HashMap<String, u16>,
+    /// Directory of Wikidata entity JSON files (`Q42.json`, as served by
+    /// `Special:EntityData`), read instead of the Wikidata API. For local
+    /// development without network access.
+    pub entity_json_dir: Option<String>,
+    /// Maximum number of Wikidata entities one query may load from the
+    /// Wikidata API (for statement values and lexeme data). Default 5000 is
+    /// applied where it's read; 0 disables the API.
+    pub max_api_entities: Option<usize>,
 }
 
 impl Config {
@@ -66,6 +74,8 @@ mod tests {
         assert!(!c.use_file_table);
         assert_eq!(c.restart_code, None);
         assert!(c.port_mapping.is_empty());
+        assert_eq!(c.entity_json_dir, None);
+        assert_eq!(c.max_api_entities, None);
     }
 
     #[test]
@@ -8,6 +8,12 @@ pub enum ContentType {
     TSV,
     KML,
     JSONL,
+    GeoJSON,
+    GPX,
+    Parquet,
+    NTriples,
+    Turtle,
+    XLSX,
 }
 
 impl ContentType {
@@ -21,6 +27,32 @@ impl ContentType {
             Self::TSV => "text/tab-separated-values; charset=utf-8",
             Self::KML => "application/vnd.google-earth.kml+xml",
             Self::JSONL => "application/x-ndjson",
+            Self::GeoJSON => "application/geo+json",
+            Self::GPX => "application/gpx+xml",
+            Self::Parquet => "application/vnd.apache.parquet",
+            Self::NTriples => "application/n-triples",
+            Self::Turtle => "text/turtle; charset=utf-8",
+            Self::XLSX => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        }
+    }
+
+    /// File extension for saving a response of this type to disk.
+    pub const fn file_extension(&self) -> &str {
+        match self {
+            Self::HTML => "html",
+            Self::Plain => "txt",
+            Self::JSON => "json",
+            Self::JSONP => "js",
+            Self::CSV => "csv",
+            Self::TSV => "tsv",
+            Self::KML => "kml",
+            Self::JSONL => "jsonl",
+            Self::GeoJSON => "geojson",
+            Self::GPX => "gpx",
+            Self::Parquet => "parquet",
+            Self::NTriples => "nt",
+            Self::Turtle => "ttl",
+            Self::XLSX => "xlsx",
         }
     }
 }
@@ -45,6 +77,31 @@ mod tests {
             "application/vnd.google-earth.kml+xml"
         );
         assert_eq!(ContentType::JSONL.as_str(), "application/x-ndjson");
+        assert_eq!(ContentType::GeoJSON.as_str(), "application/geo+json");
+        assert_eq!(ContentType::GPX.as_str(), "application/gpx+xml");
+        assert_eq!(
+            ContentType::Parquet.as_str(),
+            "application/vnd.apache.parquet"
+        );
+        assert_eq!(ContentType::NTriples.as_str(), "application/n-triples");
+        assert_eq!(ContentType::Turtle.as_str(), "text/turtle; charset=utf-8");
+        assert_eq!(
+            ContentType::XLSX.as_str(),
+            "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"
+        );
+    }
+
+    #[test]
+    fn test_content_type_file_extension() {
+        assert_eq!(ContentType::HTML.file_extension(), "html");
+        assert_eq!(ContentType::Plain.file_extension(), "txt");
+        assert_eq!(ContentType::JSONL.file_extension(), "jsonl");
+        assert_eq!(ContentType::TSV.file_extension(), "tsv");
+        assert_eq!(ContentType::GeoJSON.file_extension(), "geojson");
+        assert_eq!(ContentType::GPX.file_extension(), "gpx");
+        assert_eq!(ContentType::Parquet.file_extension(), "parquet");
+        assert_eq!(ContentType::Turtle.file_extension(), "ttl");
+        assert_eq!(ContentType::XLSX.file_extension(), "xlsx");
     }
 
     #[test]
@@ -1,5 +1,8 @@
 use crate::config::Config;
 use crate::pagelist::DatabaseCluster;
+use crate::saved_query::SavedQuery;
+use crate::scheduler::ScheduledQuery;
+use crate::snapshot::{Snapshot, SnapshotSelector};
 use anyhow::{Result, anyhow};
 use chrono::prelude::*;
 use mysql_async as my;
@@ -10,6 +13,10 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use tracing::{instrument, trace};
+use wikimisc::mediawiki::title::Title;
+
+/// `saved_query` columns: name, title, description, owner, psid, updated.
+type SavedQueryRow = (String, String, Option<String>, String, u64, Option<String>);
 
 /// The termstore host for the X3 / Wikidata term-store cluster.
 /// This is a non-standard hostname that toolforge does not generate, so we
@@ -128,6 +135,14 @@ impl DatabaseManager {
         self.config.restart_code.as_deref()
     }
 
+    pub fn entity_json_dir(&self) -> Option<&str> {
+        self.config.entity_json_dir.as_deref()
+    }
+
+    pub const fn max_api_entities(&self) -> Option<usize> {
+        self.config.max_api_entities
+    }
+
     // ------------------------------------------------------------------
     // Credential resolution
     // ------------------------------------------------------------------
@@ -498,6 +513,217 @@ impl DatabaseManager {
             )),
         }
     }
+
+    // ------------------------------------------------------------------
+    // Tool-DB query helpers (result snapshots)
+    // ------------------------------------------------------------------
+
+    /// Stores the titles in `snapshot` for its PSID and returns the new
+    /// snapshot ID. The title list is compressed server-side.
+    pub async fn create_snapshot(&self, psid: u64, wiki: &str, titles: &[Title]) -> Result<u64> {
+        let utc: DateTime<Utc> = Utc::now();
+        let now = utc.format("%Y-%m-%d %H:%M:%S").to_string();
+        let sql = (
+            "INSERT INTO `snapshot` (psid,wiki,created,entries,titles) VALUES (?,?,?,?,COMPRESS(?))",
+            vec![
+                MyValue::UInt(psid),
+                MyValue::Bytes(wiki.to_owned().into()),
+                MyValue::Bytes(now.into()),
+                MyValue::UInt(titles.len() as u64),
+                MyValue::Bytes(Snapshot::encode_titles(titles).into()),
+            ],
+        );
+
+        let mut conn = self.get_tool_db_connection().await?;
+        conn.exec_drop(sql.0, mysql_async::Params::Positional(sql.1))
+            .await
+            .map_err(|e| anyhow!(e))?;
+        conn.last_insert_id()
+            .ok_or_else(|| anyhow!("DatabaseManager::create_snapshot: Could not insert"))
+    }
+
+    /// ID and creation time of the latest snapshot of `psid`, if any.
+    pub async fn get_latest_snapshot_created(&self, psid: u64) -> Result<Option<(u64, String)>> {
+        let mut conn = self.get_tool_db_connection().await?;
+        let sql = (
+            "SELECT id,created FROM `snapshot` WHERE psid=? ORDER BY id DESC LIMIT 1",
+            vec![MyValue::UInt(psid)],
+        );
+        let rows = conn
+            .exec_iter(sql.0, mysql_async::Params::Positional(sql.1))
+            .await
+            .map_err(|e| anyhow!(e))?
+            .map_and_drop(from_row::<(u64, Option<String>)>)
+            .await
+            .map_err(|e| anyhow!(e))?;
+        Ok(rows
+            .into_iter()
+            .next()
+            .map(|(id, created)| (id, created.unwrap_or_default())))
+    }
+
+    /// Loads a stored snapshot for `psid`.
+    pub async fn get_snapshot(&self, psid: u64, selector: SnapshotSelector) -> Result<Snapshot> {
+        let mut conn = self.get_tool_db_connection().await?;
+        let sql = match selector {
+            SnapshotSelector::Latest => (
+                "SELECT id,psid,wiki,created,UNCOMPRESS(titles) FROM `snapshot` WHERE psid=? ORDER BY id DESC LIMIT 1",
+                vec![MyValue::UInt(psid)],
+            ),
+            SnapshotSelector::Id(id) => (
+                "SELECT id,psid,wiki,created,UNCOMPRESS(titles) FROM `snapshot` WHERE psid=? AND id=?",
+                vec![MyValue::UInt(psid), MyValue::UInt(id)],
+            ),
+        };
+
+        let rows = conn
+            .exec_iter(sql.0, mysql_async::Params::Positional(sql.1))
+            .await
+            .map_err(|e| anyhow!(e))?
+            .map_and_drop(from_row::<(u64, u64, String, String, Option<Vec<u8>>)>)
+            .await
+            .map_err(|e| anyhow!(e))?;
+
+        match rows.into_iter().next() {
+            Some((id, snapshot_psid, wiki, created, titles)) => Ok(Snapshot {
+                id,
+                psid: snapshot_psid,
+                wiki,
+                created,
+                titles: Snapshot::decode_titles(&String::from_utf8_lossy(
+                    &titles.unwrap_or_default(),
+                )),
+            }),
+            None => Err(anyhow!("No such snapshot for PSID {psid}")),
+        }
+    }
+
+    // ------------------------------------------------------------------
+    // Tool-DB query helpers (named saved queries)
+    // ------------------------------------------------------------------
+
+    pub async fn get_saved_query(&self, name: &str) -> Result<Option<SavedQuery>> {
+        let mut conn = self.get_tool_db_connection().await?;
+        let sql = (
+            "SELECT name,title,description,owner,psid,updated FROM `saved_query` WHERE name=?",
+            vec![MyValue::Bytes(name.to_owned().into())],
+        );
+        let rows = conn
+            .exec_iter(sql.0, mysql_async::Params::Positional(sql.1))
+            .await
+            .map_err(|e| anyhow!(e))?
+            .map_and_drop(from_row::<SavedQueryRow>)
+            .await
+            .map_err(|e| anyhow!(e))?;
+        Ok(rows.into_iter().next().map(Self::saved_query_from_row))
+    }
+
+    /// All saved queries, optionally only those with the given owner tag.
+    pub async fn list_saved_queries(&self, owner: Option<&str>) -> Result<Vec<SavedQuery>> {
+        let mut conn = self.get_tool_db_connection().await?;
+        let sql = match owner {
+            Some(owner) => (
+                "SELECT name,title,description,owner,psid,updated FROM `saved_query` WHERE owner=? ORDER BY name",
+                vec![MyValue::Bytes(owner.to_owned().into())],
+            ),
+            None => (
+                "SELECT name,title,description,owner,psid,updated FROM `saved_query` ORDER BY name",
+                vec![],
+            ),
+        };
+        let rows = conn
+            .exec_iter(sql.0, mysql_async::Params::Positional(sql.1))
+            .await
+            .map_err(|e| anyhow!(e))?
+            .map_and_drop(from_row::<SavedQueryRow>)
+            .await
+            .map_err(|e| anyhow!(e))?;
+        Ok(rows.into_iter().map(Self::saved_query_from_row).collect())
+    }
+
+    /// Creates the saved query, or merges `update` into the stored one (see
+    /// `SavedQuery::merge_update`). The row is locked from the owner check to
+    /// the write, so concurrent updates can't bypass it. The `updated` field
+    /// is set here.
+    pub async fn save_saved_query(&self, update: SavedQuery) -> Result<SavedQuery> {
+        let mut conn = self.get_tool_db_connection().await?;
+        let mut tx = conn
+            .start_transaction(my::TxOpts::default())
+            .await
+            .map_err(|e| anyhow!(e))?;
+        let sql = (
+            "SELECT name,title,description,owner,psid,updated FROM `saved_query` WHERE name=? FOR UPDATE",
+            vec![MyValue::Bytes(update.name.to_owned().into())],
+        );
+        let rows = tx
+            .exec_iter(sql.0, mysql_async::Params::Positional(sql.1))
+            .await
+            .map_err(|e| anyhow!(e))?
+            .map_and_drop(from_row::<SavedQueryRow>)
+            .await
+            .map_err(|e| anyhow!(e))?;
+        let existing = rows.into_iter().next().map(Self::saved_query_from_row);
+        let mut saved_query = SavedQuery::merge_update(existing.as_ref(), update)?;
+
+        let utc: DateTime<Utc> = Utc::now();
+        saved_query.updated = utc.format("%Y-%m-%d %H:%M:%S").to_string();
+        let sql = (
+            "INSERT INTO `saved_query` (name,title,description,owner,psid,updated) VALUES (?,?,?,?,?,?) \
+             ON DUPLICATE KEY UPDATE title=VALUES(title),description=VALUES(description),owner=VALUES(owner),psid=VALUES(psid),updated=VALUES(updated)",
+            vec![
+                MyValue::Bytes(saved_query.name.to_owned().into()),
+                MyValue::Bytes(saved_query.title.to_owned().into()),
+                MyValue::Bytes(saved_query.description.to_owned().into()),
+                MyValue::Bytes(saved_query.owner.to_owned().into()),
+                MyValue::UInt(saved_query.psid),
+                MyValue::Bytes(saved_query.updated.to_owned().into()),
+            ],
+        );
+        tx.exec_drop(sql.0, mysql_async::Params::Positional(sql.1))
+            .await
+            .map_err(|e| anyhow!(e))?;
+        tx.commit().await.map_err(|e| anyhow!(e))?;
+        Ok(saved_query)
+    }
+
+    fn saved_query_from_row(row: SavedQueryRow) -> SavedQuery {
+        let (name, title, description, owner, psid, updated) = row;
+        SavedQuery {
+            name,
+            title,
+            description: description.unwrap_or_default(),
+            owner,
+            psid,
+            updated: updated.unwrap_or_default(),
+        }
+    }
+
+    // ------------------------------------------------------------------
+    // Tool-DB query helpers (scheduled queries)
+    // ------------------------------------------------------------------
+
+    /// Loads all enabled rows of the `scheduled_query` table.
+    pub async fn get_scheduled_queries(&self) -> Result<Vec<ScheduledQuery>> {
+        let mut conn = self.get_tool_db_connection().await?;
+        let sql = "SELECT psid,run_every,format,output_path,snapshot FROM `scheduled_query` WHERE enabled=1 ORDER BY id";
+        let rows = conn
+            .exec_iter(sql, ())
+            .await
+            .map_err(|e| anyhow!(e))?
+            .map_and_drop(from_row::<(u64, String, String, String, u8)>)
+            .await
+            .map_err(|e| anyhow!(e))?;
+        Ok(rows
+            .into_iter()
+            .map(|(psid, every, format, output, snapshot)| ScheduledQuery {
+                psid,
+                every,
+                format,
+                output: output.into(),
+                snapshot: snapshot != 0,
+            })
+            .collect())
+    }
 }
 
 #[cfg(test)]
@@ -43,6 +43,7 @@
 extern crate serde_json;
 
 pub mod app_state;
+pub mod batch;
 pub mod combination;
 pub mod command_line;
 pub mod config;
@@ -55,8 +56,13 @@ pub mod pagelist_entry;
 pub mod platform;
 pub mod query_context;
 pub mod render;
+pub mod result_cache;
+pub mod saved_query;
+pub mod scheduler;
+pub mod snapshot;
 pub mod wdfist;
 pub mod webserver;
+pub mod wikidata_entities;
 
 #[cfg(test)]
 pub(crate) mod test_support;
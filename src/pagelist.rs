@@ -43,44 +43,100 @@ pub enum DatabaseCluster {
     X3,
 }
 
-/// Distinguishes Wikidata item entities (Q-prefixed, namespace 0) from
-/// property entities (P-prefixed, namespace 120). The two flavours map to
-/// different `wbt_*` term-store tables and column names; keeping the
-/// per-variant lookup colocated makes `add_wikidata_labels_for_namespace`
-/// total over its inputs (no more `_ => return None` filter arms).
+/// Distinguishes Wikidata item entities (Q-prefixed, namespace 0),
+/// property entities (P-prefixed, namespace 120) and lexeme entities
+/// (L-prefixed, namespace 146). Items and properties map to different
+/// `wbt_*` term-store tables and column names; lexemes have lemmas instead
+/// of terms, which are not in the term store, so the term-store lookups
+/// return `None` for them. Forms and senses (`L7-F1`, `L7-S1`) live on the
+/// page of their lexeme.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum WikidataEntityType {
     Item,
     Property,
+    Lexeme,
 }
 
 impl WikidataEntityType {
+    pub const ALL: [Self; 3] = [Self::Item, Self::Property, Self::Lexeme];
+
     pub const fn prefix(self) -> &'static str {
         match self {
             Self::Item => "Q",
             Self::Property => "P",
+            Self::Lexeme => "L",
+        }
+    }
+    pub const fn namespace_id(self) -> NamespaceID {
+        match self {
+            Self::Item => 0,
+            Self::Property => 120,
+            Self::Lexeme => 146,
+        }
+    }
+    pub fn from_namespace_id(namespace_id: NamespaceID) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|entity_type| entity_type.namespace_id() == namespace_id)
+    }
+    /// The type of an entity ID like `Q42`, `P31`, `L7` or `L7-F1`
+    pub fn from_entity_id(entity_id: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|entity_type| entity_id.starts_with(entity_type.prefix()))
+    }
+    /// The ID of the entity whose page holds `entity_id`: `L7` for the form
+    /// `L7-F1`, otherwise the ID itself
+    pub fn page_entity_id(entity_id: &str) -> &str {
+        match entity_id.split_once('-') {
+            Some((lexeme_id, _sub_id)) if entity_id.starts_with('L') => lexeme_id,
+            _ => entity_id,
         }
     }
-    pub const fn terms_table(self) -> &'static str {
+    pub const fn terms_table(self) -> Option<&'static str> {
         match self {
-            Self::Item => "wbt_item_terms",
-            Self::Property => "wbt_property_terms",
+            Self::Item => Some("wbt_item_terms"),
+            Self::Property => Some("wbt_property_terms"),
+            Self::Lexeme => None,
         }
     }
-    pub const fn id_field(self) -> &'static str {
+    pub const fn id_field(self) -> Option<&'static str> {
         match self {
-            Self::Item => "wbit_item_id",
-            Self::Property => "wbpt_property_id",
+            Self::Item => Some("wbit_item_id"),
+            Self::Property => Some("wbpt_property_id"),
+            Self::Lexeme => None,
         }
     }
-    pub const fn term_in_lang_field(self) -> &'static str {
+    pub const fn term_in_lang_field(self) -> Option<&'static str> {
         match self {
-            Self::Item => "wbit_term_in_lang_id",
-            Self::Property => "wbpt_term_in_lang_id",
+            Self::Item => Some("wbit_term_in_lang_id"),
+            Self::Property => Some("wbpt_term_in_lang_id"),
+            Self::Lexeme => None,
         }
     }
 }
 
+/// Languages to load Wikidata terms in: a fallback chain for the label and
+/// description (e.g. `de,en,mul`), and languages with their own columns
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TermLanguages {
+    pub fallback: Vec<String>,
+    pub columns: Vec<String>,
+}
+
+impl TermLanguages {
+    /// All languages, without duplicates
+    pub fn all(&self) -> Vec<String> {
+        let mut ret: Vec<String> = vec![];
+        for language in self.fallback.iter().chain(&self.columns) {
+            if !ret.contains(language) {
+                ret.push(language.to_owned());
+            }
+        }
+        ret
+    }
+}
+
 #[derive(Debug)]
 pub struct PageList {
     wiki: RwLock<Option<String>>,
@@ -516,7 +572,7 @@ impl PageList {
 
     pub async fn load_missing_metadata(
         &self,
-        wikidata_language: Option<String>,
+        wikidata_languages: Option<TermLanguages>,
         platform: &dyn QueryContext,
     ) -> Result<()> {
         Platform::profile("begin load_missing_metadata", None);
@@ -525,7 +581,7 @@ impl PageList {
         Platform::profile("after load_missing_page_metadata", None);
 
         // All done
-        if !self.is_wikidata() || wikidata_language.is_none() {
+        if !self.is_wikidata() || wikidata_languages.is_none() {
             return Ok(());
         }
 
@@ -534,38 +590,46 @@ impl PageList {
             return Ok(());
         }
 
-        if let Some(wikidata_language) = wikidata_language {
-            self.add_wikidata_labels_for_namespace(
-                0,
-                WikidataEntityType::Item,
-                &wikidata_language,
-                platform,
-            )
-            .await?;
-            self.add_wikidata_labels_for_namespace(
-                120,
-                WikidataEntityType::Property,
-                &wikidata_language,
-                platform,
-            )
-            .await?;
+        if let Some(wikidata_languages) = wikidata_languages {
+            let languages = wikidata_languages.all();
+            for entity_type in WikidataEntityType::ALL {
+                self.add_wikidata_labels_for_namespace(entity_type, &languages, platform)
+                    .await?;
+            }
+            let entries = self
+                .drain_into_vec()
+                .into_iter()
+                .map(|mut entry| {
+                    entry.set_wikidata_label_from_terms(&wikidata_languages.fallback);
+                    entry
+                })
+                .collect();
+            self.set_entries(entries);
         }
         Platform::profile("end load_missing_metadata", None);
         Ok(())
     }
 
+    /// Lexemes have no terms; their lemmas are loaded with the entity JSON
     async fn add_wikidata_labels_for_namespace(
         &self,
-        namespace_id: NamespaceID,
         entity_type: WikidataEntityType,
-        wikidata_language: &str,
+        wikidata_languages: &[String],
         platform: &dyn QueryContext,
     ) -> Result<()> {
+        let (Some(table), Some(field_name), Some(term_in_lang_id)) = (
+            entity_type.terms_table(),
+            entity_type.id_field(),
+            entity_type.term_in_lang_field(),
+        ) else {
+            return Ok(());
+        };
+        if wikidata_languages.is_empty() {
+            return Ok(());
+        }
         // wbt_ done
         let prefix = entity_type.prefix();
-        let table = entity_type.terms_table();
-        let field_name = entity_type.id_field();
-        let term_in_lang_id = entity_type.term_in_lang_field();
+        let namespace_id = entity_type.namespace_id();
         let batches: Vec<SQLtuple> = self
             .to_sql_batches_namespace(PAGE_BATCH_SIZE, namespace_id)
             .iter_mut()
@@ -585,7 +649,10 @@ impl PageList {
                     return None;
                 }
                 let id_placeholders = vec!["?"; id_params.len()].join(",");
-                sql_batch.1 = std::iter::once(MyValue::Bytes(wikidata_language.to_owned().into()))
+                let language_placeholders = vec!["?"; wikidata_languages.len()].join(",");
+                sql_batch.1 = wikidata_languages
+                    .iter()
+                    .map(|language| MyValue::Bytes(language.to_owned().into()))
                     .chain(id_params)
                     .collect();
                 sql_batch.0 = format!(
@@ -595,12 +662,13 @@ impl PageList {
 					 (case when wbtl_type_id = 1 then 'label'
              			when wbtl_type_id = 2 then 'description'
                 		when wbtl_type_id = 3 then 'alias'
-                		end) AS term_type
+                		end) AS term_type,
+					 wbxl_language AS term_language
 					 FROM {table}
 					 INNER JOIN wbt_term_in_lang ON {term_in_lang_id} = wbtl_id
 					 INNER JOIN wbt_type ON wbtl_type_id = wby_id
 					 INNER JOIN wbt_text_in_lang ON wbtl_text_in_lang_id = wbxl_id
-					 INNER JOIN wbt_text ON wbxl_text_id = wbx_id AND wbxl_language=?
+					 INNER JOIN wbt_text ON wbxl_text_id = wbx_id AND wbxl_language IN ({language_placeholders})
 					 WHERE {field_name} IN ({id_placeholders})"
                 );
                 Some(sql_batch.to_owned())
@@ -608,15 +676,14 @@ impl PageList {
             .collect::<Vec<SQLtuple>>();
 
         let the_f = |row: my::Row, entry: &mut PageListEntry| {
-            if let Ok((_page_title, _page_namespace, term_text, term_type)) =
-                my::from_row_opt::<(Vec<u8>, NamespaceID, Vec<u8>, Vec<u8>)>(row)
+            if let Ok((_page_title, _page_namespace, term_text, term_type, term_language)) =
+                my::from_row_opt::<(Vec<u8>, NamespaceID, Vec<u8>, Vec<u8>, Vec<u8>)>(row)
             {
-                let term_text = String::from_utf8_lossy(&term_text).into_owned();
-                match String::from_utf8_lossy(&term_type).into_owned().as_str() {
-                    "label" => entry.set_wikidata_label(Some(term_text)),
-                    "description" => entry.set_wikidata_description(Some(term_text)),
-                    _ => {}
-                }
+                entry.add_wikidata_term(
+                    &String::from_utf8_lossy(&term_language),
+                    &String::from_utf8_lossy(&term_type),
+                    String::from_utf8_lossy(&term_text).into_owned(),
+                );
             }
         };
         let col_title = 0;
@@ -691,7 +758,9 @@ impl PageList {
             return Ok(());
         }
         Platform::profile("PageList::convert_from_wikidata START", None);
-        let batches = self.to_sql_batches(PAGE_BATCH_SIZE*2)
+        // Only items have sitelinks; `substr(page_title,2)` would also match
+        // property P7 and lexeme L7 to the sitelinks of item Q7
+        let batches = self.to_sql_batches_namespace(PAGE_BATCH_SIZE*2, WikidataEntityType::Item.namespace_id())
             .par_iter_mut()
             .map(|sql|{
                 sql.0 = "SELECT ips_site_page FROM wb_items_per_site,page WHERE ips_item_id=substr(page_title,2)*1 AND ".to_owned()+&sql.0+" AND ips_site_id=?";
@@ -1144,4 +1213,21 @@ mod tests {
         pl.regexp_filter("Magnus.*");
         assert_eq!(pl.len(), 2);
     }
+
+    #[test]
+    fn test_wikidata_entity_type() {
+        assert_eq!(
+            WikidataEntityType::from_entity_id("L7-F1"),
+            Some(WikidataEntityType::Lexeme)
+        );
+        assert_eq!(WikidataEntityType::from_entity_id("X7"), None);
+        assert_eq!(
+            WikidataEntityType::from_namespace_id(120),
+            Some(WikidataEntityType::Property)
+        );
+        assert_eq!(WikidataEntityType::from_namespace_id(1), None);
+        assert_eq!(WikidataEntityType::page_entity_id("L7-S2"), "L7");
+        assert_eq!(WikidataEntityType::page_entity_id("Q7"), "Q7");
+        assert_eq!(WikidataEntityType::Lexeme.terms_table(), None);
+    }
 }
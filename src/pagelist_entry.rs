@@ -2,6 +2,7 @@ use rand::seq::SliceRandom;
 use rayon::slice::ParallelSliceMut;
 use serde_json::{Value, json};
 use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
 use wikimisc::mediawiki::api::NamespaceID;
 use wikimisc::mediawiki::title::Title;
@@ -172,6 +173,52 @@ impl FileUsage {
     pub const fn namespace_name(&self) -> &String {
         &self.namespace_name
     }
+
+    pub const fn page_id(&self) -> usize {
+        self.page_id
+    }
+}
+
+//________________________________________________________________________________________________________________________
+
+/// Label, description and aliases of a Wikidata entity in one language
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct WikidataTerms {
+    pub label: Option<String>,
+    pub description: Option<String>,
+    pub aliases: Vec<String>,
+}
+
+impl WikidataTerms {
+    /// Adds a term as returned by the term store (`label`, `description` or `alias`)
+    pub fn add(&mut self, term_type: &str, text: String) {
+        match term_type {
+            "label" => self.label = Some(text),
+            "description" => self.description = Some(text),
+            "alias" => self.aliases.push(text),
+            _ => {}
+        }
+    }
+}
+
+/// Lemma, language and lexical category of a Wikidata lexeme
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Lexeme {
+    /// Spelling variants are joined by ` / `, as on Wikidata
+    pub lemma: String,
+    pub language: Option<String>,
+    pub lexical_category: Option<String>,
+}
+
+impl Lexeme {
+    /// Checks the language and lexical category against lists of items;
+    /// an empty list matches anything
+    pub fn matches(&self, languages: &[String], lexical_categories: &[String]) -> bool {
+        let matches = |value: &Option<String>, items: &[String]| {
+            items.is_empty() || value.as_ref().is_some_and(|v| items.contains(v))
+        };
+        matches(&self.language, languages) && matches(&self.lexical_category, lexical_categories)
+    }
 }
 
 //________________________________________________________________________________________________________________________
@@ -223,6 +270,13 @@ pub struct PageListEntry {
     defaultsort: Option<String>,
     coordinates: Option<wikimisc::lat_lon::LatLon>,
     file_info: Option<FileInfo>,
+    /// Wikidata statement values by property ID, for `wikidata_values`
+    wikidata_values: Option<HashMap<String, Vec<String>>>,
+    /// Wikidata terms by language, for `wikidata_languages` and the label fallback chain
+    wikidata_terms: Option<HashMap<String, WikidataTerms>>,
+    lexeme: Option<Lexeme>,
+    /// Sitelink titles of the item by site ID, for `sitelink_matrix`
+    sitelinks: Option<HashMap<String, String>>,
 }
 
 impl Hash for PageListEntry {
@@ -256,6 +310,10 @@ impl PageListEntry {
             link_count: None,
             sitelink_count: None,
             file_info: None,
+            wikidata_values: None,
+            wikidata_terms: None,
+            lexeme: None,
+            sitelinks: None,
             wikidata_label: None,
             wikidata_description: None,
             redlink_count: None,
@@ -312,6 +370,68 @@ impl PageListEntry {
         self.wikidata_label = wikidata_label_option;
     }
 
+    /// Values of `property` (e.g. `P31`) for this entry's item, if they were loaded
+    pub fn get_wikidata_values(&self, property: &str) -> Option<&[String]> {
+        self.wikidata_values
+            .as_ref()
+            .and_then(|values| values.get(property))
+            .map(|values| values.as_slice())
+    }
+
+    pub fn set_wikidata_values(&mut self, wikidata_values: Option<HashMap<String, Vec<String>>>) {
+        self.wikidata_values = wikidata_values;
+    }
+
+    pub fn get_wikidata_terms(&self, language: &str) -> Option<&WikidataTerms> {
+        self.wikidata_terms.as_ref()?.get(language)
+    }
+
+    pub fn add_wikidata_term(&mut self, language: &str, term_type: &str, text: String) {
+        self.wikidata_terms
+            .get_or_insert_with(HashMap::new)
+            .entry(language.to_string())
+            .or_default()
+            .add(term_type, text);
+    }
+
+    /// Sets the label and the description from the first language in
+    /// `languages` that has one, from the loaded terms
+    pub fn set_wikidata_label_from_terms(&mut self, languages: &[String]) {
+        let Some(terms) = &self.wikidata_terms else {
+            return;
+        };
+        let label = languages
+            .iter()
+            .find_map(|language| terms.get(language)?.label.clone());
+        let description = languages
+            .iter()
+            .find_map(|language| terms.get(language)?.description.clone());
+        if label.is_some() {
+            self.wikidata_label = label;
+        }
+        if description.is_some() {
+            self.wikidata_description = description;
+        }
+    }
+
+    pub const fn get_lexeme(&self) -> Option<&Lexeme> {
+        self.lexeme.as_ref()
+    }
+
+    pub fn set_lexeme(&mut self, lexeme: Option<Lexeme>) {
+        self.lexeme = lexeme;
+    }
+
+    /// The title of the sitelink to `site`, if sitelinks were loaded and
+    /// the item has one
+    pub fn get_sitelink(&self, site: &str) -> Option<&str> {
+        self.sitelinks.as_ref()?.get(site).map(|s| s.as_str())
+    }
+
+    pub fn set_sitelinks(&mut self, sitelinks: Option<HashMap<String, String>>) {
+        self.sitelinks = sitelinks;
+    }
+
     pub fn get_wikidata_item(&self) -> Option<String> {
         self.wikidata_item
             .as_ref()
@@ -667,6 +787,66 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_wikidata_values_get_set() {
+        let mut entry = PageListEntry::new(Title::new("Q42", 0));
+        assert_eq!(entry.get_wikidata_values("P31"), None);
+        let values = HashMap::from([("P31".to_string(), vec!["Q5".to_string()])]);
+        entry.set_wikidata_values(Some(values));
+        assert_eq!(
+            entry.get_wikidata_values("P31"),
+            Some(&["Q5".to_string()][..])
+        );
+        assert_eq!(entry.get_wikidata_values("P569"), None);
+    }
+
+    #[test]
+    fn test_sitelinks_get_set() {
+        let mut entry = PageListEntry::new(Title::new("Q64", 0));
+        assert_eq!(entry.get_sitelink("dewiki"), None);
+        let sitelinks = HashMap::from([("dewiki".to_string(), "Berlin".to_string())]);
+        entry.set_sitelinks(Some(sitelinks));
+        assert_eq!(entry.get_sitelink("dewiki"), Some("Berlin"));
+        assert_eq!(entry.get_sitelink("enwiki"), None);
+    }
+
+    #[test]
+    fn test_lexeme_matches() {
+        let lexeme = Lexeme {
+            lemma: "run".to_string(),
+            language: Some("Q1860".to_string()),
+            lexical_category: Some("Q24905".to_string()),
+        };
+        let items = |v: &[&str]| v.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+        assert!(lexeme.matches(&[], &[]));
+        assert!(lexeme.matches(&items(&["Q1860"]), &items(&["Q1084", "Q24905"])));
+        assert!(!lexeme.matches(&items(&["Q188"]), &[]));
+        assert!(!Lexeme::default().matches(&[], &items(&["Q24905"])));
+    }
+
+    #[test]
+    fn test_wikidata_terms_fallback() {
+        let mut entry = PageListEntry::new(Title::new("Q42", 0));
+        entry.add_wikidata_term("en", "label", "Douglas Adams".to_string());
+        entry.add_wikidata_term("en", "description", "English writer".to_string());
+        entry.add_wikidata_term("de", "description", "britischer Schriftsteller".to_string());
+        entry.add_wikidata_term("de", "alias", "DNA".to_string());
+        entry.add_wikidata_term("de", "alias", "Douglas Noël Adams".to_string());
+        assert_eq!(entry.get_wikidata_terms("de").unwrap().aliases.len(), 2);
+        assert_eq!(entry.get_wikidata_terms("fr"), None);
+
+        let languages = vec!["de".to_string(), "en".to_string(), "mul".to_string()];
+        entry.set_wikidata_label_from_terms(&languages);
+        assert_eq!(
+            entry.get_wikidata_label(),
+            Some("Douglas Adams".to_string())
+        );
+        assert_eq!(
+            entry.get_wikidata_description(),
+            Some("britischer Schriftsteller".to_string())
+        );
+    }
+
     #[test]
     fn test_page_timestamp_get_set() {
         let mut entry = PageListEntry::new(Title::new("Test", 0));
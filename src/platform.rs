@@ -12,22 +12,35 @@ use crate::datasource::sparql::SourceSparql;
 use crate::datasource::wikidata::SourceWikidata;
 use crate::form_parameters::FormParameters;
 use crate::pagelist::PageList;
-use crate::pagelist_entry::PageListSort;
+use crate::pagelist_entry::{PageListEntry, PageListSort};
 use crate::render::Render;
+use crate::render::geojson::RenderGeoJSON;
+use crate::render::gpx::RenderGPX;
 use crate::render::html::RenderHTML;
 use crate::render::json::RenderJSON;
 use crate::render::jsonl::RenderJSONL;
 use crate::render::kml::RenderKML;
 use crate::render::pagepile::RenderPagePile;
+use crate::render::parquet::RenderParquet;
 use crate::render::plaintext::RenderPlainText;
+use crate::render::quickstatements::RenderQuickStatements;
+use crate::render::rdf::{RdfSyntax, RenderRDF};
+use crate::render::stats::RenderStats;
+use crate::render::template::RenderTemplate;
 use crate::render::tsv::RenderTSV;
+use crate::render::wdfist::RenderWDfist;
 use crate::render::wikitext::RenderWiki;
+use crate::render::xlsx::RenderXLSX;
+use crate::result_cache::{CachedResult, Cursor, Pagination};
+use crate::snapshot::{MAX_SNAPSHOT_ENTRIES, Snapshot};
 use crate::wdfist::WDfist;
+use crate::wikidata_entities::EntityLoader;
 use anyhow::{Result, anyhow};
 use futures::stream::{StreamExt, iter};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::{Arc, RwLock};
 use std::time::{Duration, SystemTime};
+use tokio::sync::Mutex as TokioMutex;
 use tracing::{debug, instrument};
 use wikimisc::mediawiki::api::NamespaceID;
 
@@ -56,6 +69,8 @@ pub struct MyResponse {
     /// Raw HTTP status code; defaults to 200 via `MyResponse::ok`. Use
     /// `MyResponse::with_status` for error responses.
     pub status: u16,
+    /// Body of binary formats (e.g. Parquet); sent instead of `s` when set.
+    pub binary: Option<Vec<u8>>,
 }
 
 impl MyResponse {
@@ -65,6 +80,25 @@ impl MyResponse {
             s: s.into(),
             content_type,
             status: 200,
+            binary: None,
+        }
+    }
+
+    /// Construct a 200 OK response with a binary body.
+    pub const fn ok_binary(bytes: Vec<u8>, content_type: ContentType) -> Self {
+        Self {
+            s: String::new(),
+            content_type,
+            status: 200,
+            binary: Some(bytes),
+        }
+    }
+
+    /// The raw response body, binary or text.
+    pub fn body(&self) -> &[u8] {
+        match &self.binary {
+            Some(bytes) => bytes,
+            None => self.s.as_bytes(),
         }
     }
 }
@@ -75,6 +109,8 @@ pub struct Platform {
     pub(super) state: Arc<AppState>,
     pub(super) result: Option<PageList>,
     pub psid: Option<u64>,
+    /// ID of the result snapshot stored (or loaded) for this run, if any.
+    pub snapshot_id: Option<u64>,
     pub(super) existing_labels: RwLock<HashSet<String>>,
     pub(super) combination: Combination,
     pub(super) output_redlinks: bool,
@@ -83,6 +119,12 @@ pub struct Platform {
     pub(super) wdfist_result: Option<serde_json::Value>,
     pub(super) warnings: RwLock<Vec<String>>,
     pub(super) namespace_case_sensitivity_cache: RwLock<HashMap<(String, NamespaceID), bool>>,
+    /// Cached result page requested via `cursor=`, instead of `result`
+    pub(super) cached_page: Option<(Cursor, CachedResult)>,
+    /// The page of the result being rendered, if paginated
+    pub(super) pagination: RwLock<Option<Pagination>>,
+    /// Wikidata entity JSON loaded for this query
+    pub(super) wikidata_entities: TokioMutex<EntityLoader>,
 }
 
 impl Platform {
@@ -92,6 +134,7 @@ impl Platform {
             state,
             result: None,
             psid: None,
+            snapshot_id: None,
             existing_labels: RwLock::new(HashSet::new()),
             combination: Combination::None,
             output_redlinks: false,
@@ -100,6 +143,9 @@ impl Platform {
             wdfist_result: None,
             warnings: RwLock::new(vec![]),
             namespace_case_sensitivity_cache: RwLock::new(HashMap::new()),
+            cached_page: None,
+            pagination: RwLock::new(None),
+            wikidata_entities: TokioMutex::new(EntityLoader::default()),
         }
     }
 
@@ -112,6 +158,10 @@ impl Platform {
         Ok(())
     }
 
+    pub fn pagination(&self) -> Option<Pagination> {
+        self.pagination.read().ok().and_then(|p| *p)
+    }
+
     pub fn label_exists(&self, label: &str) -> bool {
         match self.existing_labels.read() {
             Ok(el) => el.contains(label),
@@ -173,7 +223,6 @@ impl Platform {
     }
 
     #[instrument(skip_all, err(level = tracing::Level::INFO))]
-    #[allow(clippy::default_constructed_unit_structs)]
     pub async fn run(&mut self) -> Result<()> {
         Platform::profile("begin run", None);
         let start_time = SystemTime::now();
@@ -183,16 +232,7 @@ impl Platform {
         // `can_run` gate; the ones whose gates pass contribute a name and
         // a future to the parallel batch below. Order matters because the
         // names line up with `source_results` for `combine_results`.
-        let mut sources: Vec<Box<dyn DataSource + Send>> = vec![
-            Box::new(SourceDatabase::new(
-                SourceDatabaseParameters::db_params(self).await,
-            )),
-            Box::new(SourceSparql::default()),
-            Box::new(SourceManual::default()),
-            Box::new(SourcePagePile::default()),
-            Box::new(SourceSearch::default()),
-            Box::new(SourceWikidata::default()),
-        ];
+        let mut sources = self.primary_sources().await;
         // Sitelinks is a fallback that only runs when no other source applies.
         // It is declared up here (before `futures`) so its drop order is
         // strictly after `futures` — a future returned by its `.run(self)`
@@ -267,6 +307,47 @@ impl Platform {
         Ok(())
     }
 
+    /// The primary data sources, in the order `run()` evaluates them.
+    #[allow(clippy::default_constructed_unit_structs)]
+    async fn primary_sources(&self) -> Vec<Box<dyn DataSource + Send>> {
+        vec![
+            Box::new(SourceDatabase::new(
+                SourceDatabaseParameters::db_params(self).await,
+            )),
+            Box::new(SourceSparql::default()),
+            Box::new(SourceManual::default()),
+            Box::new(SourcePagePile::default()),
+            Box::new(SourceSearch::default()),
+            Box::new(SourceWikidata::default()),
+        ]
+    }
+
+    /// Describes what `run()` would do with the current parameters, without
+    /// running any data source.
+    pub async fn explain(&self) -> serde_json::Value {
+        let mut sources: Vec<String> = self
+            .primary_sources()
+            .await
+            .iter()
+            .filter(|source| source.can_run(self))
+            .map(|source| source.name())
+            .collect();
+        let s_sitelinks = SourceSitelinks::new();
+        if sources.is_empty() && s_sitelinks.can_run(self) {
+            sources.push(s_sitelinks.name());
+        }
+        let combination = self.get_combination(&sources);
+        let parameters: BTreeMap<&String, &String> = self.form_parameters.params.iter().collect();
+        json!({
+            "wiki": self.get_main_wiki(),
+            "sources": sources,
+            "combination": combination.to_string(),
+            "wdfist": self.has_param("wdf_main"),
+            "format": self.get_param_default("format", "html"),
+            "parameters": parameters,
+        })
+    }
+
     pub fn profile(label: &str, num: Option<usize>) {
         debug!(num, "{}", label);
     }
@@ -278,9 +359,13 @@ impl Platform {
     pub async fn get_response(&self) -> Result<MyResponse> {
         // Shortcut: WDFIST
         if let Some(j) = &self.wdfist_result {
-            return Ok(self
-                .state
-                .output_json(j, self.form_parameters.params.get("callback")));
+            return Ok(RenderWDfist::response(self, j));
+        }
+
+        // Shortcut: page of a cached result
+        if let Some((cursor, cached)) = &self.cached_page {
+            let pages = self.page_from_cache(cursor, cached)?;
+            return self.render(&cached.wiki, pages).await;
         }
 
         let result = match &self.result {
@@ -304,20 +389,103 @@ impl Platform {
         .await
         .map_err(|e| anyhow!("Sort task failed: {e}"))?;
         self.apply_results_limit(&mut pages);
+        let pages = self.paginate(&wiki, pages)?;
+        self.render(&wiki, pages).await
+    }
 
+    async fn render(&self, wiki: &str, pages: Vec<PageListEntry>) -> Result<MyResponse> {
         match self.get_param_blank("format").as_str() {
-            "wiki" => RenderWiki::new().response(self, &wiki, pages).await,
-            "csv" => RenderTSV::new(",").response(self, &wiki, pages).await,
-            "tsv" => RenderTSV::new("\t").response(self, &wiki, pages).await,
-            "json" => RenderJSON::new().response(self, &wiki, pages).await,
-            "jsonl" => RenderJSONL::new().response(self, &wiki, pages).await,
-            "pagepile" => RenderPagePile::new().response(self, &wiki, pages).await,
-            "kml" => RenderKML::new().response(self, &wiki, pages).await,
-            "plain" => RenderPlainText::new().response(self, &wiki, pages).await,
-            _ => RenderHTML::new().response(self, &wiki, pages).await,
+            "wiki" => RenderWiki::new().response(self, wiki, pages).await,
+            "csv" => RenderTSV::new(",").response(self, wiki, pages).await,
+            "tsv" => RenderTSV::new("\t").response(self, wiki, pages).await,
+            "xlsx" => RenderXLSX::new().response(self, wiki, pages).await,
+            "json" => RenderJSON::new().response(self, wiki, pages).await,
+            "jsonl" => RenderJSONL::new().response(self, wiki, pages).await,
+            "pagepile" => RenderPagePile::new().response(self, wiki, pages).await,
+            "parquet" => RenderParquet::new().response(self, wiki, pages).await,
+            "kml" => RenderKML::new().response(self, wiki, pages).await,
+            "geojson" => RenderGeoJSON::new().response(self, wiki, pages).await,
+            "gpx" => RenderGPX::new().response(self, wiki, pages).await,
+            "plain" => RenderPlainText::new().response(self, wiki, pages).await,
+            "template" => RenderTemplate::new().response(self, wiki, pages).await,
+            "stats" => RenderStats::new().response(self, wiki, pages).await,
+            "quickstatements" => {
+                RenderQuickStatements::new()
+                    .response(self, wiki, pages)
+                    .await
+            }
+            "ntriples" => {
+                RenderRDF::new(RdfSyntax::NTriples)
+                    .response(self, wiki, pages)
+                    .await
+            }
+            "turtle" => {
+                RenderRDF::new(RdfSyntax::Turtle)
+                    .response(self, wiki, pages)
+                    .await
+            }
+            _ => RenderHTML::new().response(self, wiki, pages).await,
         }
     }
 
+    /// Link to the page of the cached result that `cursor` points to,
+    /// keeping the output parameters of this request.
+    pub fn cursor_link(&self, cursor: &Cursor) -> String {
+        let mut form_parameters = self.form_parameters.clone();
+        form_parameters.params.remove("offset");
+        form_parameters.params.remove("limit");
+        form_parameters.set_param("cursor", &cursor.to_param());
+        "https://petscan.wmflabs.org/?".to_string() + &form_parameters.to_string()
+    }
+
+    /// Serves a page of a cached result instead of running the query.
+    pub fn load_cached_page(&mut self, cursor: Cursor) -> Result<()> {
+        let cached = self
+            .state
+            .get_cached_result(cursor.key)?
+            .ok_or_else(|| anyhow!("Cursor has expired, please re-run the query"))?;
+        self.cached_page = Some((cursor, cached));
+        Ok(())
+    }
+
+    /// Replaces the result with the titles of a stored snapshot, so
+    /// `get_response` can render it without running any data source.
+    pub fn load_snapshot(&mut self, snapshot: &Snapshot) {
+        self.result = Some(snapshot.to_pagelist());
+        self.snapshot_id = Some(snapshot.id);
+        self.query_time = None;
+    }
+
+    /// Stores the current result as a snapshot of `self.psid`, if it has at
+    /// most `MAX_SNAPSHOT_ENTRIES` entries. Must be called after `run()` and
+    /// before `get_response()`, which drains the result.
+    pub async fn create_snapshot(&mut self) -> Result<u64> {
+        let psid = self
+            .psid
+            .ok_or_else(|| anyhow!("Platform::create_snapshot: No PSID"))?;
+        let result = self
+            .result
+            .as_ref()
+            .ok_or_else(|| anyhow!("Platform::create_snapshot: No result"))?;
+        let wiki = result
+            .wiki()
+            .ok_or_else(|| anyhow!("Platform::create_snapshot: No wiki in result"))?;
+        if result.len() > MAX_SNAPSHOT_ENTRIES {
+            return Err(anyhow!(
+                "Result too large for a snapshot ({} entries, at most {MAX_SNAPSHOT_ENTRIES})",
+                result.len()
+            ));
+        }
+        let titles: Vec<_> = result
+            .as_vec()
+            .into_iter()
+            .map(|e| e.title().to_owned())
+            .collect();
+        let snapshot_id = self.state.create_snapshot(psid, &wiki, &titles).await?;
+        self.snapshot_id = Some(snapshot_id);
+        Ok(snapshot_id)
+    }
+
     pub const fn result(&self) -> &Option<PageList> {
         &self.result
     }
@@ -473,6 +641,23 @@ mod tests {
         assert!(p.result().is_none());
     }
 
+    #[test]
+    fn test_load_snapshot_sets_result() {
+        let mut p = make_platform(vec![]);
+        let snapshot = Snapshot {
+            id: 7,
+            psid: 123,
+            wiki: "enwiki".to_string(),
+            created: "2024-01-01 00:00:00".to_string(),
+            titles: vec![Title::new("Foo", 0), Title::new("Bar", 14)],
+        };
+        p.load_snapshot(&snapshot);
+        assert_eq!(p.snapshot_id, Some(7));
+        let result = p.result().as_ref().unwrap();
+        assert_eq!(result.wiki(), Some("enwiki".to_string()));
+        assert_eq!(result.len(), 2);
+    }
+
     // ─── integration tests ───────────────────────────────────────────────────
     // All tests below this point require a live MySQL replica + the live
     // Wikidata API (they call `run_psid` / `check_results_for_psid*`, which
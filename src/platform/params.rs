@@ -1,4 +1,10 @@
+use crate::pagelist::TermLanguages;
 use crate::platform::Platform;
+use crate::render::{
+    is_term_language, parse_sitelink_column, parse_term_column, sitelink_placeholders,
+    term_placeholders,
+};
+use crate::wikidata_entities::{parse_property_list, property_placeholders};
 use wikimisc::mediawiki::title::Title;
 
 impl Platform {
@@ -55,6 +61,116 @@ impl Platform {
         }
     }
 
+    /// Returns the columns requested via the `columns` parameter, in order
+    pub fn requested_columns(&self) -> Vec<String> {
+        self.get_param_as_vec("columns", ",")
+    }
+
+    /// Checks if `column` was requested via the `columns` parameter, or
+    /// is a placeholder in the `row_template` of `format=template`
+    pub fn has_column(&self, column: &str) -> bool {
+        self.requested_columns()
+            .iter()
+            .any(|c| c.eq_ignore_ascii_case(column))
+            || self.uses_template_placeholder(column)
+    }
+
+    /// Checks if the `row_template` of `format=template` contains
+    /// `{prefix...`
+    pub fn uses_template_placeholder(&self, prefix: &str) -> bool {
+        self.get_param_blank("format") == "template"
+            && self
+                .get_param_blank("row_template")
+                .contains(&format!("{{{prefix}"))
+    }
+
+    /// Returns the Wikidata properties whose values were requested, via
+    /// `wikidata_values`, as property columns (e.g. `columns=title,P31`),
+    /// or as `row_template` placeholders (e.g. `{P31}`)
+    pub fn wikidata_value_properties(&self) -> Vec<String> {
+        let mut ret = parse_property_list(&self.get_param_blank("wikidata_values"));
+        let mut more = parse_property_list(&self.requested_columns().join(","));
+        if self.get_param_blank("format") == "template" {
+            more.extend(property_placeholders(&self.get_param_blank("row_template")));
+        }
+        for property in more {
+            if !ret.contains(&property) {
+                ret.push(property);
+            }
+        }
+        ret
+    }
+
+    /// Returns the languages of `wikidata_languages`, each of which gets
+    /// label, description and aliases columns
+    pub fn wikidata_column_languages(&self) -> Vec<String> {
+        Self::parse_language_list(&self.get_param_blank("wikidata_languages"))
+    }
+
+    /// Returns the languages to load Wikidata terms in: the fallback chain of
+    /// `wikidata_label_language` (e.g. `de,en,mul`; the interface language by
+    /// default), plus the languages of `wikidata_languages` and of term
+    /// columns like `label_de` in `columns` or the `row_template`
+    pub fn wikidata_term_languages(&self) -> TermLanguages {
+        let fallback = Self::parse_language_list(&self.get_param_default(
+            "wikidata_label_language",
+            &self.get_param_default("interface_language", "en"),
+        ));
+        let mut term_columns = self.requested_columns();
+        if self.get_param_blank("format") == "template" {
+            term_columns.extend(term_placeholders(&self.get_param_blank("row_template")));
+        }
+        let mut columns = self.wikidata_column_languages();
+        for column in term_columns.iter().map(|c| c.to_lowercase()) {
+            let language = parse_term_column(&column).map(|(_kind, language)| language);
+            if let Some(language) = language.filter(|l| !columns.iter().any(|c| c == l)) {
+                columns.push(language.to_string());
+            }
+        }
+        TermLanguages { fallback, columns }
+    }
+
+    fn parse_language_list(s: &str) -> Vec<String> {
+        let mut ret: Vec<String> = vec![];
+        for language in s.split(',').map(|l| l.trim().to_lowercase()) {
+            if is_term_language(&language) && !ret.contains(&language) {
+                ret.push(language);
+            }
+        }
+        ret
+    }
+
+    /// Returns the sites of the sitelink matrix: those of `sitelink_matrix`
+    /// (e.g. `dewiki,frwiki`), plus those of sitelink columns like
+    /// `sitelink_dewiki` in `columns` or the `row_template`
+    pub fn sitelink_matrix_sites(&self) -> Vec<String> {
+        let mut columns: Vec<String> = self
+            .get_param_blank("sitelink_matrix")
+            .split(',')
+            .map(|site| format!("sitelink_{}", site.trim().to_lowercase()))
+            .collect();
+        columns.extend(self.requested_columns().iter().map(|c| c.to_lowercase()));
+        if self.get_param_blank("format") == "template" {
+            columns.extend(sitelink_placeholders(&self.get_param_blank("row_template")));
+        }
+        let mut ret: Vec<String> = vec![];
+        for site in columns.iter().filter_map(|c| parse_sitelink_column(c)) {
+            if !ret.iter().any(|s| s == site) {
+                ret.push(site.to_string());
+            }
+        }
+        ret
+    }
+
+    /// Checks if lemma, language and lexical category of lexemes should be
+    /// output, via `lexeme_data` or their columns
+    pub fn lexeme_data_requested(&self) -> bool {
+        self.has_param("lexeme_data")
+            || ["lemma", "lexeme_language", "lexical_category"]
+                .iter()
+                .any(|column| self.has_column(column))
+    }
+
     /// Returns a `usize` parsed from the given parameter, or `None`
     pub fn usize_option_from_param(&self, key: &str) -> Option<usize> {
         self.get_param(key)?.parse::<usize>().ok()
@@ -156,6 +272,80 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_has_column() {
+        let p = make_platform(vec![("columns", "title, Wikidata_Label,img_size")]);
+        assert!(p.has_column("title"));
+        assert!(p.has_column("wikidata_label"));
+        assert!(p.has_column("img_size"));
+        assert!(!p.has_column("size"));
+        assert!(!make_platform(vec![]).has_column("title"));
+    }
+
+    #[test]
+    fn test_has_column_from_row_template() {
+        let p = make_platform(vec![
+            ("format", "template"),
+            ("row_template", "* [[{title}]] {img_size}"),
+        ]);
+        assert!(p.has_column("title"));
+        assert!(p.uses_template_placeholder("img_"));
+        assert!(!p.has_column("image"));
+        let p2 = make_platform(vec![("format", "wiki"), ("row_template", "{image}")]);
+        assert!(!p2.has_column("image"));
+    }
+
+    #[test]
+    fn test_wikidata_value_properties() {
+        let p = make_platform(vec![
+            ("wikidata_values", "P31,p569"),
+            ("columns", "title,P625,P31"),
+        ]);
+        assert_eq!(p.wikidata_value_properties(), vec!["P31", "P569", "P625"]);
+        assert!(make_platform(vec![]).wikidata_value_properties().is_empty());
+        let p2 = make_platform(vec![
+            ("format", "template"),
+            ("row_template", "{title} {P18}"),
+        ]);
+        assert_eq!(p2.wikidata_value_properties(), vec!["P18"]);
+    }
+
+    #[test]
+    fn test_wikidata_term_languages() {
+        let p = make_platform(vec![
+            ("wikidata_label_language", "de, en,mul"),
+            ("wikidata_languages", "fr,EN"),
+            ("columns", "title,Label_NL,aliases_fr"),
+        ]);
+        let languages = p.wikidata_term_languages();
+        assert_eq!(languages.fallback, vec!["de", "en", "mul"]);
+        assert_eq!(languages.columns, vec!["fr", "en", "nl"]);
+        assert_eq!(languages.all(), vec!["de", "en", "mul", "fr", "nl"]);
+
+        let p2 = make_platform(vec![("interface_language", "sv")]);
+        assert_eq!(p2.wikidata_term_languages().fallback, vec!["sv"]);
+        assert!(p2.wikidata_term_languages().columns.is_empty());
+    }
+
+    #[test]
+    fn test_sitelink_matrix_sites() {
+        let p = make_platform(vec![
+            ("sitelink_matrix", "dewiki, FRWIKI,bad site,dewiki"),
+            ("columns", "title,sitelink_enwiki,Sitelink_FRWIKI"),
+        ]);
+        assert_eq!(
+            p.sitelink_matrix_sites(),
+            vec!["dewiki", "frwiki", "enwiki"]
+        );
+
+        let p2 = make_platform(vec![
+            ("format", "template"),
+            ("row_template", "{title} {sitelink_nlwiki}"),
+        ]);
+        assert_eq!(p2.sitelink_matrix_sites(), vec!["nlwiki"]);
+        assert!(make_platform(vec![]).sitelink_matrix_sites().is_empty());
+    }
+
     #[test]
     fn test_is_param_blank() {
         let p = make_platform(vec![("key", "value"), ("blank", "")]);
@@ -1,8 +1,11 @@
 use crate::datasource::SQLtuple;
 use crate::datasource::database::{SourceDatabase, SourceDatabaseParameters};
-use crate::pagelist::{DatabaseCluster, PageList};
+use crate::pagelist::{DatabaseCluster, PageList, WikidataEntityType};
 use crate::pagelist_entry::{FileInfo, LinkCount, PageListEntry, TriState};
 use crate::platform::{PAGE_BATCH_SIZE, Platform};
+use crate::result_cache::{CachedResult, Cursor, MAX_ENTRIES_PER_RESULT, Pagination};
+use crate::wikidata_entities::filter::ValueFilter;
+use crate::wikidata_entities::{EntityLoader, claim_values, lexeme_from_entity, parse_item_list};
 use anyhow::{Result, anyhow};
 use my::Value::Bytes;
 use mysql_async as my;
@@ -120,14 +123,12 @@ impl PageFields {
 impl Platform {
     // ─── Entry helpers ───────────────────────────────────────────────────────
 
-    /// Converts a Wikidata entity ID string (e.g. "Q123", "P456") into a `PageListEntry`
+    /// Converts a Wikidata entity ID string (e.g. "Q123", "P456", "L789") into a
+    /// `PageListEntry`. Forms and senses ("L789-F1", "L789-S1") become their lexeme.
     pub fn entry_from_entity(entity: &str) -> Option<PageListEntry> {
-        match entity.chars().next() {
-            Some('Q') => Some(PageListEntry::new(Title::new(entity, 0))),
-            Some('P') => Some(PageListEntry::new(Title::new(entity, 120))),
-            Some('L') => Some(PageListEntry::new(Title::new(entity, 146))),
-            _ => None,
-        }
+        let namespace_id = WikidataEntityType::from_entity_id(entity)?.namespace_id();
+        let entity = WikidataEntityType::page_entity_id(entity);
+        Some(PageListEntry::new(Title::new(entity, namespace_id)))
     }
 
     // ─── Label SQL (new wbt_ schema) ─────────────────────────────────────────
@@ -246,6 +247,8 @@ impl Platform {
         Platform::profile("before filter_wikidata", Some(result.len()));
         self.filter_wikidata(result).await?;
         Platform::profile("after filter_wikidata", Some(result.len()));
+        self.filter_wikidata_values(result).await?;
+        Platform::profile("after filter_wikidata_values", Some(result.len()));
 
         if available_sources.to_vec() != vec!["sitelinks".to_string()] {
             self.process_sitelinks(result).await?;
@@ -255,6 +258,8 @@ impl Platform {
             self.process_labels(result).await?;
             Platform::profile("after process_labels", Some(result.len()));
         }
+        self.filter_wikidata_terms(result).await?;
+        Platform::profile("after filter_wikidata_terms", Some(result.len()));
         Ok(())
     }
 
@@ -275,12 +280,8 @@ impl Platform {
 
     /// Applies text-based filters (regexp, search) and final creator/redlink pass.
     async fn apply_text_filters_and_creator(&self, result: &PageList) -> Result<()> {
-        let wikidata_label_language = self.get_param_default(
-            "wikidata_label_language",
-            &self.get_param_default("interface_language", "en"),
-        );
         result
-            .load_missing_metadata(Some(wikidata_label_language), self)
+            .load_missing_metadata(Some(self.wikidata_term_languages()), self)
             .await?;
         Platform::profile("after load_missing_metadata", Some(result.len()));
 
@@ -339,6 +340,12 @@ impl Platform {
 
         self.apply_page_enrichments(result).await?;
         self.apply_text_filters_and_creator(result).await?;
+        self.process_wikidata_values(result).await?;
+        Platform::profile("after process_wikidata_values", Some(result.len()));
+        self.process_lexemes(result).await?;
+        Platform::profile("after process_lexemes", Some(result.len()));
+        self.process_sitelink_matrix(result).await?;
+        Platform::profile("after process_sitelink_matrix", Some(result.len()));
         Ok(())
     }
 
@@ -391,6 +398,62 @@ impl Platform {
         }
     }
 
+    /// With `limit` (and optional `offset`), caches the sorted result for
+    /// later pages and returns just the requested page. Results that fit on
+    /// one page are not cached; neither are results too large to cache, which
+    /// then have no cursors.
+    pub(super) fn paginate(
+        &self,
+        wiki: &str,
+        pages: Vec<PageListEntry>,
+    ) -> Result<Vec<PageListEntry>> {
+        let limit = match self.usize_option_from_param("limit") {
+            Some(limit) if limit > 0 => limit,
+            _ => return Ok(pages),
+        };
+        let offset = self.usize_option_from_param("offset").unwrap_or(0);
+        let total = pages.len();
+        let uncached = Pagination::new(0, offset, limit, total);
+        // The cache key is only known after caching, which takes `pages`
+        let page = uncached.slice(&pages).to_vec();
+        if uncached.next.is_none() && uncached.prev.is_none() {
+            self.set_pagination(uncached)?;
+            return Ok(page);
+        }
+        let pagination = match self.state.cache_result(wiki, pages)? {
+            Some(key) => Pagination::new(key, offset, limit, total),
+            None => {
+                self.warn(format!(
+                    "Result too large to cache for paging ({total} pages, at most {MAX_ENTRIES_PER_RESULT}); use offset= for further pages"
+                ))?;
+                Pagination {
+                    next: None,
+                    prev: None,
+                    ..uncached
+                }
+            }
+        };
+        self.set_pagination(pagination)?;
+        Ok(page)
+    }
+
+    /// The page of a cached result that `cursor` points to.
+    pub(super) fn page_from_cache(
+        &self,
+        cursor: &Cursor,
+        cached: &CachedResult,
+    ) -> Result<Vec<PageListEntry>> {
+        let pagination =
+            Pagination::new(cursor.key, cursor.offset, cursor.limit, cached.pages.len());
+        self.set_pagination(pagination)?;
+        Ok(pagination.slice(&cached.pages).to_vec())
+    }
+
+    fn set_pagination(&self, pagination: Pagination) -> Result<()> {
+        *self.pagination.write().map_err(|e| anyhow!("{e}"))? = Some(pagination);
+        Ok(())
+    }
+
     // ─── Creator / labels ────────────────────────────────────────────────────
 
     /// Builds the SQL batch that queries for existing Wikidata labels matching the page titles.
@@ -621,13 +684,20 @@ impl Platform {
     // ─── Process pages (coordinates, image, defaultsort, …) ──────────────────
 
     async fn process_pages(&self, result: &PageList) -> Result<()> {
-        let is_kml = self.get_param_blank("format") == "kml";
+        let is_map = matches!(
+            self.get_param_blank("format").as_str(),
+            "kml" | "geojson" | "gpx"
+        );
         let fields = PageFields {
-            add_image: self.has_param("add_image") || is_kml,
-            add_coordinates: self.has_param("add_coordinates") || is_kml,
+            add_image: self.has_param("add_image") || is_map || self.has_column("image"),
+            add_coordinates: self.has_param("add_coordinates")
+                || is_map
+                || self.has_column("coordinates"),
             add_defaultsort: self.has_param("add_defaultsort")
-                || self.get_param_blank("sortby") == "defaultsort",
-            add_disambiguation: self.has_param("add_disambiguation"),
+                || self.get_param_blank("sortby") == "defaultsort"
+                || self.has_column("defaultsort"),
+            add_disambiguation: self.has_param("add_disambiguation")
+                || self.has_column("disambiguation"),
             add_incoming_links: self.get_param_blank("sortby") == "incoming_links",
             add_sitelinks: self.get_param_blank("sortby") == "sitelinks"
                 && !result.has_sitelink_counts(),
@@ -735,8 +805,18 @@ impl Platform {
         let file_data = self.has_param("ext_image_data")
             || self.get_param("sortby") == Some("filesize".to_string())
             || self.get_param("sortby") == Some("uploaddate".to_string())
-            || media_type_filter.is_some();
-        let file_usage = giu || self.has_param("file_usage_data");
+            || media_type_filter.is_some()
+            || self
+                .requested_columns()
+                .iter()
+                .any(|c| c.to_lowercase().starts_with("img_"))
+            || self.uses_template_placeholder("img_")
+            || (self.get_param_blank("format") == "stats"
+                && self
+                    .get_param_as_vec("stats_group", ",")
+                    .iter()
+                    .any(|g| g == "media_type"));
+        let file_usage = giu || self.has_param("file_usage_data") || self.has_column("fileusage");
         let file_usage_data_ns0 = self.has_param("file_usage_data_ns0");
 
         if file_usage {
@@ -1050,6 +1130,138 @@ impl Platform {
         Ok(())
     }
 
+    // ─── Term worklists ───────────────────────────────────────────────────────
+
+    /// Conditions of `label_present`, `label_missing`, `description_present`
+    /// and `description_missing` (comma-separated languages each), as
+    /// (`wbtl_type_id`, language, present)
+    fn term_conditions(&self) -> Vec<(u64, String, bool)> {
+        [
+            ("label_present", 1, true),
+            ("label_missing", 1, false),
+            ("description_present", 2, true),
+            ("description_missing", 2, false),
+        ]
+        .into_iter()
+        .flat_map(|(param, type_id, present)| {
+            self.get_param_as_vec(param, ",")
+                .into_iter()
+                .map(move |language| (type_id, language.to_lowercase(), present))
+        })
+        .collect()
+    }
+
+    /// Selects which of `ids` have labels or descriptions in `languages`, as
+    /// (entity ID, `wbtl_type_id`, language); `None` for lexemes, which have
+    /// no terms
+    fn term_presence_sql(
+        entity_type: WikidataEntityType,
+        ids: &[u64],
+        languages: &[String],
+    ) -> Option<SQLtuple> {
+        let table = entity_type.terms_table()?;
+        let id_field = entity_type.id_field()?;
+        let term_in_lang = entity_type.term_in_lang_field()?;
+        let prefix = entity_type.prefix();
+        let id_placeholders = vec!["?"; ids.len()].join(",");
+        let language_placeholders = vec!["?"; languages.len()].join(",");
+        let sql = format!(
+            "SELECT DISTINCT CONCAT('{prefix}',{id_field}) AS term_full_entity_id,wbtl_type_id,wbxl_language \
+             FROM {table} \
+             INNER JOIN wbt_term_in_lang ON {term_in_lang}=wbtl_id \
+             INNER JOIN wbt_text_in_lang ON wbtl_text_in_lang_id=wbxl_id \
+             WHERE {id_field} IN ({id_placeholders}) AND wbtl_type_id IN (1,2) AND wbxl_language IN ({language_placeholders})"
+        );
+        let params = ids
+            .iter()
+            .map(|id| MyValue::UInt(*id))
+            .chain(
+                languages
+                    .iter()
+                    .map(|language| MyValue::Bytes(language.to_owned().into())),
+            )
+            .collect();
+        Some((sql, params))
+    }
+
+    /// Checks the term conditions for one entity, given which of its terms exist
+    fn matches_term_conditions(
+        entity_id: &str,
+        present: &HashSet<(String, u64, String)>,
+        conditions: &[(u64, String, bool)],
+    ) -> bool {
+        conditions
+            .iter()
+            .all(|(type_id, language, should_be_present)| {
+                let key = (entity_id.to_string(), *type_id, language.to_owned());
+                present.contains(&key) == *should_be_present
+            })
+    }
+
+    /// Worklist filters for items (and properties) that lack a label or
+    /// description in some languages, or have one, e.g. "label in de but
+    /// not in en". Unlike `labels_yes`/`labels_no`, these do not look at
+    /// the text. Lexemes have no labels and are dropped. Results from other
+    /// wikis are converted to Wikidata for this, and back.
+    async fn filter_wikidata_terms(&self, result: &PageList) -> Result<()> {
+        let conditions = self.term_conditions();
+        if conditions.is_empty() || result.is_empty() {
+            return Ok(());
+        }
+        let original_wiki = result.wiki();
+        result.convert_to_wiki("wikidatawiki", self).await?;
+
+        let mut languages: Vec<String> = conditions
+            .iter()
+            .map(|(_type_id, language, _present)| language.to_owned())
+            .collect();
+        languages.sort();
+        languages.dedup();
+        let batches: Vec<SQLtuple> = result
+            .group_by_namespace()
+            .iter()
+            .filter_map(|(namespace_id, titles)| {
+                Some((
+                    WikidataEntityType::from_namespace_id(*namespace_id)?,
+                    titles,
+                ))
+            })
+            .flat_map(|(entity_type, titles)| {
+                let ids: Vec<u64> = titles
+                    .iter()
+                    .filter_map(|title| title.get(1..)?.parse::<u64>().ok())
+                    .collect();
+                ids.chunks(PAGE_BATCH_SIZE)
+                    .filter_map(|chunk| Self::term_presence_sql(entity_type, chunk, &languages))
+                    .collect::<Vec<SQLtuple>>()
+            })
+            .collect();
+
+        let present: HashSet<(String, u64, String)> = result
+            .run_batch_queries_with_cluster(&self.state(), batches, DatabaseCluster::X3)
+            .await?
+            .into_iter()
+            .filter_map(|row| my::from_row_opt::<(Vec<u8>, u64, Vec<u8>)>(row).ok())
+            .map(|(entity_id, type_id, language)| {
+                (
+                    String::from_utf8_lossy(&entity_id).into_owned(),
+                    type_id,
+                    String::from_utf8_lossy(&language).into_owned(),
+                )
+            })
+            .collect();
+        result.retain_entries(&|entry| {
+            let entity_type = WikidataEntityType::from_namespace_id(entry.title().namespace_id());
+            entity_type.is_some_and(|entity_type| entity_type.terms_table().is_some())
+                && Self::matches_term_conditions(entry.title().pretty(), &present, &conditions)
+        });
+
+        if let Some(wiki) = original_wiki {
+            result.convert_to_wiki(&wiki, self).await?;
+        }
+        Ok(())
+    }
+
     // ─── Sitelinks ────────────────────────────────────────────────────────────
 
     /// Builds the base SQL (SELECT + WHERE) and the HAVING/GROUP BY postfix for sitelink filtering.
@@ -1169,6 +1381,65 @@ impl Platform {
         Ok(())
     }
 
+    /// Selects the sitelinks of `item_ids` to `sites`, as (item ID, site, title)
+    fn build_sitelink_matrix_sql(item_ids: &[u64], sites: &[String]) -> SQLtuple {
+        let item_placeholders = vec!["?"; item_ids.len()].join(",");
+        let site_placeholders = vec!["?"; sites.len()].join(",");
+        let sql = format!(
+            "SELECT CONCAT('Q',ips_item_id),ips_site_id,ips_site_page FROM wb_items_per_site \
+             WHERE ips_item_id IN ({item_placeholders}) AND ips_site_id IN ({site_placeholders})"
+        );
+        let params = item_ids
+            .iter()
+            .map(|id| MyValue::UInt(*id))
+            .chain(sites.iter().map(|site| site.into()))
+            .collect();
+        (sql, params)
+    }
+
+    /// Adds the sitelinks of each item to the sites of `sitelink_matrix` (or
+    /// sitelink columns), for a column per site with the title there, if
+    /// any. Together with `sitelinks_yes`/`sitelinks_no`, this gives e.g.
+    /// "articles in de and fr but not in en", with the de and fr titles.
+    async fn process_sitelink_matrix(&self, result: &PageList) -> Result<()> {
+        let sites = self.sitelink_matrix_sites();
+        if sites.is_empty() || result.is_empty() {
+            return Ok(());
+        }
+        // Items of non-Wikidata pages were annotated in `apply_page_enrichments`
+        let is_wikidata = result.is_wikidata();
+        let entries = result.as_vec();
+        let mut item_ids: Vec<u64> = entries
+            .iter()
+            .filter_map(|entry| Self::wikidata_entity_id(entry, is_wikidata))
+            .filter_map(|id| id.strip_prefix('Q')?.parse::<u64>().ok())
+            .collect();
+        item_ids.sort_unstable();
+        item_ids.dedup();
+        let batches: Vec<SQLtuple> = item_ids
+            .chunks(PAGE_BATCH_SIZE)
+            .map(|chunk| Self::build_sitelink_matrix_sql(chunk, &sites))
+            .collect();
+
+        let mut sitelinks: HashMap<String, HashMap<String, String>> = HashMap::new();
+        PageList::new_from_wiki("wikidatawiki")
+            .run_batch_queries(&self.state(), batches)
+            .await?
+            .into_iter()
+            .filter_map(|row| my::from_row_opt::<(String, String, String)>(row).ok())
+            .for_each(|(item, site, title)| {
+                sitelinks.entry(item).or_default().insert(site, title);
+            });
+        for mut entry in entries {
+            let Some(item) = Self::wikidata_entity_id(&entry, is_wikidata) else {
+                continue;
+            };
+            entry.set_sitelinks(Some(sitelinks.get(&item).cloned().unwrap_or_default()));
+            result.add_entry(entry);
+        }
+        Ok(())
+    }
+
     // ─── Wikidata property/item filter ────────────────────────────────────────
 
     /// Builds the `sql_post` snippet that encodes all Wikidata property/item filters.
@@ -1307,8 +1578,9 @@ impl Platform {
             .to_sql_batches(PAGE_BATCH_SIZE)
             .iter_mut()
             .map(|sql| {
-                sql.0 =
-                    "SELECT DISTINCT page_title FROM page WHERE ".to_owned() + &sql.0 + &sql_post.0;
+                sql.0 = "SELECT DISTINCT page_title,page_namespace FROM page WHERE ".to_owned()
+                    + &sql.0
+                    + &sql_post.0;
                 sql.1.append(&mut sql_post.1.to_owned());
                 sql.to_owned()
             })
@@ -1316,9 +1588,10 @@ impl Platform {
 
         result.clear_entries();
         let state = self.state();
+        // Keeps the namespace, for properties and lexemes
         let the_f = |row: my::Row| {
-            let pp_value: String = my::from_row(row);
-            Some(PageListEntry::new(Title::new(&pp_value, 0)))
+            let (page_title, page_namespace) = my::from_row::<(String, NamespaceID)>(row);
+            Some(PageListEntry::new(Title::new(&page_title, page_namespace)))
         };
         result
             .run_batch_queries(&state, batches)
@@ -1332,6 +1605,152 @@ impl Platform {
         }
         Ok(())
     }
+
+    // ─── Wikidata statement values ────────────────────────────────────────────
+
+    /// The Wikidata entity an entry stands for: the page itself on Wikidata,
+    /// otherwise its item
+    fn wikidata_entity_id(entry: &PageListEntry, is_wikidata: bool) -> Option<String> {
+        if is_wikidata {
+            match entry.title().namespace_id() {
+                0 | 120 | 146 => Some(entry.title().pretty().to_string()),
+                _ => None,
+            }
+        } else {
+            entry.get_wikidata_item()
+        }
+    }
+
+    /// Loads the given entities into the loader of this query, which is
+    /// shared by statement values, the value filter and lexeme data. Entities
+    /// that could not be loaded are reported as a warning.
+    async fn load_wikidata_entities(
+        &self,
+        ids: &[String],
+    ) -> Result<tokio::sync::MutexGuard<'_, EntityLoader>> {
+        let mut loader = self.wikidata_entities.lock().await;
+        if let Some(warning) = loader.load(&self.state, ids).await? {
+            self.warn(warning)?;
+        }
+        Ok(loader)
+    }
+
+    /// Keeps the entries whose items match `wikidata_value_filter`, e.g.
+    /// `P31 = Q5 AND P569 before 1900`. Results from other wikis are
+    /// converted to Wikidata for this, and back. Entries whose items could
+    /// not be loaded are kept, with a warning.
+    async fn filter_wikidata_values(&self, result: &PageList) -> Result<()> {
+        let filter = match self.get_param("wikidata_value_filter") {
+            Some(filter) => ValueFilter::parse(&filter)
+                .map_err(|e| anyhow!("Invalid wikidata_value_filter: {e}"))?,
+            None => return Ok(()),
+        };
+        if result.is_empty() {
+            return Ok(());
+        }
+
+        let original_wiki = result.wiki();
+        result.convert_to_wiki("wikidatawiki", self).await?;
+        let ids: Vec<String> = result
+            .as_vec()
+            .iter()
+            .filter_map(|entry| Self::wikidata_entity_id(entry, true))
+            .collect();
+        let entities = self.load_wikidata_entities(&ids).await?;
+        let unchecked = ids.iter().filter(|id| entities.is_unloaded(id)).count();
+        result.retain_entries(&|entry| {
+            Self::wikidata_entity_id(entry, true).is_some_and(|id| entities.passes(&id, &filter))
+        });
+        drop(entities);
+        if unchecked > 0 {
+            self.warn(format!(
+                "{unchecked} results could not be checked against wikidata_value_filter, and were kept"
+            ))?;
+        }
+
+        if let Some(wiki) = original_wiki {
+            result.convert_to_wiki(&wiki, self).await?;
+        }
+        Ok(())
+    }
+
+    /// Adds the values of the properties in `wikidata_values` (or property
+    /// columns) to each entry that has a Wikidata item
+    async fn process_wikidata_values(&self, result: &PageList) -> Result<()> {
+        let properties = self.wikidata_value_properties();
+        if properties.is_empty() || result.is_empty() {
+            return Ok(());
+        }
+        // Items of non-Wikidata pages were annotated in `apply_page_enrichments`
+        let is_wikidata = result.is_wikidata();
+        let entries = result.as_vec();
+        let mut ids: Vec<String> = entries
+            .iter()
+            .filter_map(|entry| Self::wikidata_entity_id(entry, is_wikidata))
+            .collect();
+        ids.sort();
+        ids.dedup();
+        let entities = self.load_wikidata_entities(&ids).await?;
+        for mut entry in entries {
+            let Some(entity) =
+                Self::wikidata_entity_id(&entry, is_wikidata).and_then(|id| entities.get(&id))
+            else {
+                continue;
+            };
+            let values = properties
+                .iter()
+                .map(|property| (property.to_owned(), claim_values(entity, property)))
+                .filter(|(_property, values)| !values.is_empty())
+                .collect();
+            entry.set_wikidata_values(Some(values));
+            result.add_entry(entry);
+        }
+        Ok(())
+    }
+
+    // ─── Lexemes ──────────────────────────────────────────────────────────────
+
+    /// Loads lemma, language and lexical category of lexemes on Wikidata, for
+    /// output and for the `lexeme_language` and `lexical_category` filters,
+    /// which drop everything but matching lexemes. Lexemes have no labels, so
+    /// the lemma is used as one.
+    async fn process_lexemes(&self, result: &PageList) -> Result<()> {
+        let languages = parse_item_list(&self.get_param_blank("lexeme_language"));
+        let lexical_categories = parse_item_list(&self.get_param_blank("lexical_category"));
+        let filter = !languages.is_empty() || !lexical_categories.is_empty();
+        if !result.is_wikidata() || result.is_empty() || !(filter || self.lexeme_data_requested()) {
+            return Ok(());
+        }
+        let lexeme_namespace_id = WikidataEntityType::Lexeme.namespace_id();
+        let lexemes: Vec<PageListEntry> = result
+            .as_vec()
+            .into_iter()
+            .filter(|entry| entry.title().namespace_id() == lexeme_namespace_id)
+            .collect();
+        let ids: Vec<String> = lexemes
+            .iter()
+            .map(|entry| entry.title().pretty().to_string())
+            .collect();
+        let entities = self.load_wikidata_entities(&ids).await?;
+        for mut entry in lexemes {
+            let lexeme = entities
+                .get(entry.title().pretty())
+                .and_then(lexeme_from_entity);
+            if entry.get_wikidata_label().is_none() {
+                entry.set_wikidata_label(lexeme.as_ref().map(|l| l.lemma.to_owned()));
+            }
+            entry.set_lexeme(lexeme);
+            result.add_entry(entry);
+        }
+        if filter {
+            result.retain_entries(&|entry| {
+                entry
+                    .get_lexeme()
+                    .is_some_and(|lexeme| lexeme.matches(&languages, &lexical_categories))
+            });
+        }
+        Ok(())
+    }
 }
 
 // ─── tests ───────────────────────────────────────────────────────────────────
@@ -1343,8 +1762,79 @@ mod tests {
     use crate::test_support::make_platform;
     use wikimisc::mediawiki::title::Title;
 
+    // ─── Term worklists ───────────────────────────────────────────────────────
+
+    #[test]
+    fn test_term_conditions() {
+        let p = make_platform(vec![
+            ("label_present", "de"),
+            ("label_missing", "EN,fr"),
+            ("description_missing", "de"),
+        ]);
+        assert_eq!(
+            p.term_conditions(),
+            vec![
+                (1, "de".to_string(), true),
+                (1, "en".to_string(), false),
+                (1, "fr".to_string(), false),
+                (2, "de".to_string(), false),
+            ]
+        );
+        assert!(make_platform(vec![]).term_conditions().is_empty());
+    }
+
+    #[test]
+    fn test_term_presence_sql() {
+        let languages = vec!["de".to_string(), "en".to_string()];
+        let (sql, params) =
+            Platform::term_presence_sql(WikidataEntityType::Property, &[31, 569], &languages)
+                .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT DISTINCT CONCAT('P',wbpt_property_id) AS term_full_entity_id,wbtl_type_id,wbxl_language \
+             FROM wbt_property_terms \
+             INNER JOIN wbt_term_in_lang ON wbpt_term_in_lang_id=wbtl_id \
+             INNER JOIN wbt_text_in_lang ON wbtl_text_in_lang_id=wbxl_id \
+             WHERE wbpt_property_id IN (?,?) AND wbtl_type_id IN (1,2) AND wbxl_language IN (?,?)"
+        );
+        assert_eq!(params.len(), 4);
+        assert!(
+            Platform::term_presence_sql(WikidataEntityType::Lexeme, &[1], &languages).is_none()
+        );
+    }
+
+    #[test]
+    fn test_matches_term_conditions() {
+        let present: HashSet<(String, u64, String)> =
+            HashSet::from([("Q1".to_string(), 1, "de".to_string())]);
+        let matches = |entity_id: &str, conditions: &[(u64, String, bool)]| {
+            Platform::matches_term_conditions(entity_id, &present, conditions)
+        };
+        // Label in de, but not in en
+        let conditions = vec![(1, "de".to_string(), true), (1, "en".to_string(), false)];
+        assert!(matches("Q1", &conditions));
+        assert!(!matches("Q2", &conditions));
+        assert!(matches("Q1", &[(2, "de".to_string(), false)]));
+    }
+
     // ─── entry_from_entity ────────────────────────────────────────────────────
 
+    #[test]
+    fn test_build_sitelink_matrix_sql() {
+        let sites = vec!["dewiki".to_string(), "frwiki".to_string()];
+        let (sql, params) = Platform::build_sitelink_matrix_sql(&[64, 90], &sites);
+        assert!(sql.contains("ips_item_id IN (?,?) AND ips_site_id IN (?,?)"));
+        assert_eq!(
+            params,
+            vec![
+                MyValue::UInt(64),
+                MyValue::UInt(90),
+                MyValue::Bytes(b"dewiki".to_vec()),
+                MyValue::Bytes(b"frwiki".to_vec())
+            ]
+        );
+    }
+
     #[test]
     fn test_entry_from_entity_q_item() {
         let entry = Platform::entry_from_entity("Q42").unwrap();
@@ -1366,6 +1856,15 @@ mod tests {
         assert_eq!(entry.title().namespace_id(), 146);
     }
 
+    #[test]
+    fn test_entry_from_entity_lexeme_form_and_sense() {
+        for entity in ["L1234-F2", "L1234-S1"] {
+            let entry = Platform::entry_from_entity(entity).unwrap();
+            assert_eq!(entry.title().pretty(), "L1234");
+            assert_eq!(entry.title().namespace_id(), 146);
+        }
+    }
+
     #[test]
     fn test_entry_from_entity_unknown_returns_none() {
         assert!(Platform::entry_from_entity("X999").is_none());
@@ -1415,6 +1914,52 @@ mod tests {
         assert_eq!(pages.len(), 5);
     }
 
+    // ─── paginate ─────────────────────────────────────────────────────────────
+
+    #[test]
+    fn test_paginate_without_limit() {
+        let p = make_platform(vec![("offset", "3")]);
+        let pages: Vec<PageListEntry> = (0..5)
+            .map(|i| PageListEntry::new(Title::new(&format!("Page{i}"), 0)))
+            .collect();
+        assert_eq!(p.paginate("enwiki", pages).unwrap().len(), 5);
+        assert!(p.pagination().is_none());
+    }
+
+    #[test]
+    fn test_paginate_and_page_from_cache() {
+        let p = make_platform(vec![("limit", "2"), ("offset", "1")]);
+        let pages: Vec<PageListEntry> = (0..5)
+            .map(|i| PageListEntry::new(Title::new(&format!("Page{i}"), 0)))
+            .collect();
+        let first_page = p.paginate("enwiki", pages).unwrap();
+        let first_titles: Vec<&str> = first_page.iter().map(|e| e.title().pretty()).collect();
+        assert_eq!(first_titles, vec!["Page1", "Page2"]);
+        let pagination = p.pagination().unwrap();
+        assert_eq!(pagination.total, 5);
+
+        // Follow the "next" cursor on the cached result
+        let mut p2 = make_platform(vec![]);
+        p2.state = p.state();
+        p2.load_cached_page(pagination.next.unwrap()).unwrap();
+        let (cursor, cached) = p2.cached_page.clone().unwrap();
+        let next_page = p2.page_from_cache(&cursor, &cached).unwrap();
+        let next_titles: Vec<&str> = next_page.iter().map(|e| e.title().pretty()).collect();
+        assert_eq!(next_titles, vec!["Page3", "Page4"]);
+        assert!(p2.pagination().unwrap().next.is_none());
+    }
+
+    #[test]
+    fn test_load_cached_page_expired() {
+        let mut p = make_platform(vec![]);
+        let cursor = Cursor {
+            key: 1,
+            offset: 0,
+            limit: 10,
+        };
+        assert!(p.load_cached_page(cursor).is_err());
+    }
+
     // ─── get_label_sql_new ────────────────────────────────────────────────────
 
     #[test]
@@ -1,17 +1,27 @@
+pub mod geojson;
+pub mod gpx;
 pub mod html;
 pub mod json;
 pub mod jsonl;
 pub mod kml;
 pub mod pagepile;
 pub mod params;
+pub mod parquet;
 pub mod plaintext;
+pub mod quickstatements;
+pub mod rdf;
+pub mod stats;
+pub mod template;
 pub mod tsv;
+pub mod wdfist;
 pub mod wikitext;
+pub mod xlsx;
 
 use crate::form_parameters::FormParameters;
 use crate::pagelist_entry::{LinkCount, PageListEntry};
 use crate::platform::{MyResponse, Platform};
 use crate::render::params::RenderParams;
+use crate::wikidata_entities::is_property_id;
 use anyhow::Result;
 use async_trait::async_trait;
 use std::sync::Arc;
@@ -21,6 +31,113 @@ use wikimisc::mediawiki::title::Title;
 pub static AUTOLIST_WIKIDATA: &str = "www.wikidata.org";
 pub static AUTOLIST_COMMONS: &str = "commons.wikimedia.org";
 
+/// File metadata columns, filled when file data is loaded
+pub(crate) const FILE_DATA_KEYS: &[&str] = &[
+    "img_size",
+    "img_width",
+    "img_height",
+    "img_media_type",
+    "img_major_mime",
+    "img_minor_mime",
+    "img_user_text",
+    "img_timestamp",
+    "img_sha1",
+];
+
+/// Columns that can be picked with the `columns` parameter, besides
+/// `FILE_DATA_KEYS`
+pub(crate) const COLUMNS: &[&str] = &[
+    "number",
+    "image",
+    "title",
+    "page_id",
+    "namespace",
+    "size",
+    "timestamp",
+    "wikidata_item",
+    "wikidata_label",
+    "wikidata_description",
+    "coordinates",
+    "defaultsort",
+    "disambiguation",
+    "incoming_links",
+    "sitelinks",
+    "linknumber",
+    "redlink_count",
+    "fileusage",
+    "lemma",
+    "lexeme_language",
+    "lexical_category",
+];
+
+/// Kinds of per-language Wikidata term columns, like `label_de`
+pub(crate) const TERM_KINDS: &[&str] = &["label", "description", "aliases"];
+
+/// Checks for a Wikidata term language code, like `de` or `pt-br`
+pub(crate) fn is_term_language(s: &str) -> bool {
+    !s.is_empty()
+        && s.chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+}
+
+/// Splits a term column like `label_de` or `aliases_pt-br` into kind and language
+pub(crate) fn parse_term_column(column: &str) -> Option<(&str, &str)> {
+    let (kind, language) = column.split_once('_')?;
+    (TERM_KINDS.contains(&kind) && is_term_language(language)).then_some((kind, language))
+}
+
+/// Label or description of a term column as a JSON string, aliases as an array
+pub(crate) fn term_column_value(entry: &PageListEntry, column: &str) -> Option<serde_json::Value> {
+    let (kind, language) = parse_term_column(column)?;
+    let terms = entry.get_wikidata_terms(language)?;
+    match kind {
+        "label" => terms.label.as_ref().map(|s| json!(s)),
+        "description" => terms.description.as_ref().map(|s| json!(s)),
+        _ => Some(json!(terms.aliases)),
+    }
+}
+
+/// Checks for a site ID, like `dewiki` or `commonswiki`
+fn is_site_id(s: &str) -> bool {
+    !s.is_empty()
+        && s.chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+/// The site of a sitelink matrix column, like `dewiki` for `sitelink_dewiki`
+pub(crate) fn parse_sitelink_column(column: &str) -> Option<&str> {
+    column
+        .strip_prefix("sitelink_")
+        .filter(|site| is_site_id(site))
+}
+
+/// Placeholders in a `row_template` that `is_column` accepts, in order
+fn placeholders_where(template: &str, is_column: fn(&str) -> bool) -> Vec<String> {
+    let mut ret: Vec<String> = vec![];
+    for column in template
+        .split('{')
+        .skip(1)
+        .filter_map(|part| Some(part.split_once('}')?.0))
+        .filter(|column| is_column(column))
+    {
+        if !ret.iter().any(|c| c == column) {
+            ret.push(column.to_string());
+        }
+    }
+    ret
+}
+
+/// Term column placeholders like `{label_de}` in a `row_template`, in order
+pub(crate) fn term_placeholders(template: &str) -> Vec<String> {
+    placeholders_where(template, |column| parse_term_column(column).is_some())
+}
+
+/// Sitelink column placeholders like `{sitelink_dewiki}` in a `row_template`,
+/// in order
+pub(crate) fn sitelink_placeholders(template: &str) -> Vec<String> {
+    placeholders_where(template, |column| parse_sitelink_column(column).is_some())
+}
+
 /// Namespace-and-title operations that the renderers need from a
 /// MediaWiki `Api`. Extracting these behind a trait lets `RenderParams`
 /// hold an `Arc<dyn NamespaceContext>` instead of an `Api` directly,
@@ -119,20 +236,10 @@ pub trait Render {
     ) -> Result<MyResponse>;
 
     fn file_data_keys(&self) -> Vec<&str> {
-        vec![
-            "img_size",
-            "img_width",
-            "img_height",
-            "img_media_type",
-            "img_major_mime",
-            "img_minor_mime",
-            "img_user_text",
-            "img_timestamp",
-            "img_sha1",
-        ]
-    }
-
-    fn get_initial_columns(&self, params: &RenderParams) -> Vec<&str> {
+        FILE_DATA_KEYS.to_vec()
+    }
+
+    fn get_initial_columns<'a>(&'a self, params: &'a RenderParams) -> Vec<&'a str> {
         let mut columns = vec![];
         if params.use_autolist() {
             columns.push("checkbox");
@@ -175,9 +282,36 @@ pub trait Render {
         if params.file_usage() {
             columns.push("fileusage");
         }
+        if params.add_lexeme_data() {
+            columns.push("lemma");
+            columns.push("lexeme_language");
+            columns.push("lexical_category");
+        }
+        params
+            .wikidata_values()
+            .iter()
+            .chain(params.term_columns())
+            .chain(params.sitelink_columns())
+            .for_each(|column| columns.push(column));
         columns
     }
 
+    /// Columns picked and ordered by the `columns` parameter, as
+    /// `(key, label)` pairs; `None` if the parameter was not given, in
+    /// which case each renderer uses its default layout.
+    fn get_selected_columns(&self, params: &RenderParams) -> Option<Vec<(String, String)>> {
+        if params.columns().is_empty() {
+            return None;
+        }
+        Some(
+            params
+                .columns()
+                .iter()
+                .map(|col| (col.to_string(), col.to_string()))
+                .collect(),
+        )
+    }
+
     // The five cell-render methods below are only invoked by the default
     // `row_from_entry` implementation, which non-tabular renderers (JSON,
     // JSONL, PagePile) bypass entirely. Each has a `String::new()` default
@@ -226,6 +360,35 @@ pub trait Render {
             None => String::new(),
         }
     }
+    fn render_cell_wikidata_term(&self, term: &Option<String>, _params: &RenderParams) -> String {
+        self.opt_string(term)
+    }
+    /// Label, description or aliases (joined by `|`) in one language
+    fn render_cell_term_column(
+        &self,
+        entry: &PageListEntry,
+        column: &str,
+        params: &RenderParams,
+    ) -> String {
+        let terms = parse_term_column(column).and_then(|(kind, language)| {
+            let terms = entry.get_wikidata_terms(language)?;
+            match kind {
+                "label" => terms.label.clone(),
+                "description" => terms.description.clone(),
+                _ => Some(terms.aliases.join("|")),
+            }
+        });
+        self.render_cell_wikidata_term(&terms, params)
+    }
+    /// The title of the sitelink to `site`; empty if the item has none
+    fn render_cell_sitelink(
+        &self,
+        _site: &str,
+        title: Option<&str>,
+        _params: &RenderParams,
+    ) -> String {
+        title.unwrap_or_default().to_string()
+    }
     fn render_coordinates(&self, entry: &PageListEntry, _params: &RenderParams) -> String {
         match &entry.get_coordinates() {
             Some(coords) => format!("{}/{}", coords.lat, coords.lon),
@@ -279,6 +442,12 @@ pub trait Render {
                 "size" => self.opt_u32(&entry.page_bytes()),
                 "timestamp" => self.opt_string(&entry.get_page_timestamp()),
                 "wikidata_item" => self.render_cell_wikidata_item(entry, params),
+                "wikidata_label" => {
+                    self.render_cell_wikidata_term(&entry.get_wikidata_label(), params)
+                }
+                "wikidata_description" => {
+                    self.render_cell_wikidata_term(&entry.get_wikidata_description(), params)
+                }
                 "image" => self.render_cell_image(&entry.get_page_image(), params),
                 "number" => params.row_number().to_string(),
                 "defaultsort" => self.opt_string(&entry.get_defaultsort()),
@@ -313,6 +482,33 @@ pub trait Render {
                 },
                 "coordinates" => self.render_coordinates(entry, params),
                 "fileusage" => self.render_cell_fileusage(entry, params),
+                "lemma" => self.render_cell_wikidata_term(
+                    &entry.get_lexeme().map(|lexeme| lexeme.lemma.to_owned()),
+                    params,
+                ),
+                "lexeme_language" => self.opt_string(
+                    &entry
+                        .get_lexeme()
+                        .and_then(|lexeme| lexeme.language.to_owned()),
+                ),
+                "lexical_category" => self.opt_string(
+                    &entry
+                        .get_lexeme()
+                        .and_then(|lexeme| lexeme.lexical_category.to_owned()),
+                ),
+                property if is_property_id(property) => self.render_cell_wikidata_term(
+                    &entry
+                        .get_wikidata_values(property)
+                        .map(|values| values.join("|")),
+                    params,
+                ),
+                column if parse_term_column(column).is_some() => {
+                    self.render_cell_term_column(entry, column, params)
+                }
+                column if parse_sitelink_column(column).is_some() => {
+                    let site = parse_sitelink_column(column).unwrap_or_default();
+                    self.render_cell_sitelink(site, entry.get_sitelink(site), params)
+                }
 
                 _ => "<".to_string() + k + ">",
             };
@@ -435,4 +631,35 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_parse_term_column() {
+        assert_eq!(parse_term_column("label_de"), Some(("label", "de")));
+        assert_eq!(
+            parse_term_column("aliases_pt-br"),
+            Some(("aliases", "pt-br"))
+        );
+        assert_eq!(parse_term_column("wikidata_label"), None);
+        assert_eq!(parse_term_column("label_DE"), None);
+        assert_eq!(parse_term_column("img_size"), None);
+        assert_eq!(
+            term_placeholders("{title} {label_de} {aliases_en}{label_de} {label_}"),
+            vec!["label_de", "aliases_en"]
+        );
+    }
+
+    #[test]
+    fn test_parse_sitelink_column() {
+        assert_eq!(parse_sitelink_column("sitelink_dewiki"), Some("dewiki"));
+        assert_eq!(
+            parse_sitelink_column("sitelink_be_x_oldwiki"),
+            Some("be_x_oldwiki")
+        );
+        assert_eq!(parse_sitelink_column("sitelink_"), None);
+        assert_eq!(parse_sitelink_column("sitelinks"), None);
+        assert_eq!(
+            sitelink_placeholders("{title}: {sitelink_dewiki} {sitelink_frwiki}"),
+            vec!["sitelink_dewiki", "sitelink_frwiki"]
+        );
+    }
 }
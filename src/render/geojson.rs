@@ -0,0 +1,178 @@
+use crate::content_type::ContentType;
+use crate::form_parameters::FormParameters;
+use crate::pagelist_entry::PageListEntry;
+use crate::platform::{MyResponse, Platform};
+use crate::render::params::RenderParams;
+use crate::render::{Render, parse_sitelink_column, term_column_value};
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::Value;
+
+/// Renders a `GeoJSON` `FeatureCollection` of the entries with coordinates
+#[derive(Clone, Copy, Debug)]
+pub struct RenderGeoJSON;
+
+#[async_trait]
+impl Render for RenderGeoJSON {
+    async fn response(
+        &self,
+        platform: &Platform,
+        wiki: &str,
+        entries: Vec<PageListEntry>,
+    ) -> Result<MyResponse> {
+        let params = RenderParams::new(platform, wiki).await?;
+        let server = params
+            .state()
+            .site_matrix()
+            .get_server_url_for_wiki(wiki)
+            .unwrap_or_default();
+        let features: Vec<Value> = entries
+            .iter()
+            .filter_map(|entry| Self::feature(entry, &params, &server))
+            .collect();
+        let j = json!({"type": "FeatureCollection", "features": features});
+        let s = if params.json_pretty() {
+            ::serde_json::to_string_pretty(&j)?
+        } else {
+            ::serde_json::to_string(&j)?
+        };
+        Ok(MyResponse::ok(s, ContentType::GeoJSON))
+    }
+}
+
+impl RenderGeoJSON {
+    pub fn new() -> Box<Self> {
+        Box::new(Self {})
+    }
+
+    /// One `Point` feature, or `None` if the entry has no coordinates.
+    /// `GeoJSON` positions are `[longitude, latitude]`.
+    fn feature(entry: &PageListEntry, params: &RenderParams, server: &str) -> Option<Value> {
+        let coords = entry.get_coordinates()?;
+        let title = entry.title();
+        let full_title = params
+            .ns()
+            .full_pretty(title)
+            .unwrap_or_else(|| title.pretty().to_string());
+        let full_title_underscores = params
+            .ns()
+            .full_with_underscores(title)
+            .unwrap_or_else(|| title.with_underscores());
+        let url = format!(
+            "{server}/wiki/{}",
+            FormParameters::percent_encode(&full_title_underscores)
+        );
+        let image = entry.get_page_image().map(|img| {
+            format!(
+                "{server}/wiki/Special:Redirect/file/{}?width=120",
+                FormParameters::percent_encode(&img)
+            )
+        });
+        let label = match entry.get_wikidata_label() {
+            Some(label) => label,
+            None => title.pretty().to_string(),
+        };
+        let mut feature = json!({
+            "type": "Feature",
+            "geometry": {
+                "type": "Point",
+                "coordinates": [coords.lon, coords.lat],
+            },
+            "properties": {
+                "title": full_title,
+                "namespace": title.namespace_id(),
+                "url": url,
+                "q": entry.get_wikidata_item(),
+                "label": label,
+                "description": entry.get_wikidata_description(),
+                "image": image,
+            },
+        });
+        for property in params.wikidata_values() {
+            if let Some(values) = entry.get_wikidata_values(property) {
+                feature["properties"][property] = json!(values);
+            }
+        }
+        for column in params.term_columns() {
+            if let Some(value) = term_column_value(entry, column) {
+                feature["properties"][column] = value;
+            }
+        }
+        for column in params.sitelink_columns() {
+            let title = parse_sitelink_column(column).and_then(|site| entry.get_sitelink(site));
+            if let Some(title) = title {
+                feature["properties"][column] = json!(title);
+            }
+        }
+        Some(feature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::StubNamespaceContext;
+    use std::sync::Arc;
+    use wikimisc::lat_lon::LatLon;
+    use wikimisc::mediawiki::title::Title;
+
+    const SERVER: &str = "https://en.wikipedia.org";
+
+    fn enwiki_params() -> RenderParams {
+        RenderParams::for_tests("enwiki", Arc::new(StubNamespaceContext::enwiki()))
+    }
+
+    #[test]
+    fn test_feature_without_coordinates_is_skipped() {
+        let entry = PageListEntry::new(Title::new("Nowhere", 0));
+        assert!(RenderGeoJSON::feature(&entry, &enwiki_params(), SERVER).is_none());
+    }
+
+    #[test]
+    fn test_feature_minimal() {
+        let mut entry = PageListEntry::new(Title::new("London Eye", 0));
+        entry.set_coordinates(Some(LatLon {
+            lat: 51.5033,
+            lon: -0.1196,
+        }));
+        let feature = RenderGeoJSON::feature(&entry, &enwiki_params(), SERVER).unwrap();
+        assert_eq!(feature["type"], "Feature");
+        assert_eq!(feature["geometry"]["type"], "Point");
+        // GeoJSON order is longitude first
+        assert_eq!(
+            feature["geometry"]["coordinates"],
+            json!([-0.1196, 51.5033])
+        );
+        let properties = &feature["properties"];
+        assert_eq!(properties["title"], "London Eye");
+        assert_eq!(properties["namespace"], 0);
+        assert_eq!(
+            properties["url"],
+            "https://en.wikipedia.org/wiki/London%5FEye"
+        );
+        assert_eq!(properties["label"], "London Eye");
+        assert!(properties["q"].is_null());
+        assert!(properties["description"].is_null());
+        assert!(properties["image"].is_null());
+    }
+
+    #[test]
+    fn test_feature_with_wikidata_and_image() {
+        let mut entry = PageListEntry::new(Title::new("Some file", 6));
+        entry.set_coordinates(Some(LatLon { lat: 1.0, lon: 2.0 }));
+        entry.set_wikidata_item(Some("Q42".to_string()));
+        entry.set_wikidata_label(Some("A label".to_string()));
+        entry.set_wikidata_description(Some("A description".to_string()));
+        entry.set_page_image(Some("Foo bar.jpg".to_string()));
+        let feature = RenderGeoJSON::feature(&entry, &enwiki_params(), SERVER).unwrap();
+        let properties = &feature["properties"];
+        assert_eq!(properties["title"], "File:Some file");
+        assert_eq!(properties["q"], "Q42");
+        assert_eq!(properties["label"], "A label");
+        assert_eq!(properties["description"], "A description");
+        assert_eq!(
+            properties["image"],
+            "https://en.wikipedia.org/wiki/Special:Redirect/file/Foo%20bar%2Ejpg?width=120"
+        );
+    }
+}
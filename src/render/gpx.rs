@@ -0,0 +1,166 @@
+use crate::content_type::ContentType;
+use crate::pagelist_entry::PageListEntry;
+use crate::platform::{MyResponse, Platform};
+use crate::render::Render;
+use crate::render::kml::RenderKML;
+use crate::render::params::RenderParams;
+use anyhow::Result;
+use async_trait::async_trait;
+
+/// Renders GPX waypoints
+#[derive(Clone, Copy, Debug)]
+pub struct RenderGPX;
+
+#[async_trait]
+impl Render for RenderGPX {
+    async fn response(
+        &self,
+        platform: &Platform,
+        wiki: &str,
+        entries: Vec<PageListEntry>,
+    ) -> Result<MyResponse> {
+        let params = RenderParams::new(platform, wiki).await?;
+        let server = params
+            .state()
+            .site_matrix()
+            .get_server_url_for_wiki(wiki)
+            .unwrap_or_default();
+        let mut gpx = String::new();
+        gpx += r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx version="1.1" creator="PetScan" xmlns="http://www.topografix.com/GPX/1/1" xmlns:wdt="http://www.wikidata.org/prop/direct/">"#;
+        for entry in &entries {
+            if let Some(wpt) = Self::waypoint(entry, &params, &server) {
+                gpx += &wpt;
+            }
+        }
+        gpx += "</gpx>";
+        Ok(MyResponse::ok(gpx, ContentType::GPX))
+    }
+}
+
+impl RenderGPX {
+    pub fn new() -> Box<Self> {
+        Box::new(Self {})
+    }
+
+    /// One `<wpt>` element, or `None` if the entry has no coordinates. The
+    /// values of `wikidata_values` go into `<extensions>`, one `wdt:` element
+    /// per value.
+    fn waypoint(entry: &PageListEntry, params: &RenderParams, server: &str) -> Option<String> {
+        let coords = entry.get_coordinates()?;
+        let title = entry.title();
+        let name = match entry.get_wikidata_label() {
+            Some(label) => label,
+            None => title.pretty().to_string(),
+        };
+        let full_title = params
+            .ns()
+            .full_with_underscores(title)
+            .unwrap_or_else(|| title.with_underscores());
+        let url = format!("{server}/wiki/{}", super::escape_attribute(&full_title));
+
+        let mut wpt = format!(
+            "<wpt lat=\"{}\" lon=\"{}\"><name>{}</name>",
+            coords.lat,
+            coords.lon,
+            RenderKML::escape_xml(&name)
+        );
+        if let Some(q) = entry.get_wikidata_item() {
+            wpt += &format!("<cmt>{}</cmt>", RenderKML::escape_xml(&q));
+        }
+        if let Some(desc) = entry.get_wikidata_description() {
+            wpt += &format!("<desc>{}</desc>", RenderKML::escape_xml(&desc));
+        }
+        wpt += &format!(
+            "<link href=\"{}\"><text>{}</text></link>",
+            RenderKML::escape_xml(&url),
+            RenderKML::escape_xml(title.pretty())
+        );
+        let values: String = params
+            .wikidata_values()
+            .iter()
+            .flat_map(|property| {
+                entry
+                    .get_wikidata_values(property)
+                    .unwrap_or_default()
+                    .iter()
+                    .map(move |value| {
+                        format!(
+                            "<wdt:{property}>{}</wdt:{property}>",
+                            RenderKML::escape_xml(value)
+                        )
+                    })
+            })
+            .collect();
+        if !values.is_empty() {
+            wpt += &format!("<extensions>{values}</extensions>");
+        }
+        wpt += "</wpt>";
+        Some(wpt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::StubNamespaceContext;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use wikimisc::lat_lon::LatLon;
+    use wikimisc::mediawiki::title::Title;
+
+    const SERVER: &str = "https://en.wikipedia.org";
+
+    fn enwiki_params() -> RenderParams {
+        RenderParams::for_tests("enwiki", Arc::new(StubNamespaceContext::enwiki()))
+    }
+
+    #[test]
+    fn test_waypoint_without_coordinates_is_skipped() {
+        let entry = PageListEntry::new(Title::new("Nowhere", 0));
+        assert!(RenderGPX::waypoint(&entry, &enwiki_params(), SERVER).is_none());
+    }
+
+    #[test]
+    fn test_waypoint_minimal() {
+        let mut entry = PageListEntry::new(Title::new("Tower", 0));
+        entry.set_coordinates(Some(LatLon {
+            lat: 51.5081,
+            lon: -0.0759,
+        }));
+        assert_eq!(
+            RenderGPX::waypoint(&entry, &enwiki_params(), SERVER).unwrap(),
+            "<wpt lat=\"51.5081\" lon=\"-0.0759\"><name>Tower</name>\
+             <link href=\"https://en.wikipedia.org/wiki/Tower\"><text>Tower</text></link></wpt>"
+        );
+    }
+
+    #[test]
+    fn test_waypoint_with_wikidata_values() {
+        let mut params = enwiki_params();
+        params.set_wikidata_values(vec!["P31".to_string(), "P18".to_string()]);
+        let mut entry = PageListEntry::new(Title::new("Tower", 0));
+        entry.set_coordinates(Some(LatLon { lat: 1.0, lon: 2.0 }));
+        let values = vec!["Q5".to_string(), "Q& 1".to_string()];
+        entry.set_wikidata_values(Some(HashMap::from([("P31".to_string(), values)])));
+        let wpt = RenderGPX::waypoint(&entry, &params, SERVER).unwrap();
+        assert!(wpt.ends_with(
+            "</link><extensions><wdt:P31>Q5</wdt:P31><wdt:P31>Q&amp; 1</wdt:P31></extensions></wpt>"
+        ));
+    }
+
+    #[test]
+    fn test_waypoint_with_wikidata_is_escaped() {
+        let mut entry = PageListEntry::new(Title::new("Fish & Chips", 0));
+        entry.set_coordinates(Some(LatLon { lat: 1.0, lon: 2.0 }));
+        entry.set_wikidata_item(Some("Q42".to_string()));
+        entry.set_wikidata_label(Some("<Fish>".to_string()));
+        entry.set_wikidata_description(Some("Tom & Jerry".to_string()));
+        let wpt = RenderGPX::waypoint(&entry, &enwiki_params(), SERVER).unwrap();
+        assert!(wpt.contains("<name>&lt;Fish&gt;</name>"));
+        assert!(wpt.contains("<cmt>Q42</cmt>"));
+        assert!(wpt.contains("<desc>Tom &amp; Jerry</desc>"));
+        assert!(wpt.contains("/wiki/Fish%5F%26%5FChips"));
+        assert!(wpt.contains("<text>Fish &amp; Chips</text>"));
+    }
+}
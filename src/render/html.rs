@@ -2,7 +2,10 @@ use crate::content_type::ContentType;
 use crate::pagelist_entry::PageListEntry;
 use crate::platform::{MyResponse, Platform};
 use crate::render::params::RenderParams;
-use crate::render::{AUTOLIST_COMMONS, AUTOLIST_WIKIDATA, Render};
+use crate::render::{
+    AUTOLIST_COMMONS, AUTOLIST_WIKIDATA, Render, parse_sitelink_column, parse_term_column,
+};
+use crate::wikidata_entities::is_property_id;
 use anyhow::Result;
 use async_trait::async_trait;
 use htmlescape::encode_minimal;
@@ -84,16 +87,25 @@ impl Render for RenderHTML {
             ));
         }
 
-        let header = self.get_initial_columns(&params);
+        let header: Vec<(String, String)> = match self.get_selected_columns(&params) {
+            Some(mut columns) => {
+                // The checkbox drives autolist, so it is kept
+                if params.use_autolist() {
+                    columns.insert(0, ("checkbox".to_string(), "checkbox".to_string()));
+                }
+                columns
+            }
+            None => self
+                .get_initial_columns(&params)
+                .iter()
+                .map(|x| (x.to_string(), x.to_string()))
+                .collect(),
+        };
+        let columns: Vec<&str> = header.iter().map(|(k, _)| k.as_str()).collect();
         rows.push("<div style='clear:both;overflow:auto'>".to_string());
-        rows.push(self.get_table_header(&header, &params));
+        rows.push(self.get_table_header(&columns, &params));
         rows.push("<tbody>".to_string());
 
-        let header: Vec<(String, String)> = header
-            .iter()
-            .map(|x| (x.to_string(), x.to_string()))
-            .collect();
-
         let entries_len = entries.len();
         let mut output = rows.join("\n");
         entries.drain(..).for_each(|entry| {
@@ -137,6 +149,7 @@ impl Render for RenderHTML {
             s: html,
             content_type: ContentType::HTML,
             status: 200,
+            binary: None,
         })
     }
 
@@ -183,6 +196,27 @@ impl Render for RenderHTML {
             params,
         )
     }
+    fn render_cell_wikidata_term(&self, term: &Option<String>, _params: &RenderParams) -> String {
+        term.as_deref().map(encode_minimal).unwrap_or_default()
+    }
+    fn render_cell_sitelink(
+        &self,
+        site: &str,
+        title: Option<&str>,
+        params: &RenderParams,
+    ) -> String {
+        let Some(title) = title else {
+            return String::new();
+        };
+        let label = encode_minimal(title);
+        match params.state().site_matrix().get_server_url_for_wiki(site) {
+            Ok(server) => {
+                let url = server + "/wiki/" + &super::escape_attribute(&title.replace(' ', "_"));
+                format!("<a target='_blank' href='{url}'>{label}</a>")
+            }
+            Err(_e) => label,
+        }
+    }
     fn render_cell_image(&self, image: &Option<String>, params: &RenderParams) -> String {
         match image {
             Some(img) => {
@@ -429,6 +463,8 @@ impl RenderHTML {
                 "size" => "<th class='text-nowrap' tt='h_len'></th>".to_string(),
                 "timestamp" => "<th class='text-nowrap' tt='h_touched'></th>".to_string(),
                 "wikidata_item" => "<th tt='h_wikidata'></th>".to_string(),
+                "wikidata_label" => "<th>Label</th>".to_string(),
+                "wikidata_description" => "<th>Description</th>".to_string(),
                 "coordinates" => "<th tt='h_coordinates'></th>".to_string(),
                 "defaultsort" => {
                     "<th tt='h_defaultsort' style='white-space: nowrap;'></th>".to_string()
@@ -437,6 +473,19 @@ impl RenderHTML {
                 "incoming_links" => "<th tt='h_incoming_links'></th>".to_string(),
                 "sitelinks" => "<th tt='h_sitelinks'></th>".to_string(),
                 "fileusage" => "<th tt='file_usage_data'></th>".to_string(),
+                "lemma" => "<th tt='h_lemma'>Lemma</th>".to_string(),
+                "lexeme_language" => "<th tt='h_lexeme_language'>Language</th>".to_string(),
+                "lexical_category" => {
+                    "<th tt='h_lexical_category'>Lexical category</th>".to_string()
+                }
+                property if is_property_id(property) => format!("<th>{property}</th>"),
+                column if parse_term_column(column).is_some() => {
+                    format!("<th>{}</th>", encode_minimal(column))
+                }
+                column if parse_sitelink_column(column).is_some() => {
+                    let site = parse_sitelink_column(column).unwrap_or_default();
+                    format!("<th>{}</th>", encode_minimal(site))
+                }
                 other => {
                     // File data etc.
                     if fdk.contains(&other) {
@@ -1,16 +1,83 @@
 use crate::content_type::ContentType;
 use crate::platform::MyResponse;
-use crate::render::Render;
 use crate::render::params::RenderParams;
+use crate::render::{Render, parse_sitelink_column, parse_term_column, term_column_value};
+use crate::wikidata_entities::is_property_id;
 use crate::{pagelist_entry::PageListEntry, platform::Platform};
 use anyhow::{Result, anyhow};
 use async_trait::async_trait;
+use serde::ser::{Serialize, SerializeMap, SerializeSeq, Serializer};
 use serde_json::Value;
 
 /// Renders JSON
 #[derive(Clone, Copy, Debug)]
 pub struct RenderJSON;
 
+/// Serializes a `Value`, writing objects that hold exactly the picked
+/// columns (see `RenderJSON::columns_object`) with their keys in the picked
+/// order. Other objects keep serde_json's sorted key order.
+#[derive(Clone, Copy, Debug)]
+pub struct ColumnsOrdered<'a> {
+    value: &'a Value,
+    keys: &'a [(String, String)],
+}
+
+impl<'a> ColumnsOrdered<'a> {
+    pub const fn new(value: &'a Value, columns: &'a [(String, String)]) -> Self {
+        Self {
+            value,
+            keys: columns,
+        }
+    }
+
+    const fn child(&self, value: &'a Value) -> Self {
+        Self::new(value, self.keys)
+    }
+
+    /// The distinct column keys, in order
+    fn column_keys(&self) -> Vec<&'a str> {
+        let mut ret: Vec<&str> = vec![];
+        for (key, _) in self.keys {
+            if !ret.contains(&key.as_str()) {
+                ret.push(key);
+            }
+        }
+        ret
+    }
+}
+
+impl Serialize for ColumnsOrdered<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.value {
+            Value::Object(o) => {
+                let column_keys = self.column_keys();
+                let mut map = serializer.serialize_map(Some(o.len()))?;
+                if !o.is_empty()
+                    && o.len() == column_keys.len()
+                    && column_keys.iter().all(|key| o.contains_key(*key))
+                {
+                    for key in column_keys {
+                        map.serialize_entry(key, &o[key])?;
+                    }
+                } else {
+                    for (key, value) in o {
+                        map.serialize_entry(key, &self.child(value))?;
+                    }
+                }
+                map.end()
+            }
+            Value::Array(a) => {
+                let mut seq = serializer.serialize_seq(Some(a.len()))?;
+                for value in a {
+                    seq.serialize_element(&self.child(value))?;
+                }
+                seq.end()
+            }
+            other => other.serialize(serializer),
+        }
+    }
+}
+
 #[async_trait]
 impl Render for RenderJSON {
     async fn response(
@@ -27,6 +94,8 @@ impl Render for RenderJSON {
         };
 
         let value = self.generate_json(platform, &mut params, entries).await?;
+        let columns = self.get_selected_columns(&params).unwrap_or_default();
+        let value = ColumnsOrdered::new(&value, &columns);
 
         let mut out: String = String::new();
         if !params.json_callback().is_empty() {
@@ -52,6 +121,7 @@ impl Render for RenderJSON {
             s: out,
             content_type,
             status: 200,
+            binary: None,
         })
     }
 
@@ -100,13 +170,31 @@ impl RenderJSON {
                 .for_each(|k| header.push((k.to_string(), k.to_string())));
         }
 
+        let columns = self.get_selected_columns(params);
         let value: Value = match params.json_output_compatability() {
-            "quick-intersection" => Self::quick_intersection(platform, entries, params, &header),
-            _ => self.cat_scan(platform, entries, params, &header), // Default
+            "quick-intersection" => {
+                Self::quick_intersection(platform, entries, params, &header, columns.as_deref())
+            }
+            _ => self.cat_scan(platform, entries, params, &header, columns.as_deref()), // Default
         };
         Ok(value)
     }
 
+    /// Position of this page in a paginated result, with links to the
+    /// neighbouring pages; `None` if the result is not paginated.
+    pub fn pagination(platform: &Platform) -> Option<Value> {
+        let pagination = platform.pagination()?;
+        Some(json!({
+            "offset": pagination.offset,
+            "limit": pagination.limit,
+            "total": pagination.total,
+            "next": pagination.next.map(|cursor| platform.cursor_link(&cursor)),
+            "prev": pagination.prev.map(|cursor| platform.cursor_link(&cursor)),
+            "next_cursor": pagination.next.map(|cursor| cursor.to_param()),
+            "prev_cursor": pagination.prev.map(|cursor| cursor.to_param()),
+        }))
+    }
+
     fn get_query_string(platform: &Platform) -> String {
         "https://petscan.wmflabs.org/?".to_string() + &platform.form_parameters().to_string()
     }
@@ -117,12 +205,15 @@ impl RenderJSON {
         entries: Vec<PageListEntry>,
         params: &RenderParams,
         header: &[(String, String)],
+        columns: Option<&[(String, String)]>,
     ) -> Value {
         let entry_data: Vec<Value> = if params.json_sparse() {
             entries
                 .iter()
                 .filter_map(|entry| Some(json!(params.ns().full_with_underscores(entry.title())?)))
                 .collect()
+        } else if let Some(columns) = columns {
+            Self::columns_objects(&entries, params, columns)
         } else {
             entries.iter().map(|entry| {
                 let mut o = json!({
@@ -152,7 +243,11 @@ impl RenderJSON {
             Some(duration) => (duration.as_millis() as f32) / (1000_f32),
             None => 0.0,
         };
-        json!({"n":"result","a":{"query":Self::get_query_string(platform),"querytime_sec":seconds},"*":[{"n":"combination","a":{"type":platform.get_param_default("combination","subset"),"*":entry_data}}]})
+        let mut ret = json!({"n":"result","a":{"query":Self::get_query_string(platform),"querytime_sec":seconds},"*":[{"n":"combination","a":{"type":platform.get_param_default("combination","subset"),"*":entry_data}}]});
+        if let Some(pagination) = Self::pagination(platform) {
+            ret["a"]["pagination"] = pagination;
+        }
+        ret
     }
 
     fn quick_intersection(
@@ -160,6 +255,7 @@ impl RenderJSON {
         entries: Vec<PageListEntry>,
         params: &RenderParams,
         header: &[(String, String)],
+        columns: Option<&[(String, String)]>,
     ) -> Value {
         let mut ret = json!({
             "namespaces":{},
@@ -174,6 +270,12 @@ impl RenderJSON {
         if let Some(duration) = platform.query_time() {
             ret["querytime"] = json!((duration.as_millis() as f32) / 1000_f32);
         }
+        if let Some(snapshot_id) = platform.snapshot_id {
+            ret["snapshot"] = json!(snapshot_id);
+        }
+        if let Some(pagination) = Self::pagination(platform) {
+            ret["pagination"] = pagination;
+        }
 
         // Namespaces
         params.ns().for_each_local_namespace(&mut |k, name| {
@@ -186,6 +288,8 @@ impl RenderJSON {
                 .iter()
                 .filter_map(|entry| params.ns().full_with_underscores(entry.title()))
                 .collect();
+        } else if let Some(columns) = columns {
+            ret["pages"] = json!(Self::columns_objects(&entries, params, columns));
         } else {
             ret["pages"] = entries
                 .iter()
@@ -267,26 +371,155 @@ impl RenderJSON {
 
     fn add_metadata(o: &mut Value, entry: &PageListEntry, header: &[(String, String)]) {
         header.iter().for_each(|(head, _)| {
-            let value = match head.to_string().as_str() {
-                "checkbox" | "number" | "page_id" | "title" | "namespace" | "size"
-                | "timestamp" => None,
-                "image" => entry.get_page_image().map(|s| json!(s)),
-                "linknumber" => entry.link_count().map(|s| json!(s)),
-                "wikidata" => entry.get_wikidata_item().map(|s| json!(s)),
-                "defaultsort" => entry.get_defaultsort().map(|s| json!(s)),
-                "disambiguation" => Some(entry.disambiguation().as_json()),
-                "incoming_links" => entry.incoming_links().map(|s| json!(s)),
-                "sitelinks" => entry.sitelink_count().map(|s| json!(s)),
-                "coordinates" => entry
-                    .get_coordinates()
-                    .as_ref()
-                    .map(|coord| json!(format!("{}/{}", coord.lat, coord.lon))),
-                "fileusage" => Self::get_file_usage_as_string(entry),
-                other => Self::get_file_info_value(entry, other),
-            };
-            if let Some(v) = value {
+            if let Some(v) = Self::metadata_value(entry, head) {
                 o["metadata"][head] = v;
             }
         });
     }
+
+    fn metadata_value(entry: &PageListEntry, head: &str) -> Option<Value> {
+        match head {
+            "checkbox" | "number" | "page_id" | "title" | "namespace" | "size" | "timestamp" => {
+                None
+            }
+            "image" => entry.get_page_image().map(|s| json!(s)),
+            "linknumber" => entry.link_count().map(|s| json!(s)),
+            "wikidata" => entry.get_wikidata_item().map(|s| json!(s)),
+            "wikidata_label" => entry.get_wikidata_label().map(|s| json!(s)),
+            "wikidata_description" => entry.get_wikidata_description().map(|s| json!(s)),
+            "defaultsort" => entry.get_defaultsort().map(|s| json!(s)),
+            "disambiguation" => Some(entry.disambiguation().as_json()),
+            "incoming_links" => entry.incoming_links().map(|s| json!(s)),
+            "sitelinks" => entry.sitelink_count().map(|s| json!(s)),
+            "coordinates" => entry
+                .get_coordinates()
+                .as_ref()
+                .map(|coord| json!(format!("{}/{}", coord.lat, coord.lon))),
+            "fileusage" => Self::get_file_usage_as_string(entry),
+            "lemma" => entry.get_lexeme().map(|lexeme| json!(lexeme.lemma)),
+            "lexeme_language" => entry
+                .get_lexeme()
+                .and_then(|lexeme| lexeme.language.as_ref())
+                .map(|s| json!(s)),
+            "lexical_category" => entry
+                .get_lexeme()
+                .and_then(|lexeme| lexeme.lexical_category.as_ref())
+                .map(|s| json!(s)),
+            property if is_property_id(property) => entry
+                .get_wikidata_values(property)
+                .map(|values| json!(values)),
+            column if parse_term_column(column).is_some() => term_column_value(entry, column),
+            column if parse_sitelink_column(column).is_some() => parse_sitelink_column(column)
+                .and_then(|site| entry.get_sitelink(site))
+                .map(|title| json!(title)),
+            other => Self::get_file_info_value(entry, other),
+        }
+    }
+
+    fn columns_objects(
+        entries: &[PageListEntry],
+        params: &RenderParams,
+        columns: &[(String, String)],
+    ) -> Vec<Value> {
+        entries
+            .iter()
+            .enumerate()
+            .map(|(num, entry)| Self::columns_object(entry, num + 1, params, columns))
+            .collect()
+    }
+
+    /// One page as an object holding exactly the picked columns; missing
+    /// values are `null`. `ColumnsOrdered` writes them in the picked order.
+    fn columns_object(
+        entry: &PageListEntry,
+        number: usize,
+        params: &RenderParams,
+        columns: &[(String, String)],
+    ) -> Value {
+        let mut o = json!({});
+        for (key, _) in columns {
+            o[key] = match key.as_str() {
+                "number" => json!(number),
+                "title" => json!(entry.title().with_underscores()),
+                "page_id" => json!(entry.page_id()),
+                "namespace" => json!(entry.title().namespace_id()),
+                "size" => json!(entry.page_bytes()),
+                "timestamp" => json!(entry.get_page_timestamp()),
+                "wikidata_item" => json!(Self::wikidata_item(entry, params)),
+                "redlink_count" => json!(entry.redlink_count()),
+                other => Self::metadata_value(entry, other).unwrap_or(Value::Null),
+            };
+        }
+        o
+    }
+
+    fn wikidata_item(entry: &PageListEntry, params: &RenderParams) -> Option<String> {
+        if params.is_wikidata() && entry.title().namespace_id() == 0 {
+            return Some(entry.title().pretty().to_string());
+        }
+        entry.get_wikidata_item()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::StubNamespaceContext;
+    use std::sync::Arc;
+    use wikimisc::mediawiki::title::Title;
+
+    fn columns(keys: &[&str]) -> Vec<(String, String)> {
+        keys.iter()
+            .map(|k| (k.to_string(), k.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_columns_object() {
+        let params = RenderParams::for_tests("enwiki", Arc::new(StubNamespaceContext::enwiki()));
+        let mut entry = PageListEntry::new(Title::new("Foo bar", 0));
+        entry.set_wikidata_item(Some("Q42".to_string()));
+        entry.set_wikidata_description(Some("A page".to_string()));
+        let o = RenderJSON::columns_object(
+            &entry,
+            3,
+            &params,
+            &columns(&[
+                "number",
+                "title",
+                "wikidata_item",
+                "wikidata_description",
+                "size",
+            ]),
+        );
+        assert_eq!(
+            o,
+            json!({
+                "number": 3,
+                "title": "Foo_bar",
+                "wikidata_item": "Q42",
+                "wikidata_description": "A page",
+                "size": null,
+            })
+        );
+        // In the picked order, not alphabetically, also when nested
+        let picked = columns(&["title", "number", "title"]);
+        let value = json!({
+            "pages": [RenderJSON::columns_object(&entry, 3, &params, &picked)],
+            "a": {"b": 1, "a": 2}
+        });
+        assert_eq!(
+            serde_json::to_string(&ColumnsOrdered::new(&value, &picked)).unwrap(),
+            r#"{"a":{"a":2,"b":1},"pages":[{"title":"Foo_bar","number":3}]}"#
+        );
+    }
+
+    #[test]
+    fn test_columns_object_wikidata_item_on_wikidata() {
+        let params =
+            RenderParams::for_tests("wikidatawiki", Arc::new(StubNamespaceContext::enwiki()));
+        let entry = PageListEntry::new(Title::new("Q42", 0));
+        let o = RenderJSON::columns_object(&entry, 1, &params, &columns(&["wikidata_item"]));
+        assert_eq!(o, json!({"wikidata_item": "Q42"}));
+    }
 }
@@ -1,7 +1,7 @@
 use crate::content_type::ContentType;
 use crate::platform::MyResponse;
 use crate::render::Render;
-use crate::render::json::RenderJSON;
+use crate::render::json::{ColumnsOrdered, RenderJSON};
 use crate::render::params::RenderParams;
 use crate::{pagelist_entry::PageListEntry, platform::Platform};
 use anyhow::{Result, anyhow};
@@ -26,6 +26,7 @@ impl Render for RenderJSONL {
         let rj = RenderJSON::new();
 
         let value = rj.generate_json(platform, &mut params, entries).await?;
+        let columns = rj.get_selected_columns(&params).unwrap_or_default();
 
         let value: &Value = match params.json_output_compatability() {
             "quick-intersection" => &value["pages"],
@@ -39,18 +40,24 @@ impl Render for RenderJSONL {
 
         let mut out: String = String::new();
         for part in parts {
-            let output = ::serde_json::to_string(&part);
+            let output = ::serde_json::to_string(&ColumnsOrdered::new(part, &columns));
             match output {
                 Ok(o) => out += &o,
                 Err(e) => return Err(anyhow!("JSON encoding failed: {e}")),
             };
             out += "\n";
         }
+        // Paginated results end with a line pointing to the other pages
+        if let Some(pagination) = RenderJSON::pagination(platform) {
+            out += &json!({ "pagination": pagination }).to_string();
+            out += "\n";
+        }
 
         Ok(MyResponse {
             s: out,
             content_type,
             status: 200,
+            binary: None,
         })
     }
 
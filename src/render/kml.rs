@@ -78,6 +78,7 @@ impl Render for RenderKML {
                     )
                     .as_str();
                 }
+                kml += &Self::wikidata_value_data(&entry, params.wikidata_values());
 
                 kml += "</ExtendedData>";
 
@@ -96,6 +97,7 @@ impl Render for RenderKML {
             s: kml,
             content_type: ContentType::Plain,
             status: 200,
+            binary: None,
         })
     }
 
@@ -131,7 +133,21 @@ impl RenderKML {
         Box::new(Self {})
     }
 
-    fn escape_xml(s: &str) -> String {
+    /// `<Data>` elements for the values of `wikidata_values`, joined by `|`
+    fn wikidata_value_data(entry: &PageListEntry, properties: &[String]) -> String {
+        properties
+            .iter()
+            .filter_map(|property| Some((property, entry.get_wikidata_values(property)?)))
+            .map(|(property, values)| {
+                format!(
+                    "<Data name=\"{property}\"><value>{}</value></Data>",
+                    Self::escape_xml(&values.join("|"))
+                )
+            })
+            .collect()
+    }
+
+    pub(crate) fn escape_xml(s: &str) -> String {
         // `&` must be replaced first; otherwise the ampersands introduced by
         // the other replacements get re-escaped on the final pass.
         s.replace('&', "&amp;")
@@ -145,6 +161,20 @@ impl RenderKML {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashMap;
+    use wikimisc::mediawiki::title::Title;
+
+    #[test]
+    fn test_wikidata_value_data() {
+        let mut entry = PageListEntry::new(Title::new("Tower", 0));
+        let values = vec!["Q5".to_string(), "Q<1>".to_string()];
+        entry.set_wikidata_values(Some(HashMap::from([("P31".to_string(), values)])));
+        let properties = vec!["P31".to_string(), "P18".to_string()];
+        assert_eq!(
+            RenderKML::wikidata_value_data(&entry, &properties),
+            "<Data name=\"P31\"><value>Q5|Q&lt;1&gt;</value></Data>"
+        );
+    }
 
     #[test]
     fn test_escape_xml_all_specials() {
@@ -1,6 +1,10 @@
 use crate::app_state::AppState;
 use crate::platform::Platform;
-use crate::render::{AUTOLIST_WIKIDATA, ApiNamespaceContext, NamespaceContext};
+use crate::render::{
+    AUTOLIST_WIKIDATA, ApiNamespaceContext, COLUMNS, FILE_DATA_KEYS, NamespaceContext, TERM_KINDS,
+    parse_sitelink_column, parse_term_column,
+};
+use crate::wikidata_entities::is_property_id;
 use anyhow::Result;
 use std::sync::Arc;
 
@@ -19,6 +23,7 @@ pub struct RenderParams {
     add_disambiguation: bool,
     add_incoming_links: bool,
     add_sitelinks: bool,
+    add_lexeme_data: bool,
     do_output_redlinks: bool,
     use_autolist: bool,
     autolist_creator_mode: bool,
@@ -37,6 +42,14 @@ pub struct RenderParams {
     json_sparse: bool,
     json_pretty: bool,
     giu: bool,
+    /// Columns picked via the `columns` parameter; empty for the defaults
+    columns: Vec<String>,
+    /// Wikidata properties whose values are output, e.g. `P31`
+    wikidata_values: Vec<String>,
+    /// Wikidata term columns for `wikidata_languages`, e.g. `label_de`
+    term_columns: Vec<String>,
+    /// Sitelink matrix columns for `sitelink_matrix`, e.g. `sitelink_dewiki`
+    sitelink_columns: Vec<String>,
 }
 
 impl RenderParams {
@@ -51,11 +64,15 @@ impl RenderParams {
             wdi: platform.get_param_default("wikidata_item", "no"),
             add_coordinates: platform.has_param("add_coordinates"),
             add_image: platform.has_param("add_image")
-                || platform.get_param_blank("format") == "kml",
+                || matches!(
+                    platform.get_param_blank("format").as_str(),
+                    "kml" | "geojson" | "gpx"
+                ),
             add_defaultsort: platform.has_param("add_defaultsort"),
             add_disambiguation: platform.has_param("add_disambiguation"),
             add_incoming_links: platform.get_param_blank("sortby") == "incoming_links",
             add_sitelinks: platform.get_param_blank("sortby") == "sitelinks",
+            add_lexeme_data: platform.has_param("lexeme_data"),
             show_wikidata_item: false,
             is_wikidata: wiki == "wikidatawiki",
             do_output_redlinks: platform.do_output_redlinks(),
@@ -64,14 +81,28 @@ impl RenderParams {
             autolist_wiki_server: AUTOLIST_WIKIDATA.to_string(), // Possibly set downstream
             ns,
             state: platform.state(),
-            row_number: 0,
+            // Continue the numbering on later pages
+            row_number: platform.pagination().map_or(0, |p| p.offset),
             json_output_compatability: platform
                 .get_param_default("output_compatability", "catscan"), // Default; "quick-intersection" ?
             json_callback: platform.get_param_blank("callback"),
             json_sparse: platform.has_param("sparse"),
             json_pretty: platform.has_param("json-pretty"),
             giu: platform.has_param("giu"),
+            columns: vec![],
+            wikidata_values: platform.wikidata_value_properties(),
+            term_columns: Self::term_columns_for(&platform.wikidata_column_languages()),
+            sitelink_columns: platform
+                .sitelink_matrix_sites()
+                .iter()
+                .map(|site| format!("sitelink_{site}"))
+                .collect(),
         };
+        let (columns, unknown) = Self::parse_columns(&platform.requested_columns());
+        if !unknown.is_empty() {
+            platform.warn(format!("Unknown columns ignored: {}", unknown.join(", ")))?;
+        }
+        ret.columns = columns;
         ret.show_wikidata_item = ret.wdi == "any" || ret.wdi == "with";
         Ok(ret)
     }
@@ -96,6 +127,7 @@ impl RenderParams {
             add_disambiguation: false,
             add_incoming_links: false,
             add_sitelinks: false,
+            add_lexeme_data: false,
             do_output_redlinks: false,
             use_autolist: false,
             autolist_creator_mode: false,
@@ -108,6 +140,10 @@ impl RenderParams {
             json_sparse: false,
             json_pretty: false,
             giu: false,
+            columns: vec![],
+            wikidata_values: vec![],
+            term_columns: vec![],
+            sitelink_columns: vec![],
         }
     }
 
@@ -230,4 +266,139 @@ impl RenderParams {
     pub const fn add_sitelinks(&self) -> bool {
         self.add_sitelinks
     }
+
+    pub const fn add_lexeme_data(&self) -> bool {
+        self.add_lexeme_data
+    }
+
+    pub const fn add_lexeme_data_mut(&mut self) -> &mut bool {
+        &mut self.add_lexeme_data
+    }
+
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    pub fn set_columns(&mut self, columns: Vec<String>) {
+        self.columns = columns;
+    }
+
+    pub fn wikidata_values(&self) -> &[String] {
+        &self.wikidata_values
+    }
+
+    pub fn set_wikidata_values(&mut self, wikidata_values: Vec<String>) {
+        self.wikidata_values = wikidata_values;
+    }
+
+    pub fn term_columns(&self) -> &[String] {
+        &self.term_columns
+    }
+
+    pub fn set_term_columns(&mut self, term_columns: Vec<String>) {
+        self.term_columns = term_columns;
+    }
+
+    pub fn sitelink_columns(&self) -> &[String] {
+        &self.sitelink_columns
+    }
+
+    pub fn set_sitelink_columns(&mut self, sitelink_columns: Vec<String>) {
+        self.sitelink_columns = sitelink_columns;
+    }
+
+    /// Label, description and aliases columns for each language
+    fn term_columns_for(languages: &[String]) -> Vec<String> {
+        languages
+            .iter()
+            .flat_map(|language| {
+                TERM_KINDS
+                    .iter()
+                    .map(move |kind| format!("{kind}_{language}"))
+            })
+            .collect()
+    }
+
+    /// Splits requested columns into known ones (in the requested order,
+    /// without duplicates) and unknown ones. Wikidata property columns
+    /// like `P31` are known, and kept upper-case; so are term columns like
+    /// `label_de` and sitelink columns like `sitelink_dewiki`, in lower case.
+    fn parse_columns(requested: &[String]) -> (Vec<String>, Vec<String>) {
+        let mut columns: Vec<String> = vec![];
+        let mut unknown = vec![];
+        for col in requested {
+            let col = match col.to_uppercase() {
+                property if is_property_id(&property) => property,
+                _ => col.to_lowercase(),
+            };
+            let known = is_property_id(&col)
+                || parse_term_column(&col).is_some()
+                || parse_sitelink_column(&col).is_some()
+                || COLUMNS.contains(&col.as_str())
+                || FILE_DATA_KEYS.contains(&col.as_str());
+            if !known {
+                unknown.push(col);
+            } else if !columns.contains(&col) {
+                columns.push(col);
+            }
+        }
+        (columns, unknown)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(v: &[&str]) -> Vec<String> {
+        v.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_columns() {
+        let (columns, unknown) = RenderParams::parse_columns(&strings(&[
+            "wikidata_label",
+            "Title",
+            "img_size",
+            "title",
+            "p31",
+            "Label_DE",
+            "Sitelink_DEWIKI",
+            "bogus",
+        ]));
+        assert_eq!(
+            columns,
+            strings(&[
+                "wikidata_label",
+                "title",
+                "img_size",
+                "P31",
+                "label_de",
+                "sitelink_dewiki"
+            ])
+        );
+        assert_eq!(unknown, strings(&["bogus"]));
+    }
+
+    #[test]
+    fn test_term_columns_for() {
+        assert_eq!(
+            RenderParams::term_columns_for(&strings(&["de", "pt-br"])),
+            strings(&[
+                "label_de",
+                "description_de",
+                "aliases_de",
+                "label_pt-br",
+                "description_pt-br",
+                "aliases_pt-br"
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_columns_empty() {
+        let (columns, unknown) = RenderParams::parse_columns(&[]);
+        assert!(columns.is_empty());
+        assert!(unknown.is_empty());
+    }
 }
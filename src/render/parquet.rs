@@ -0,0 +1,560 @@
+use crate::content_type::ContentType;
+use crate::pagelist_entry::{FileInfo, FileUsage, PageListEntry};
+use crate::platform::{MyResponse, Platform};
+use crate::render::params::RenderParams;
+use crate::render::{Render, parse_sitelink_column, parse_term_column};
+use anyhow::Result;
+use arrow_array::builder::{ListBuilder, StringBuilder};
+use arrow_array::{
+    ArrayRef, BooleanArray, Float64Array, Int64Array, ListArray, RecordBatch, StringArray,
+    StructArray, TimestampSecondArray, UInt32Array, UInt64Array,
+};
+use arrow_buffer::{NullBuffer, OffsetBuffer};
+use arrow_schema::{DataType, Field, Fields};
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use std::sync::Arc;
+
+/// Renders Apache Parquet, with one typed column per `PageListEntry` field
+#[derive(Clone, Copy, Debug)]
+pub struct RenderParquet;
+
+#[async_trait]
+impl Render for RenderParquet {
+    async fn response(
+        &self,
+        platform: &Platform,
+        wiki: &str,
+        entries: Vec<PageListEntry>,
+    ) -> Result<MyResponse> {
+        let params = RenderParams::new(platform, wiki).await?;
+        let batch = Self::record_batch(&entries, &params)?;
+        Ok(MyResponse::ok_binary(
+            Self::write_parquet(&batch)?,
+            ContentType::Parquet,
+        ))
+    }
+}
+
+impl RenderParquet {
+    pub fn new() -> Box<Self> {
+        Box::new(Self {})
+    }
+
+    /// All entries as one batch. Column names follow the JSON output
+    /// (`page_id`, `page_namespace`, ...); unset values are nulls.
+    fn record_batch(entries: &[PageListEntry], params: &RenderParams) -> Result<RecordBatch> {
+        let file_infos: Vec<Option<FileInfo>> =
+            entries.iter().map(PageListEntry::get_file_info).collect();
+        let wiki: ArrayRef = Arc::new(StringArray::from(vec![params.wiki(); entries.len()]));
+        let page_title: ArrayRef = Arc::new(StringArray::from_iter_values(
+            entries.iter().map(|e| e.title().with_underscores()),
+        ));
+        let page_namespace: ArrayRef = Arc::new(Int64Array::from_iter_values(
+            entries.iter().map(|e| e.title().namespace_id()),
+        ));
+        let disambiguation: ArrayRef = Arc::new(BooleanArray::from(
+            entries
+                .iter()
+                .map(|e| e.disambiguation().as_option_bool())
+                .collect::<Vec<Option<bool>>>(),
+        ));
+        let lat: ArrayRef = Arc::new(Float64Array::from(
+            entries
+                .iter()
+                .map(|e| e.get_coordinates().map(|c| c.lat))
+                .collect::<Vec<Option<f64>>>(),
+        ));
+        let lon: ArrayRef = Arc::new(Float64Array::from(
+            entries
+                .iter()
+                .map(|e| e.get_coordinates().map(|c| c.lon))
+                .collect::<Vec<Option<f64>>>(),
+        ));
+        let mut columns = vec![
+            ("wiki", wiki, false),
+            ("page_title", page_title, false),
+            ("page_namespace", page_namespace, false),
+            (
+                "page_id",
+                Self::u32_column(entries, PageListEntry::page_id),
+                true,
+            ),
+            (
+                "page_len",
+                Self::u32_column(entries, PageListEntry::page_bytes),
+                true,
+            ),
+            (
+                "page_timestamp",
+                Self::timestamp_column(entries.iter().map(PageListEntry::get_page_timestamp)),
+                true,
+            ),
+            (
+                "q",
+                Self::string_column(entries.iter().map(PageListEntry::get_wikidata_item)),
+                true,
+            ),
+            (
+                "label",
+                Self::string_column(entries.iter().map(PageListEntry::get_wikidata_label)),
+                true,
+            ),
+            (
+                "description",
+                Self::string_column(entries.iter().map(PageListEntry::get_wikidata_description)),
+                true,
+            ),
+            (
+                "defaultsort",
+                Self::string_column(entries.iter().map(PageListEntry::get_defaultsort)),
+                true,
+            ),
+            ("disambiguation", disambiguation, true),
+            (
+                "image",
+                Self::string_column(entries.iter().map(PageListEntry::get_page_image)),
+                true,
+            ),
+            (
+                "incoming_links",
+                Self::u32_column(entries, PageListEntry::incoming_links),
+                true,
+            ),
+            (
+                "link_count",
+                Self::u32_column(entries, PageListEntry::link_count),
+                true,
+            ),
+            (
+                "redlink_count",
+                Self::u32_column(entries, PageListEntry::redlink_count),
+                true,
+            ),
+            (
+                "sitelink_count",
+                Self::u32_column(entries, PageListEntry::sitelink_count),
+                true,
+            ),
+            ("lat", lat, true),
+            ("lon", lon, true),
+            (
+                "img_size",
+                Self::file_u64_column(&file_infos, |fi| fi.img_size),
+                true,
+            ),
+            (
+                "img_width",
+                Self::file_u64_column(&file_infos, |fi| fi.img_width),
+                true,
+            ),
+            (
+                "img_height",
+                Self::file_u64_column(&file_infos, |fi| fi.img_height),
+                true,
+            ),
+            (
+                "img_media_type",
+                Self::file_string_column(&file_infos, |fi| fi.img_media_type.clone()),
+                true,
+            ),
+            (
+                "img_major_mime",
+                Self::file_string_column(&file_infos, |fi| fi.img_major_mime.clone()),
+                true,
+            ),
+            (
+                "img_minor_mime",
+                Self::file_string_column(&file_infos, |fi| fi.img_minor_mime.clone()),
+                true,
+            ),
+            (
+                "img_user_text",
+                Self::file_string_column(&file_infos, |fi| fi.img_user_text.clone()),
+                true,
+            ),
+            (
+                "img_timestamp",
+                Self::timestamp_column(
+                    file_infos
+                        .iter()
+                        .map(|fi| fi.as_ref().and_then(|fi| fi.img_timestamp.clone())),
+                ),
+                true,
+            ),
+            (
+                "img_sha1",
+                Self::file_string_column(&file_infos, |fi| fi.img_sha1.clone()),
+                true,
+            ),
+            ("file_usage", Self::file_usage_column(&file_infos)?, true),
+        ];
+        for property in params.wikidata_values() {
+            let values = entries.iter().map(|e| e.get_wikidata_values(property));
+            columns.push((property.as_str(), Self::string_list_column(values), true));
+        }
+        if params.add_lexeme_data() {
+            let lexemes = || entries.iter().map(PageListEntry::get_lexeme);
+            columns.push((
+                "lemma",
+                Self::string_column(lexemes().map(|l| Some(l?.lemma.to_owned()))),
+                true,
+            ));
+            columns.push((
+                "lexeme_language",
+                Self::string_column(lexemes().map(|l| l?.language.to_owned())),
+                true,
+            ));
+            columns.push((
+                "lexical_category",
+                Self::string_column(lexemes().map(|l| l?.lexical_category.to_owned())),
+                true,
+            ));
+        }
+        for column in params.term_columns() {
+            let Some((kind, language)) = parse_term_column(column) else {
+                continue;
+            };
+            let terms = entries.iter().map(|e| e.get_wikidata_terms(language));
+            let array = match kind {
+                "label" => Self::string_column(terms.map(|t| t?.label.clone())),
+                "description" => Self::string_column(terms.map(|t| t?.description.clone())),
+                _ => Self::string_list_column(terms.map(|t| Some(t?.aliases.as_slice()))),
+            };
+            columns.push((column.as_str(), array, true));
+        }
+        for column in params.sitelink_columns() {
+            let Some(site) = parse_sitelink_column(column) else {
+                continue;
+            };
+            let titles = entries
+                .iter()
+                .map(|e| e.get_sitelink(site).map(|title| title.to_string()));
+            columns.push((column.as_str(), Self::string_column(titles), true));
+        }
+        let batch = RecordBatch::try_from_iter_with_nullable(columns)?;
+        Ok(batch)
+    }
+
+    fn write_parquet(batch: &RecordBatch) -> Result<Vec<u8>> {
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+        let mut buffer = Vec::new();
+        let mut writer = ArrowWriter::try_new(&mut buffer, batch.schema(), Some(properties))?;
+        writer.write(batch)?;
+        writer.close()?;
+        Ok(buffer)
+    }
+
+    fn u32_column(entries: &[PageListEntry], f: fn(&PageListEntry) -> Option<u32>) -> ArrayRef {
+        Arc::new(UInt32Array::from(
+            entries.iter().map(f).collect::<Vec<Option<u32>>>(),
+        ))
+    }
+
+    fn string_column(values: impl Iterator<Item = Option<String>>) -> ArrayRef {
+        Arc::new(StringArray::from(values.collect::<Vec<Option<String>>>()))
+    }
+
+    /// `list<utf8>`, for Wikidata property values and aliases; null where
+    /// none were loaded
+    fn string_list_column<'a>(values: impl Iterator<Item = Option<&'a [String]>>) -> ArrayRef {
+        let mut builder = ListBuilder::new(StringBuilder::new());
+        for value in values {
+            match value {
+                Some(value) => {
+                    value.iter().for_each(|s| builder.values().append_value(s));
+                    builder.append(true);
+                }
+                None => builder.append_null(),
+            }
+        }
+        Arc::new(builder.finish())
+    }
+
+    /// `MediaWiki` timestamps (`YYYYMMDDHHMMSS`) as UTC seconds; anything
+    /// unparseable becomes null.
+    fn timestamp_column(values: impl Iterator<Item = Option<String>>) -> ArrayRef {
+        let seconds: Vec<Option<i64>> = values
+            .map(|ts| {
+                NaiveDateTime::parse_from_str(&ts?, "%Y%m%d%H%M%S")
+                    .ok()
+                    .map(|dt| dt.and_utc().timestamp())
+            })
+            .collect();
+        Arc::new(TimestampSecondArray::from(seconds).with_timezone("UTC"))
+    }
+
+    fn file_u64_column(
+        file_infos: &[Option<FileInfo>],
+        f: fn(&FileInfo) -> Option<usize>,
+    ) -> ArrayRef {
+        Arc::new(UInt64Array::from(
+            file_infos
+                .iter()
+                .map(|fi| fi.as_ref().and_then(f).map(|v| v as u64))
+                .collect::<Vec<Option<u64>>>(),
+        ))
+    }
+
+    fn file_string_column(
+        file_infos: &[Option<FileInfo>],
+        f: fn(&FileInfo) -> Option<String>,
+    ) -> ArrayRef {
+        Self::string_column(file_infos.iter().map(|fi| fi.as_ref().and_then(f)))
+    }
+
+    /// `list<struct<wiki, namespace, title, page_id>>`; null for entries
+    /// without file information.
+    fn file_usage_column(file_infos: &[Option<FileInfo>]) -> Result<ArrayRef> {
+        let usages: Vec<&FileUsage> = file_infos
+            .iter()
+            .flatten()
+            .flat_map(|fi| fi.file_usage.iter())
+            .collect();
+        let wikis: ArrayRef = Arc::new(StringArray::from_iter_values(
+            usages.iter().map(|fu| fu.wiki().to_owned()),
+        ));
+        let namespaces: ArrayRef = Arc::new(Int64Array::from_iter_values(
+            usages.iter().map(|fu| fu.title().namespace_id()),
+        ));
+        let titles: ArrayRef = Arc::new(StringArray::from_iter_values(
+            usages.iter().map(|fu| fu.title().with_underscores()),
+        ));
+        let page_ids: ArrayRef = Arc::new(UInt64Array::from_iter_values(
+            usages.iter().map(|fu| fu.page_id() as u64),
+        ));
+        let fields = Fields::from(vec![
+            Field::new("wiki", DataType::Utf8, false),
+            Field::new("namespace", DataType::Int64, false),
+            Field::new("title", DataType::Utf8, false),
+            Field::new("page_id", DataType::UInt64, false),
+        ]);
+        let values = StructArray::try_new(
+            fields.clone(),
+            vec![wikis, namespaces, titles, page_ids],
+            None,
+        )?;
+        let item = Arc::new(Field::new("item", DataType::Struct(fields), false));
+        let offsets = OffsetBuffer::from_lengths(
+            file_infos
+                .iter()
+                .map(|fi| fi.as_ref().map_or(0, |fi| fi.file_usage.len())),
+        );
+        let nulls = NullBuffer::from(
+            file_infos
+                .iter()
+                .map(Option::is_some)
+                .collect::<Vec<bool>>(),
+        );
+        Ok(Arc::new(ListArray::try_new(
+            item,
+            offsets,
+            Arc::new(values),
+            Some(nulls),
+        )?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pagelist_entry::{Lexeme, TriState};
+    use crate::test_support::StubNamespaceContext;
+    use arrow_array::Array;
+    use std::collections::HashMap;
+    use wikimisc::mediawiki::title::Title;
+
+    fn enwiki_params() -> RenderParams {
+        RenderParams::for_tests("enwiki", Arc::new(StubNamespaceContext::enwiki()))
+    }
+
+    fn entries() -> Vec<PageListEntry> {
+        let mut plain = PageListEntry::new(Title::new("Plain page", 0));
+        plain.set_page_id(Some(12));
+        plain.set_page_bytes(Some(3456));
+        plain.set_page_timestamp(Some("20240102030405".to_string()));
+        plain.set_wikidata_item(Some("Q42".to_string()));
+        plain.set_disambiguation(TriState::No);
+
+        let mut file = PageListEntry::new(Title::new("Foo.jpg", 6));
+        let mut file_info = FileInfo::new_from_gil_group("enwiki:0:7::Main_page|dewiki:1:8:Talk:X");
+        file_info.img_size = Some(1024);
+        file_info.img_timestamp = Some("not a timestamp".to_string());
+        file.set_file_info(Some(file_info));
+        vec![plain, file]
+    }
+
+    #[test]
+    fn test_record_batch_types_and_nulls() {
+        let batch = RenderParquet::record_batch(&entries(), &enwiki_params()).unwrap();
+        assert_eq!(batch.num_rows(), 2);
+        let schema = batch.schema();
+        assert_eq!(
+            schema.field_with_name("page_id").unwrap().data_type(),
+            &DataType::UInt32
+        );
+        assert_eq!(
+            schema
+                .field_with_name("page_timestamp")
+                .unwrap()
+                .data_type(),
+            &DataType::Timestamp(arrow_schema::TimeUnit::Second, Some("UTC".into()))
+        );
+        assert!(!schema.field_with_name("page_title").unwrap().is_nullable());
+
+        let page_id = batch
+            .column_by_name("page_id")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<UInt32Array>()
+            .unwrap();
+        assert_eq!(page_id.value(0), 12);
+        assert!(page_id.is_null(1));
+
+        let timestamps = batch
+            .column_by_name("page_timestamp")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<TimestampSecondArray>()
+            .unwrap();
+        assert_eq!(timestamps.value(0), 1_704_164_645);
+        let img_timestamps = batch.column_by_name("img_timestamp").unwrap();
+        assert!(img_timestamps.is_null(0));
+        assert!(img_timestamps.is_null(1));
+
+        let disambiguation = batch.column_by_name("disambiguation").unwrap();
+        assert!(!disambiguation.is_null(0));
+        assert!(disambiguation.is_null(1));
+    }
+
+    #[test]
+    fn test_record_batch_file_usage_list() {
+        let batch = RenderParquet::record_batch(&entries(), &enwiki_params()).unwrap();
+        let file_usage = batch
+            .column_by_name("file_usage")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<ListArray>()
+            .unwrap();
+        assert!(file_usage.is_null(0));
+        assert_eq!(file_usage.value_length(1), 2);
+        let usages = file_usage.value(1);
+        let usages = usages.as_any().downcast_ref::<StructArray>().unwrap();
+        let titles = usages
+            .column_by_name("title")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert_eq!(titles.value(0), "Main_page");
+        assert_eq!(titles.value(1), "X");
+    }
+
+    #[test]
+    fn test_record_batch_wikidata_values() {
+        let mut params = enwiki_params();
+        params.set_wikidata_values(vec!["P31".to_string()]);
+        let mut entries = entries();
+        let values = vec!["Q5".to_string(), "Q215627".to_string()];
+        entries[0].set_wikidata_values(Some(HashMap::from([("P31".to_string(), values)])));
+        let batch = RenderParquet::record_batch(&entries, &params).unwrap();
+        let p31 = batch
+            .column_by_name("P31")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<ListArray>()
+            .unwrap();
+        assert_eq!(p31.value_length(0), 2);
+        assert!(p31.is_null(1));
+    }
+
+    #[test]
+    fn test_record_batch_term_columns() {
+        let mut params = enwiki_params();
+        params.set_term_columns(vec!["label_de".to_string(), "aliases_de".to_string()]);
+        let mut entries = entries();
+        entries[0].add_wikidata_term("de", "label", "Hauptseite".to_string());
+        entries[0].add_wikidata_term("de", "alias", "Startseite".to_string());
+        let batch = RenderParquet::record_batch(&entries, &params).unwrap();
+        let labels = batch
+            .column_by_name("label_de")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert_eq!(labels.value(0), "Hauptseite");
+        assert!(labels.is_null(1));
+        let aliases = batch
+            .column_by_name("aliases_de")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<ListArray>()
+            .unwrap();
+        assert_eq!(aliases.value_length(0), 1);
+        assert!(aliases.is_null(1));
+    }
+
+    #[test]
+    fn test_record_batch_sitelink_columns() {
+        let mut params = enwiki_params();
+        params.set_sitelink_columns(vec!["sitelink_dewiki".to_string()]);
+        let mut entries = entries();
+        let sitelinks = HashMap::from([("dewiki".to_string(), "Hauptseite".to_string())]);
+        entries[0].set_sitelinks(Some(sitelinks));
+        let batch = RenderParquet::record_batch(&entries, &params).unwrap();
+        let titles = batch
+            .column_by_name("sitelink_dewiki")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert_eq!(titles.value(0), "Hauptseite");
+        assert!(titles.is_null(1));
+    }
+
+    #[test]
+    fn test_record_batch_lexemes() {
+        let mut params = enwiki_params();
+        *params.add_lexeme_data_mut() = true;
+        let mut entries = entries();
+        entries[0].set_lexeme(Some(Lexeme {
+            lemma: "run".to_string(),
+            language: Some("Q1860".to_string()),
+            lexical_category: None,
+        }));
+        let batch = RenderParquet::record_batch(&entries, &params).unwrap();
+        let column = |name: &str| {
+            batch
+                .column_by_name(name)
+                .unwrap()
+                .as_any()
+                .downcast_ref::<StringArray>()
+                .unwrap()
+                .clone()
+        };
+        assert_eq!(column("lemma").value(0), "run");
+        assert!(column("lemma").is_null(1));
+        assert_eq!(column("lexeme_language").value(0), "Q1860");
+        assert!(column("lexical_category").is_null(0));
+    }
+
+    #[test]
+    fn test_write_parquet_magic() {
+        let batch = RenderParquet::record_batch(&entries(), &enwiki_params()).unwrap();
+        let bytes = RenderParquet::write_parquet(&batch).unwrap();
+        assert_eq!(&bytes[..4], b"PAR1");
+        assert_eq!(&bytes[bytes.len() - 4..], b"PAR1");
+    }
+
+    #[test]
+    fn test_empty_result() {
+        let batch = RenderParquet::record_batch(&[], &enwiki_params()).unwrap();
+        assert_eq!(batch.num_rows(), 0);
+        assert!(RenderParquet::write_parquet(&batch).is_ok());
+    }
+}
@@ -28,6 +28,7 @@ impl Render for RenderPlainText {
             s: output,
             content_type: ContentType::Plain,
             status: 200,
+            binary: None,
         })
     }
 
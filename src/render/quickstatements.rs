@@ -0,0 +1,353 @@
+use crate::content_type::ContentType;
+use crate::pagelist_entry::PageListEntry;
+use crate::platform::{MyResponse, Platform};
+use crate::render::Render;
+use crate::render::params::RenderParams;
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use std::collections::HashMap;
+
+/// Placeholders available in `qs_template`
+const PLACEHOLDERS: &[&str] = &[
+    "item",
+    "title",
+    "full_title",
+    "page",
+    "wiki",
+    "label",
+    "description",
+    "page_image",
+    "page_id",
+    "coordinates",
+];
+
+/// Renders `QuickStatements` V1 commands from the `qs_template` parameter
+///
+/// On Wikidata, `{item}` is the result item itself; on other wikis it is
+/// the page's item, and pages without one are skipped. With
+/// `wikidata_item=without`, each page instead gets a new item (sitelink
+/// and label, as the autolist creator mode does), and `{item}` is `LAST`.
+#[derive(Clone, Copy, Debug)]
+pub struct RenderQuickStatements;
+
+#[async_trait]
+impl Render for RenderQuickStatements {
+    async fn response(
+        &self,
+        platform: &Platform,
+        wiki: &str,
+        entries: Vec<PageListEntry>,
+    ) -> Result<MyResponse> {
+        let params = RenderParams::new(platform, wiki).await?;
+        let template = Self::parse_template(&platform.get_param_blank("qs_template"));
+        let creator_mode =
+            !params.is_wikidata() && platform.get_param_blank("wikidata_item") == "without";
+        if template.is_empty() && !creator_mode {
+            return Err(anyhow!(
+                "format=quickstatements requires a qs_template parameter"
+            ));
+        }
+        let language = if creator_mode {
+            Some(Self::label_language(
+                wiki,
+                &platform.get_param_default("interface_language", "en"),
+            ))
+        } else {
+            None
+        };
+
+        let mut commands = vec![];
+        for entry in &entries {
+            // Likely duplicates; unchecked by default in the HTML creator mode
+            if creator_mode && platform.label_exists(entry.title().pretty()) {
+                continue;
+            }
+            commands.append(&mut Self::commands(
+                entry,
+                &params,
+                &template,
+                language.as_deref(),
+            ));
+        }
+        Ok(MyResponse::ok(commands.join("\n"), ContentType::Plain))
+    }
+}
+
+impl RenderQuickStatements {
+    pub fn new() -> Box<Self> {
+        Box::new(Self {})
+    }
+
+    /// Template lines are separated by newlines or `||`, as in
+    /// `QuickStatements` itself.
+    fn parse_template(template: &str) -> Vec<String> {
+        template
+            .split('\n')
+            .flat_map(|line| line.split("||"))
+            .map(|line| line.trim_end_matches('\r').trim().to_string())
+            .filter(|line| !line.is_empty())
+            .collect()
+    }
+
+    /// Commands for one entry; `creator_language` creates a new item first.
+    fn commands(
+        entry: &PageListEntry,
+        params: &RenderParams,
+        template: &[String],
+        creator_language: Option<&str>,
+    ) -> Vec<String> {
+        let mut commands = vec![];
+        let item = match creator_language {
+            Some(language) => {
+                let page = params
+                    .ns()
+                    .full_pretty(entry.title())
+                    .unwrap_or_else(|| entry.title().pretty().to_string());
+                commands.push("CREATE".to_string());
+                commands.push(format!(
+                    "LAST|S{}|\"{}\"",
+                    params.wiki(),
+                    Self::sanitize(&page)
+                ));
+                commands.push(format!(
+                    "LAST|L{language}|\"{}\"",
+                    Self::sanitize(&Self::creation_label(entry.title().pretty()))
+                ));
+                "LAST".to_string()
+            }
+            None => match Self::item(entry, params) {
+                Some(item) => item,
+                None => return commands,
+            },
+        };
+        let values = Self::values(entry, params, item);
+        commands.extend(template.iter().filter_map(|line| Self::fill(line, &values)));
+        commands
+    }
+
+    fn item(entry: &PageListEntry, params: &RenderParams) -> Option<String> {
+        if !params.is_wikidata() {
+            return entry.get_wikidata_item();
+        }
+        // Items, properties and lexemes
+        match entry.title().namespace_id() {
+            0 | 120 | 146 => Some(entry.title().pretty().to_string()),
+            _ => None,
+        }
+    }
+
+    fn values(
+        entry: &PageListEntry,
+        params: &RenderParams,
+        item: String,
+    ) -> HashMap<&'static str, String> {
+        let title = entry.title();
+        let mut values = HashMap::from([
+            ("item", item),
+            ("title", title.pretty().to_string()),
+            ("wiki", params.wiki().to_string()),
+        ]);
+        if let Some(full_title) = params.ns().full_pretty(title) {
+            values.insert("full_title", full_title);
+        }
+        if let Some(page) = params.ns().full_with_underscores(title) {
+            values.insert("page", page);
+        }
+        if let Some(label) = entry.get_wikidata_label() {
+            values.insert("label", label);
+        }
+        if let Some(description) = entry.get_wikidata_description() {
+            values.insert("description", description);
+        }
+        if let Some(image) = entry.get_page_image() {
+            values.insert("page_image", image);
+        }
+        if let Some(page_id) = entry.page_id() {
+            values.insert("page_id", page_id.to_string());
+        }
+        if let Some(coords) = entry.get_coordinates() {
+            values.insert("coordinates", format!("@{}/{}", coords.lat, coords.lon));
+        }
+        values
+    }
+
+    /// Replaces all placeholders in a template line. Lines using a
+    /// placeholder without a value for this entry are dropped, rather than
+    /// emitted as broken commands.
+    fn fill(line: &str, values: &HashMap<&'static str, String>) -> Option<String> {
+        let mut ret = line.to_string();
+        for key in PLACEHOLDERS {
+            let placeholder = format!("{{{key}}}");
+            if ret.contains(&placeholder) {
+                ret = ret.replace(&placeholder, &Self::sanitize(values.get(key)?));
+            }
+        }
+        Some(ret)
+    }
+
+    /// V1 has no escaping: a TAB, newline or `|` in a value would start a
+    /// new field or command, and a `"` would end a string. Separators
+    /// become spaces, double quotes single ones.
+    pub(crate) fn sanitize(value: &str) -> String {
+        value
+            .chars()
+            .map(|c| match c {
+                '\t' | '\n' | '\r' | '|' => ' ',
+                '"' => '\'',
+                c => c,
+            })
+            .collect()
+    }
+
+    /// Label language for new items: the wiki language for Wikipedias,
+    /// the interface language otherwise (as `autolist.js` does).
+    fn label_language(wiki: &str, interface_language: &str) -> String {
+        match wiki.strip_suffix("wiki") {
+            Some(language)
+                if !language.is_empty()
+                    && !matches!(language, "commons" | "meta" | "species" | "wikidata")
+                    && language.chars().all(|c| c.is_ascii_lowercase() || c == '_') =>
+            {
+                language.replace('_', "-")
+            }
+            _ => interface_language.to_string(),
+        }
+    }
+
+    /// Page title without disambiguation, e.g. "Foo (band)" => "Foo".
+    fn creation_label(title: &str) -> String {
+        match (title.find(" ("), title.find(')')) {
+            (Some(start), Some(end)) if start < end => {
+                format!("{} {}", title[..start].trim(), title[end + 1..].trim())
+                    .trim()
+                    .to_string()
+            }
+            _ => title.trim().to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::StubNamespaceContext;
+    use std::sync::Arc;
+    use wikimisc::mediawiki::title::Title;
+
+    fn params(wiki: &str) -> RenderParams {
+        RenderParams::for_tests(wiki, Arc::new(StubNamespaceContext::enwiki()))
+    }
+
+    #[test]
+    fn test_parse_template() {
+        assert_eq!(
+            RenderQuickStatements::parse_template(
+                "LAST|P31|Q5\r\n\n{item}|P18|\"{page_image}\"||x"
+            ),
+            vec!["LAST|P31|Q5", "{item}|P18|\"{page_image}\"", "x"]
+        );
+        assert!(RenderQuickStatements::parse_template("  \n").is_empty());
+    }
+
+    #[test]
+    fn test_fill() {
+        let values = HashMap::from([("item", "Q42".to_string())]);
+        assert_eq!(
+            RenderQuickStatements::fill("{item}|P31|Q5", &values),
+            Some("Q42|P31|Q5".to_string())
+        );
+        assert_eq!(
+            RenderQuickStatements::fill("{item}|P18|\"{page_image}\"", &values),
+            None
+        );
+        assert_eq!(
+            RenderQuickStatements::fill("{unknown}", &values),
+            Some("{unknown}".to_string())
+        );
+    }
+
+    #[test]
+    fn test_fill_sanitizes_values() {
+        let values = HashMap::from([
+            ("item", "Q42".to_string()),
+            ("label", "a \"b\"\tQ1|P31|Q5\nCREATE||LAST".to_string()),
+        ]);
+        assert_eq!(
+            RenderQuickStatements::fill("{item}|Den|\"{label}\"", &values),
+            Some("Q42|Den|\"a 'b' Q1 P31 Q5 CREATE  LAST\"".to_string())
+        );
+    }
+
+    #[test]
+    fn test_commands_wikidata() {
+        let template = vec!["{item}|P31|Q5".to_string()];
+        let entry = PageListEntry::new(Title::new("Q42", 0));
+        assert_eq!(
+            RenderQuickStatements::commands(&entry, &params("wikidatawiki"), &template, None),
+            vec!["Q42|P31|Q5"]
+        );
+        let user_page = PageListEntry::new(Title::new("Magnus", 2));
+        assert!(
+            RenderQuickStatements::commands(&user_page, &params("wikidatawiki"), &template, None)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_commands_page_with_and_without_item() {
+        let template = vec!["{item}|P18|\"{page_image}\"".to_string()];
+        let mut entry = PageListEntry::new(Title::new("Douglas Adams", 0));
+        assert!(
+            RenderQuickStatements::commands(&entry, &params("enwiki"), &template, None).is_empty()
+        );
+        entry.set_wikidata_item(Some("Q42".to_string()));
+        entry.set_page_image(Some("Douglas adams portrait.jpg".to_string()));
+        assert_eq!(
+            RenderQuickStatements::commands(&entry, &params("enwiki"), &template, None),
+            vec!["Q42|P18|\"Douglas adams portrait.jpg\""]
+        );
+    }
+
+    #[test]
+    fn test_commands_creator() {
+        let template = vec!["LAST|P31|Q5".to_string(), "{item}|P31|Q5".to_string()];
+        let entry = PageListEntry::new(Title::new("Some person", 0));
+        assert_eq!(
+            RenderQuickStatements::commands(&entry, &params("enwiki"), &template, Some("en")),
+            vec![
+                "CREATE",
+                "LAST|Senwiki|\"Some person\"",
+                "LAST|Len|\"Some person\"",
+                "LAST|P31|Q5",
+                "LAST|P31|Q5",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_label_language() {
+        assert_eq!(RenderQuickStatements::label_language("dewiki", "en"), "de");
+        assert_eq!(
+            RenderQuickStatements::label_language("be_x_oldwiki", "en"),
+            "be-x-old"
+        );
+        assert_eq!(
+            RenderQuickStatements::label_language("commonswiki", "fr"),
+            "fr"
+        );
+        assert_eq!(
+            RenderQuickStatements::label_language("enwikisource", "fr"),
+            "fr"
+        );
+    }
+
+    #[test]
+    fn test_creation_label() {
+        assert_eq!(RenderQuickStatements::creation_label("Foo (band)"), "Foo");
+        assert_eq!(
+            RenderQuickStatements::creation_label("Foo (band) live"),
+            "Foo live"
+        );
+        assert_eq!(RenderQuickStatements::creation_label("Foo"), "Foo");
+    }
+}
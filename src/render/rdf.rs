@@ -0,0 +1,472 @@
+use crate::content_type::ContentType;
+use crate::pagelist::WikidataEntityType;
+use crate::pagelist_entry::PageListEntry;
+use crate::platform::{MyResponse, Platform};
+use crate::render::Render;
+use crate::render::params::RenderParams;
+use crate::wikidata_entities::{is_item_id, is_property_id};
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC, utf8_percent_encode};
+use std::borrow::Cow;
+
+const WIKIDATA_ENTITY: &str = "http://www.wikidata.org/entity/";
+
+/// Characters that `wfUrlencode` leaves as they are in page URLs, so page
+/// IRIs match the `schema:about` sitelink IRIs of WDQS
+const WIKI_URL_SAFE: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b';')
+    .remove(b'@')
+    .remove(b'$')
+    .remove(b'!')
+    .remove(b'*')
+    .remove(b'(')
+    .remove(b')')
+    .remove(b',')
+    .remove(b'/')
+    .remove(b'~')
+    .remove(b':');
+
+/// Prefixes for the vocabularies used; predicates are written as CURIEs
+/// and expanded for N-Triples. `petscan:namespace` (the namespace ID of a
+/// page) is the only term without a standard equivalent.
+const PREFIXES: &[(&str, &str)] = &[
+    ("ontolex", "http://www.w3.org/ns/lemon/ontolex#"),
+    ("petscan", "https://petscan.wmcloud.org/ontology#"),
+    ("rdf", "http://www.w3.org/1999/02/22-rdf-syntax-ns#"),
+    ("rdfs", "http://www.w3.org/2000/01/rdf-schema#"),
+    ("schema", "http://schema.org/"),
+    ("wdt", "http://www.wikidata.org/prop/direct/"),
+    ("wikibase", "http://wikiba.se/ontology#"),
+    ("xsd", "http://www.w3.org/2001/XMLSchema#"),
+];
+
+/// Predicate CURIE and object
+type Triple = (Cow<'static, str>, Term);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RdfSyntax {
+    NTriples,
+    Turtle,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Term {
+    Iri(String),
+    /// A CURIE such as `schema:Article`
+    Curie(&'static str),
+    Literal(String),
+    /// Lexical value and datatype CURIE
+    Typed(String, &'static str),
+}
+
+/// Renders RDF (N-Triples or Turtle)
+#[derive(Clone, Copy, Debug)]
+pub struct RenderRDF {
+    syntax: RdfSyntax,
+}
+
+#[async_trait]
+impl Render for RenderRDF {
+    async fn response(
+        &self,
+        platform: &Platform,
+        wiki: &str,
+        entries: Vec<PageListEntry>,
+    ) -> Result<MyResponse> {
+        let params = RenderParams::new(platform, wiki).await?;
+        let server = params
+            .state()
+            .site_matrix()
+            .get_server_url_for_wiki(wiki)
+            .unwrap_or_default();
+        let mut out = String::new();
+        if self.syntax == RdfSyntax::Turtle {
+            for (prefix, iri) in PREFIXES {
+                out += &format!("@prefix {prefix}: <{iri}> .\n");
+            }
+        }
+        for entry in &entries {
+            let (subject, triples) = Self::resource(entry, &params, &server);
+            out += &match self.syntax {
+                RdfSyntax::NTriples => Self::ntriples(&subject, &triples),
+                RdfSyntax::Turtle => Self::turtle(&subject, &triples),
+            };
+        }
+        let content_type = match self.syntax {
+            RdfSyntax::NTriples => ContentType::NTriples,
+            RdfSyntax::Turtle => ContentType::Turtle,
+        };
+        Ok(MyResponse::ok(out, content_type))
+    }
+}
+
+impl RenderRDF {
+    pub fn new(syntax: RdfSyntax) -> Box<Self> {
+        Box::new(Self { syntax })
+    }
+
+    /// Subject IRI and `(predicate, object)` pairs for one entry. Items on
+    /// Wikidata are described as entities; all other pages by their URL,
+    /// linked to their item via `schema:about`.
+    fn resource(
+        entry: &PageListEntry,
+        params: &RenderParams,
+        server: &str,
+    ) -> (String, Vec<Triple>) {
+        let title = entry.title();
+        let full_title = params
+            .ns()
+            .full_with_underscores(title)
+            .unwrap_or_else(|| title.with_underscores());
+        let page_url = format!("{server}/wiki/{}", Self::wiki_url_encode(&full_title));
+        let entity = Self::wikidata_entity(entry, params);
+        let mut triples = vec![];
+        let subject = match (&entity, params.is_wikidata()) {
+            (Some(entity), true) => {
+                let entity_type = match WikidataEntityType::from_namespace_id(title.namespace_id())
+                {
+                    Some(WikidataEntityType::Property) => "wikibase:Property",
+                    Some(WikidataEntityType::Lexeme) => "ontolex:LexicalEntry",
+                    _ => "wikibase:Item",
+                };
+                triples.push(("rdf:type".into(), Term::Curie(entity_type)));
+                triples.push(("schema:url".into(), Term::Iri(page_url)));
+                entity.to_owned()
+            }
+            _ => {
+                triples.push(("rdf:type".into(), Term::Curie("schema:Article")));
+                triples.push(("schema:isPartOf".into(), Term::Iri(format!("{server}/"))));
+                if let Some(entity) = &entity {
+                    triples.push(("schema:about".into(), Term::Iri(entity.to_owned())));
+                }
+                page_url
+            }
+        };
+
+        let name = params
+            .ns()
+            .full_pretty(title)
+            .unwrap_or_else(|| title.pretty().to_string());
+        triples.push(("schema:name".into(), Term::Literal(name)));
+        triples.push((
+            "petscan:namespace".into(),
+            Term::Typed(title.namespace_id().to_string(), "xsd:integer"),
+        ));
+        if let Some(page_id) = entry.page_id() {
+            triples.push((
+                "schema:identifier".into(),
+                Term::Typed(page_id.to_string(), "xsd:integer"),
+            ));
+        }
+        // The size of a file page is that of the file, if known
+        let size = match entry.get_file_info().and_then(|fi| fi.img_size) {
+            Some(file_size) => Some(file_size.to_string()),
+            None => entry.page_bytes().map(|bytes| bytes.to_string()),
+        };
+        if let Some(size) = size {
+            triples.push((
+                "schema:contentSize".into(),
+                Term::Typed(size, "xsd:integer"),
+            ));
+        }
+        if let Some(ts) = entry
+            .get_page_timestamp()
+            .and_then(|ts| Self::datetime(&ts))
+        {
+            triples.push((
+                "schema:dateModified".into(),
+                Term::Typed(ts, "xsd:dateTime"),
+            ));
+        }
+        if let Some(label) = entry.get_wikidata_label() {
+            triples.push(("rdfs:label".into(), Term::Literal(label)));
+        }
+        if let Some(description) = entry.get_wikidata_description() {
+            triples.push(("schema:description".into(), Term::Literal(description)));
+        }
+        if let Some(coords) = entry.get_coordinates() {
+            triples.push((
+                "schema:latitude".into(),
+                Term::Typed(coords.lat.to_string(), "xsd:double"),
+            ));
+            triples.push((
+                "schema:longitude".into(),
+                Term::Typed(coords.lon.to_string(), "xsd:double"),
+            ));
+        }
+        if let Some(image) = entry.get_page_image() {
+            triples.push((
+                "schema:image".into(),
+                Term::Iri(format!(
+                    "{server}/wiki/Special:FilePath/{}",
+                    Self::wiki_url_encode(&image)
+                )),
+            ));
+        }
+        if let Some(fi) = entry.get_file_info() {
+            if let Some(width) = fi.img_width {
+                triples.push((
+                    "schema:width".into(),
+                    Term::Typed(width.to_string(), "xsd:integer"),
+                ));
+            }
+            if let Some(height) = fi.img_height {
+                triples.push((
+                    "schema:height".into(),
+                    Term::Typed(height.to_string(), "xsd:integer"),
+                ));
+            }
+            if let (Some(major), Some(minor)) = (&fi.img_major_mime, &fi.img_minor_mime) {
+                triples.push((
+                    "schema:encodingFormat".into(),
+                    Term::Literal(format!("{major}/{minor}")),
+                ));
+            }
+            if let Some(ts) = fi.img_timestamp.as_deref().and_then(Self::datetime) {
+                triples.push(("schema:uploadDate".into(), Term::Typed(ts, "xsd:dateTime")));
+            }
+        }
+        for property in params.wikidata_values() {
+            for value in entry.get_wikidata_values(property).unwrap_or_default() {
+                triples.push((format!("wdt:{property}").into(), Self::value_term(value)));
+            }
+        }
+        (subject, triples)
+    }
+
+    /// Item and property values as entity IRIs, everything else as a literal
+    fn value_term(value: &str) -> Term {
+        if is_item_id(value) || is_property_id(value) {
+            Term::Iri(format!("{WIKIDATA_ENTITY}{value}"))
+        } else {
+            Term::Literal(value.to_string())
+        }
+    }
+
+    /// Entity IRI of the item, or of the page itself on Wikidata.
+    fn wikidata_entity(entry: &PageListEntry, params: &RenderParams) -> Option<String> {
+        let id = match entry.get_wikidata_item() {
+            Some(q) => q,
+            // Items, properties and lexemes
+            None if params.is_wikidata()
+                && matches!(entry.title().namespace_id(), 0 | 120 | 146) =>
+            {
+                entry.title().pretty().to_string()
+            }
+            None => return None,
+        };
+        Some(format!("{WIKIDATA_ENTITY}{id}"))
+    }
+
+    /// Percent-encodes a title for a page URL the way `MediaWiki` does
+    fn wiki_url_encode(title: &str) -> String {
+        utf8_percent_encode(title, WIKI_URL_SAFE).to_string()
+    }
+
+    /// `MediaWiki` timestamp (`YYYYMMDDHHMMSS`) as `xsd:dateTime`.
+    fn datetime(ts: &str) -> Option<String> {
+        NaiveDateTime::parse_from_str(ts, "%Y%m%d%H%M%S")
+            .ok()
+            .map(|dt| dt.format("%Y-%m-%dT%H:%M:%SZ").to_string())
+    }
+
+    fn ntriples(subject: &str, triples: &[Triple]) -> String {
+        triples
+            .iter()
+            .map(|(predicate, object)| {
+                format!(
+                    "<{subject}> <{}> {} .\n",
+                    Self::expand(predicate),
+                    Self::term(object, RdfSyntax::NTriples)
+                )
+            })
+            .collect()
+    }
+
+    fn turtle(subject: &str, triples: &[Triple]) -> String {
+        let predicates: Vec<String> = triples
+            .iter()
+            .map(|(predicate, object)| {
+                let predicate = match predicate.as_ref() {
+                    "rdf:type" => "a",
+                    other => other,
+                };
+                format!("{predicate} {}", Self::term(object, RdfSyntax::Turtle))
+            })
+            .collect();
+        format!("\n<{subject}> {} .\n", predicates.join(" ;\n    "))
+    }
+
+    fn term(term: &Term, syntax: RdfSyntax) -> String {
+        let curie = |c: &str| match syntax {
+            RdfSyntax::NTriples => format!("<{}>", Self::expand(c)),
+            RdfSyntax::Turtle => c.to_string(),
+        };
+        match term {
+            Term::Iri(iri) => format!("<{iri}>"),
+            Term::Curie(c) => curie(c),
+            Term::Literal(s) => format!("\"{}\"", Self::escape_literal(s)),
+            Term::Typed(s, datatype) => {
+                format!("\"{}\"^^{}", Self::escape_literal(s), curie(datatype))
+            }
+        }
+    }
+
+    fn expand(curie: &str) -> String {
+        match curie.split_once(':') {
+            Some((prefix, local)) => match PREFIXES.iter().find(|(p, _)| *p == prefix) {
+                Some((_, iri)) => format!("{iri}{local}"),
+                None => curie.to_string(),
+            },
+            None => curie.to_string(),
+        }
+    }
+
+    fn escape_literal(s: &str) -> String {
+        s.replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+            .replace('\r', "\\r")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::StubNamespaceContext;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use wikimisc::mediawiki::title::Title;
+
+    const SERVER: &str = "https://en.wikipedia.org";
+
+    fn params(wiki: &str) -> RenderParams {
+        RenderParams::for_tests(wiki, Arc::new(StubNamespaceContext::enwiki()))
+    }
+
+    fn article() -> PageListEntry {
+        let mut entry = PageListEntry::new(Title::new("Douglas Adams", 0));
+        entry.set_page_id(Some(8091));
+        entry.set_page_bytes(Some(12345));
+        entry.set_page_timestamp(Some("20240102030405".to_string()));
+        entry.set_wikidata_item(Some("Q42".to_string()));
+        entry
+    }
+
+    #[test]
+    fn test_resource_page() {
+        let (subject, triples) = RenderRDF::resource(&article(), &params("enwiki"), SERVER);
+        assert_eq!(subject, "https://en.wikipedia.org/wiki/Douglas_Adams");
+        assert!(triples.contains(&("rdf:type".into(), Term::Curie("schema:Article"))));
+        assert!(triples.contains(&(
+            "schema:about".into(),
+            Term::Iri("http://www.wikidata.org/entity/Q42".to_string())
+        )));
+        assert!(triples.contains(&(
+            "schema:dateModified".into(),
+            Term::Typed("2024-01-02T03:04:05Z".to_string(), "xsd:dateTime")
+        )));
+        assert!(triples.contains(&(
+            "schema:contentSize".into(),
+            Term::Typed("12345".to_string(), "xsd:integer")
+        )));
+    }
+
+    #[test]
+    fn test_wiki_url_encode() {
+        assert_eq!(
+            RenderRDF::wiki_url_encode("Category:Foo_(bar),_baz/qux"),
+            "Category:Foo_(bar),_baz/qux"
+        );
+        assert_eq!(RenderRDF::wiki_url_encode("Köln"), "K%C3%B6ln");
+        assert_eq!(
+            RenderRDF::wiki_url_encode("A&B?C#D\"E%"),
+            "A%26B%3FC%23D%22E%25"
+        );
+    }
+
+    #[test]
+    fn test_resource_wikidata_item() {
+        let entry = PageListEntry::new(Title::new("Q42", 0));
+        let (subject, triples) = RenderRDF::resource(&entry, &params("wikidatawiki"), SERVER);
+        assert_eq!(subject, "http://www.wikidata.org/entity/Q42");
+        assert!(triples.contains(&("rdf:type".into(), Term::Curie("wikibase:Item"))));
+
+        let entry = PageListEntry::new(Title::new("P31", 120));
+        let (subject, triples) = RenderRDF::resource(&entry, &params("wikidatawiki"), SERVER);
+        assert_eq!(subject, "http://www.wikidata.org/entity/P31");
+        assert!(triples.contains(&("rdf:type".into(), Term::Curie("wikibase:Property"))));
+
+        let entry = PageListEntry::new(Title::new("L7", 146));
+        let (_subject, triples) = RenderRDF::resource(&entry, &params("wikidatawiki"), SERVER);
+        assert!(triples.contains(&("rdf:type".into(), Term::Curie("ontolex:LexicalEntry"))));
+    }
+
+    #[test]
+    fn test_resource_wikidata_values() {
+        let mut params = params("enwiki");
+        params.set_wikidata_values(vec!["P31".to_string(), "P569".to_string()]);
+        let mut entry = article();
+        let values = HashMap::from([
+            ("P31".to_string(), vec!["Q5".to_string()]),
+            ("P569".to_string(), vec!["1952-03-11T00:00:00Z".to_string()]),
+        ]);
+        entry.set_wikidata_values(Some(values));
+        let (_subject, triples) = RenderRDF::resource(&entry, &params, SERVER);
+        assert!(triples.contains(&(
+            "wdt:P31".into(),
+            Term::Iri("http://www.wikidata.org/entity/Q5".to_string())
+        )));
+        assert!(triples.contains(&(
+            "wdt:P569".into(),
+            Term::Literal("1952-03-11T00:00:00Z".to_string())
+        )));
+    }
+
+    #[test]
+    fn test_ntriples() {
+        let triples: Vec<Triple> = vec![
+            ("rdf:type".into(), Term::Curie("schema:Article")),
+            (
+                "schema:name".into(),
+                Term::Literal("A \"quoted\"\nname".to_string()),
+            ),
+            (
+                "schema:identifier".into(),
+                Term::Typed("1".to_string(), "xsd:integer"),
+            ),
+        ];
+        assert_eq!(
+            RenderRDF::ntriples("http://x/", &triples),
+            "<http://x/> <http://www.w3.org/1999/02/22-rdf-syntax-ns#type> <http://schema.org/Article> .\n\
+             <http://x/> <http://schema.org/name> \"A \\\"quoted\\\"\\nname\" .\n\
+             <http://x/> <http://schema.org/identifier> \"1\"^^<http://www.w3.org/2001/XMLSchema#integer> .\n"
+        );
+    }
+
+    #[test]
+    fn test_turtle() {
+        let triples: Vec<Triple> = vec![
+            ("rdf:type".into(), Term::Curie("schema:Article")),
+            (
+                "schema:identifier".into(),
+                Term::Typed("1".to_string(), "xsd:integer"),
+            ),
+        ];
+        assert_eq!(
+            RenderRDF::turtle("http://x/", &triples),
+            "\n<http://x/> a schema:Article ;\n    schema:identifier \"1\"^^xsd:integer .\n"
+        );
+    }
+
+    #[test]
+    fn test_expand() {
+        assert_eq!(RenderRDF::expand("schema:name"), "http://schema.org/name");
+        assert_eq!(RenderRDF::expand("unknown:x"), "unknown:x");
+    }
+}
@@ -0,0 +1,283 @@
+use crate::content_type::ContentType;
+use crate::datasource::{SQLtuple, get_placeholders};
+use crate::pagelist::PageList;
+use crate::pagelist_entry::PageListEntry;
+use crate::platform::{MyResponse, PAGE_BATCH_SIZE, Platform};
+use crate::render::Render;
+use crate::render::params::RenderParams;
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use mysql_async as my;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Groupings that can be requested via `stats_group`
+const GROUPS: &[&str] = &[
+    "namespace",
+    "wikidata",
+    "size",
+    "month",
+    "media_type",
+    "category",
+];
+
+/// Groupings used when `stats_group` is not given; they need no extra data
+const DEFAULT_GROUPS: &[&str] = &["namespace", "wikidata", "size", "month"];
+
+/// Page size buckets, in bytes, in output order
+const SIZE_BUCKETS: &[&str] = &["0-999", "1000-9999", "10000-99999", "100000+", "unknown"];
+
+/// Only the most common categories are listed
+const MAX_CATEGORY_GROUPS: usize = 500;
+
+/// Renders counts of the entries, grouped as set by `stats_group`,
+/// instead of the entries themselves
+#[derive(Clone, Copy, Debug)]
+pub struct RenderStats;
+
+#[async_trait]
+impl Render for RenderStats {
+    async fn response(
+        &self,
+        platform: &Platform,
+        wiki: &str,
+        entries: Vec<PageListEntry>,
+    ) -> Result<MyResponse> {
+        let params = RenderParams::new(platform, wiki).await?;
+        let groups = Self::parse_groups(&platform.get_param_as_vec("stats_group", ","))?;
+
+        let mut j = json!({"wiki": wiki, "total": entries.len(), "groups": {}});
+        for group in groups {
+            let counts = if group == "category" {
+                Self::category_counts(platform, wiki, &entries).await?
+            } else {
+                Self::group_counts(&entries, group, &params)
+            };
+            j["groups"][group] = counts
+                .into_iter()
+                .map(|(key, count)| {
+                    let mut o = json!({"key": key, "count": count});
+                    if group == "namespace"
+                        && let Ok(namespace_id) = key.parse()
+                    {
+                        o["label"] = json!(params.ns().local_namespace_name(namespace_id));
+                    }
+                    o
+                })
+                .collect::<Value>();
+        }
+
+        let s = if params.json_pretty() {
+            ::serde_json::to_string_pretty(&j)?
+        } else {
+            ::serde_json::to_string(&j)?
+        };
+        Ok(MyResponse::ok(s, ContentType::JSON))
+    }
+}
+
+impl RenderStats {
+    pub fn new() -> Box<Self> {
+        Box::new(Self {})
+    }
+
+    fn parse_groups(requested: &[String]) -> Result<Vec<&'static str>> {
+        if requested.is_empty() {
+            return Ok(DEFAULT_GROUPS.to_vec());
+        }
+        let mut ret = vec![];
+        for group in requested {
+            match GROUPS.iter().find(|g| **g == group.as_str()) {
+                Some(g) if !ret.contains(g) => ret.push(*g),
+                Some(_) => {}
+                None => return Err(anyhow!("Unknown stats_group '{group}'")),
+            }
+        }
+        Ok(ret)
+    }
+
+    fn size_bucket(bytes: Option<u32>) -> &'static str {
+        match bytes {
+            Some(0..1_000) => SIZE_BUCKETS[0],
+            Some(1_000..10_000) => SIZE_BUCKETS[1],
+            Some(10_000..100_000) => SIZE_BUCKETS[2],
+            Some(_) => SIZE_BUCKETS[3],
+            None => SIZE_BUCKETS[4],
+        }
+    }
+
+    fn group_key(entry: &PageListEntry, group: &str, params: &RenderParams) -> String {
+        match group {
+            "namespace" => entry.title().namespace_id().to_string(),
+            "wikidata" => {
+                let has_item = entry.get_wikidata_item().is_some()
+                    || (params.is_wikidata() && entry.title().namespace_id() == 0);
+                if has_item { "with" } else { "without" }.to_string()
+            }
+            "size" => Self::size_bucket(entry.page_bytes()).to_string(),
+            "month" => match entry.get_page_timestamp() {
+                Some(ts) if ts.len() >= 6 => format!("{}-{}", &ts[0..4], &ts[4..6]),
+                _ => "unknown".to_string(),
+            },
+            "media_type" => entry
+                .get_file_info()
+                .and_then(|fi| fi.img_media_type)
+                .unwrap_or_else(|| "unknown".to_string()),
+            _ => String::new(),
+        }
+    }
+
+    /// Counts per key, in the natural order of the group: namespaces by
+    /// ID, sizes by bucket, months by date; others by descending count.
+    fn group_counts(
+        entries: &[PageListEntry],
+        group: &str,
+        params: &RenderParams,
+    ) -> Vec<(String, usize)> {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for entry in entries {
+            *counts
+                .entry(Self::group_key(entry, group, params))
+                .or_default() += 1;
+        }
+        let mut ret: Vec<(String, usize)> = counts.into_iter().collect();
+        match group {
+            "namespace" => ret.sort_by_key(|(key, _)| key.parse::<i64>().unwrap_or(i64::MAX)),
+            "size" => ret.sort_by_key(|(key, _)| SIZE_BUCKETS.iter().position(|b| b == key)),
+            // "unknown" sorts after all dates
+            "month" => ret.sort(),
+            _ => Self::sort_by_count(&mut ret),
+        }
+        ret
+    }
+
+    fn sort_by_count(counts: &mut [(String, usize)]) {
+        counts.sort_by(|(k1, c1), (k2, c2)| c2.cmp(c1).then_with(|| k1.cmp(k2)));
+    }
+
+    /// Number of entries in each category, most common first.
+    async fn category_counts(
+        platform: &Platform,
+        wiki: &str,
+        entries: &[PageListEntry],
+    ) -> Result<Vec<(String, usize)>> {
+        let page_ids: Vec<my::Value> = entries
+            .iter()
+            .filter_map(|entry| entry.page_id())
+            .map(my::Value::from)
+            .collect();
+        let batches: Vec<SQLtuple> = page_ids
+            .chunks(PAGE_BATCH_SIZE)
+            .map(|chunk| {
+                let sql = format!(
+                    "SELECT lt_title,count(*) FROM categorylinks,linktarget WHERE lt_id=cl_target_id AND lt_namespace=14 AND cl_from IN ({}) GROUP BY lt_title",
+                    get_placeholders(chunk.len())
+                );
+                (sql, chunk.to_vec())
+            })
+            .collect();
+        let rows = PageList::new_from_wiki(wiki)
+            .run_batch_queries(&platform.state(), batches)
+            .await?;
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for row in rows {
+            let (category, count) = my::from_row::<(Vec<u8>, usize)>(row);
+            *counts
+                .entry(String::from_utf8_lossy(&category).to_string())
+                .or_default() += count;
+        }
+        let mut ret: Vec<(String, usize)> = counts.into_iter().collect();
+        Self::sort_by_count(&mut ret);
+        ret.truncate(MAX_CATEGORY_GROUPS);
+        Ok(ret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::StubNamespaceContext;
+    use std::sync::Arc;
+    use wikimisc::mediawiki::title::Title;
+
+    fn params(wiki: &str) -> RenderParams {
+        RenderParams::for_tests(wiki, Arc::new(StubNamespaceContext::enwiki()))
+    }
+
+    fn entry(title: &str, ns: i64, bytes: Option<u32>, timestamp: Option<&str>) -> PageListEntry {
+        let mut entry = PageListEntry::new(Title::new(title, ns));
+        entry.set_page_bytes(bytes);
+        entry.set_page_timestamp(timestamp.map(|s| s.to_string()));
+        entry
+    }
+
+    fn counts(v: &[(&str, usize)]) -> Vec<(String, usize)> {
+        v.iter().map(|(k, c)| (k.to_string(), *c)).collect()
+    }
+
+    #[test]
+    fn test_parse_groups() {
+        assert_eq!(RenderStats::parse_groups(&[]).unwrap(), DEFAULT_GROUPS);
+        let requested = vec![
+            "category".to_string(),
+            "size".to_string(),
+            "category".to_string(),
+        ];
+        assert_eq!(
+            RenderStats::parse_groups(&requested).unwrap(),
+            vec!["category", "size"]
+        );
+        assert!(RenderStats::parse_groups(&["bogus".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_size_bucket() {
+        assert_eq!(RenderStats::size_bucket(Some(0)), "0-999");
+        assert_eq!(RenderStats::size_bucket(Some(1_000)), "1000-9999");
+        assert_eq!(RenderStats::size_bucket(Some(99_999)), "10000-99999");
+        assert_eq!(RenderStats::size_bucket(Some(100_000)), "100000+");
+        assert_eq!(RenderStats::size_bucket(None), "unknown");
+    }
+
+    #[test]
+    fn test_group_counts() {
+        let mut with_item = entry("A", 0, Some(50_000), Some("20240102030405"));
+        with_item.set_wikidata_item(Some("Q1".to_string()));
+        let entries = vec![
+            with_item,
+            entry("B", 14, Some(10), Some("20231231000000")),
+            entry("C", 0, None, None),
+            entry("D", 2, Some(500), Some("20240115000000")),
+        ];
+        let params = params("enwiki");
+        assert_eq!(
+            RenderStats::group_counts(&entries, "namespace", &params),
+            counts(&[("0", 2), ("2", 1), ("14", 1)])
+        );
+        assert_eq!(
+            RenderStats::group_counts(&entries, "wikidata", &params),
+            counts(&[("without", 3), ("with", 1)])
+        );
+        assert_eq!(
+            RenderStats::group_counts(&entries, "size", &params),
+            counts(&[("0-999", 2), ("10000-99999", 1), ("unknown", 1)])
+        );
+        assert_eq!(
+            RenderStats::group_counts(&entries, "month", &params),
+            counts(&[("2023-12", 1), ("2024-01", 2), ("unknown", 1)])
+        );
+        assert_eq!(
+            RenderStats::group_counts(&entries, "media_type", &params),
+            counts(&[("unknown", 4)])
+        );
+    }
+
+    #[test]
+    fn test_wikidata_items_have_an_item() {
+        let entries = vec![entry("Q42", 0, None, None)];
+        assert_eq!(
+            RenderStats::group_counts(&entries, "wikidata", &params("wikidatawiki")),
+            counts(&[("with", 1)])
+        );
+    }
+}
@@ -0,0 +1,342 @@
+use crate::content_type::ContentType;
+use crate::pagelist_entry::PageListEntry;
+use crate::platform::{MyResponse, Platform};
+use crate::render::params::RenderParams;
+use crate::render::{COLUMNS, FILE_DATA_KEYS, Render, sitelink_placeholders, term_placeholders};
+use crate::wikidata_entities::property_placeholders;
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use htmlescape::encode_minimal;
+use std::collections::HashMap;
+
+/// How placeholder values are escaped before they go into the template
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Escaping {
+    Wikitext,
+    Html,
+    Plain,
+}
+
+impl Escaping {
+    fn from_param(s: &str) -> Result<Self> {
+        match s {
+            "" | "wikitext" => Ok(Self::Wikitext),
+            "html" => Ok(Self::Html),
+            "plain" => Ok(Self::Plain),
+            other => Err(anyhow!("Unknown template_escape '{other}'")),
+        }
+    }
+
+    fn escape(self, s: &str) -> String {
+        match self {
+            // Characters that would open or close links, templates,
+            // tables or tags; titles are safe inside `[[...]]` this way
+            Self::Wikitext => s
+                .chars()
+                .map(|c| match c {
+                    '[' => "&#91;".to_string(),
+                    ']' => "&#93;".to_string(),
+                    '{' => "&#123;".to_string(),
+                    '}' => "&#125;".to_string(),
+                    '|' => "&#124;".to_string(),
+                    '<' => "&lt;".to_string(),
+                    '>' => "&gt;".to_string(),
+                    '\'' => "&#39;".to_string(),
+                    '~' => "&#126;".to_string(),
+                    c => c.to_string(),
+                })
+                .collect(),
+            Self::Html => encode_minimal(s),
+            Self::Plain => s.to_string(),
+        }
+    }
+}
+
+/// Renders one line per entry from the `row_template` parameter, with
+/// optional `header_template` and `footer_template`
+///
+/// Row placeholders are the column names (`{title}`, `{size}`,
+/// `{wikidata_item}`, ...), Wikidata properties (`{P31}`, values joined
+/// by `|`), terms (`{label_de}`, `{aliases_de}`) and sitelink titles
+/// (`{sitelink_dewiki}`); header and footer know `{count}` and `{wiki}`.
+/// Values are escaped as set by `template_escape` (`wikitext`, `html` or
+/// `plain`). The output is always served as plain text: the templates come
+/// from the query string, so serving them as HTML would allow XSS.
+#[derive(Clone, Copy, Debug)]
+pub struct RenderTemplate;
+
+#[async_trait]
+impl Render for RenderTemplate {
+    async fn response(
+        &self,
+        platform: &Platform,
+        wiki: &str,
+        entries: Vec<PageListEntry>,
+    ) -> Result<MyResponse> {
+        let params = RenderParams::new(platform, wiki).await?;
+        self.render_rows(platform, wiki, &entries, params)
+    }
+
+    fn render_cell_title(&self, entry: &PageListEntry, params: &RenderParams) -> String {
+        params
+            .ns()
+            .full_pretty(entry.title())
+            .unwrap_or_else(|| entry.title().pretty().to_string())
+    }
+
+    fn render_cell_wikidata_item(&self, entry: &PageListEntry, params: &RenderParams) -> String {
+        if params.is_wikidata() && entry.title().namespace_id() == 0 {
+            return entry.title().pretty().to_string();
+        }
+        entry.get_wikidata_item().unwrap_or_default()
+    }
+
+    fn render_user_name(&self, user: &str, _params: &RenderParams) -> String {
+        user.to_string()
+    }
+
+    fn render_cell_image(&self, image: &Option<String>, _params: &RenderParams) -> String {
+        image.to_owned().unwrap_or_default()
+    }
+
+    fn render_cell_namespace(&self, entry: &PageListEntry, params: &RenderParams) -> String {
+        params
+            .ns()
+            .local_namespace_name(entry.title().namespace_id())
+            .unwrap_or_default()
+            .to_string()
+    }
+}
+
+impl RenderTemplate {
+    pub fn new() -> Box<Self> {
+        Box::new(Self {})
+    }
+
+    /// The rows for `entries`, with header and footer
+    fn render_rows(
+        &self,
+        platform: &Platform,
+        wiki: &str,
+        entries: &[PageListEntry],
+        mut params: RenderParams,
+    ) -> Result<MyResponse> {
+        let row_template = platform.get_param_blank("row_template");
+        if row_template.is_empty() {
+            return Err(anyhow!("format=template requires a row_template parameter"));
+        }
+        let escaping = Escaping::from_param(&platform.get_param_blank("template_escape"))?;
+        let header = Self::placeholders(&row_template);
+        let count = entries.len().to_string();
+
+        let mut rows = vec![];
+        if let Some(header_template) = platform.get_param("header_template") {
+            rows.push(Self::fill_header(&header_template, &count, wiki));
+        }
+        for entry in entries {
+            *params.row_number_mut() += 1;
+            let values = self.row_from_entry(entry, &header, &params, platform);
+            rows.push(Self::fill_row(&row_template, &header, &values, escaping));
+        }
+        if let Some(footer_template) = platform.get_param("footer_template") {
+            rows.push(Self::fill_header(&footer_template, &count, wiki));
+        }
+        Ok(MyResponse::ok(rows.join("\n"), ContentType::Plain))
+    }
+
+    /// The columns used in `template`, as a header for `row_from_entry`.
+    /// Wikidata property, term and sitelink placeholders like `{P31}`,
+    /// `{label_de}` and `{sitelink_dewiki}` come last.
+    fn placeholders(template: &str) -> Vec<(String, String)> {
+        COLUMNS
+            .iter()
+            .chain(FILE_DATA_KEYS)
+            .filter(|key| template.contains(&format!("{{{key}}}")))
+            .map(|key| key.to_string())
+            .chain(property_placeholders(template))
+            .chain(term_placeholders(template))
+            .chain(sitelink_placeholders(template))
+            .map(|key| (key.clone(), key))
+            .collect()
+    }
+
+    /// Replaces placeholders in one pass, so values are never expanded
+    /// again. Unknown placeholders are left as they are.
+    fn fill_row(
+        template: &str,
+        header: &[(String, String)],
+        values: &[String],
+        escaping: Escaping,
+    ) -> String {
+        let values: HashMap<&str, &String> = header
+            .iter()
+            .map(|(key, _)| key.as_str())
+            .zip(values)
+            .collect();
+        let mut out = String::new();
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            out += &rest[..start];
+            rest = &rest[start..];
+            let value = rest
+                .find('}')
+                .and_then(|end| Some((end, *values.get(&rest[1..end])?)));
+            match value {
+                Some((end, value)) => {
+                    out += &escaping.escape(value);
+                    rest = &rest[end + 1..];
+                }
+                None => {
+                    out.push('{');
+                    rest = &rest[1..];
+                }
+            }
+        }
+        out + rest
+    }
+
+    fn fill_header(template: &str, count: &str, wiki: &str) -> String {
+        template.replace("{count}", count).replace("{wiki}", wiki)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{StubNamespaceContext, make_platform};
+    use std::sync::Arc;
+    use wikimisc::mediawiki::title::Title;
+
+    fn row(template: &str, entry: &PageListEntry, escaping: Escaping) -> String {
+        let mut params =
+            RenderParams::for_tests("enwiki", Arc::new(StubNamespaceContext::enwiki()));
+        *params.row_number_mut() += 1;
+        let header = RenderTemplate::placeholders(template);
+        let values =
+            RenderTemplate {}.row_from_entry(entry, &header, &params, &make_platform(vec![]));
+        RenderTemplate::fill_row(template, &header, &values, escaping)
+    }
+
+    #[test]
+    fn test_escaping_from_param() {
+        assert_eq!(Escaping::from_param("").unwrap(), Escaping::Wikitext);
+        assert_eq!(Escaping::from_param("html").unwrap(), Escaping::Html);
+        assert_eq!(Escaping::from_param("plain").unwrap(), Escaping::Plain);
+        assert!(Escaping::from_param("xml").is_err());
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(
+            Escaping::Wikitext.escape("a|b [[c]] {{d}} <e> 'f' ~~~~"),
+            "a&#124;b &#91;&#91;c&#93;&#93; &#123;&#123;d&#125;&#125; &lt;e&gt; &#39;f&#39; &#126;&#126;&#126;&#126;"
+        );
+        assert_eq!(
+            Escaping::Html.escape("<b>&</b>"),
+            "&lt;b&gt;&amp;&lt;/b&gt;"
+        );
+        assert_eq!(Escaping::Plain.escape("<b>|</b>"), "<b>|</b>");
+    }
+
+    #[test]
+    fn test_html_escaping_is_served_as_plain_text() {
+        let platform = make_platform(vec![
+            ("template_escape", "html"),
+            ("row_template", "<script>alert(1)</script>{title}"),
+        ]);
+        let params = RenderParams::for_tests("enwiki", Arc::new(StubNamespaceContext::enwiki()));
+        let entries = vec![PageListEntry::new(Title::new("<b>", 0))];
+        let response = RenderTemplate {}
+            .render_rows(&platform, "enwiki", &entries, params)
+            .unwrap();
+        assert_eq!(response.content_type, ContentType::Plain);
+        assert_eq!(response.s, "<script>alert(1)</script>&lt;b&gt;");
+    }
+
+    #[test]
+    fn test_placeholders() {
+        let header = RenderTemplate::placeholders("* [[{title}]] ({size}, {img_sha1}, {bogus})");
+        let keys: Vec<&str> = header.iter().map(|(k, _)| k.as_str()).collect();
+        assert_eq!(keys, vec!["title", "size", "img_sha1"]);
+    }
+
+    #[test]
+    fn test_fill_row_wikidata_values() {
+        let mut entry = PageListEntry::new(Title::new("Q42", 0));
+        let values = vec!["Q5".to_string(), "Q36180".to_string()];
+        entry.set_wikidata_values(Some(HashMap::from([("P31".to_string(), values)])));
+        assert_eq!(
+            row("{title}: {P31} {P18}", &entry, Escaping::Plain),
+            "Q42: Q5|Q36180 "
+        );
+    }
+
+    #[test]
+    fn test_fill_row_wikidata_terms() {
+        let mut entry = PageListEntry::new(Title::new("Q42", 0));
+        entry.add_wikidata_term("de", "label", "Douglas Adams".to_string());
+        entry.add_wikidata_term("de", "alias", "DNA".to_string());
+        entry.add_wikidata_term("de", "alias", "Douglas Noël Adams".to_string());
+        assert_eq!(
+            row(
+                "{label_de} ({aliases_de}) {description_de}",
+                &entry,
+                Escaping::Plain
+            ),
+            "Douglas Adams (DNA|Douglas Noël Adams) "
+        );
+    }
+
+    #[test]
+    fn test_fill_row_sitelinks() {
+        let mut entry = PageListEntry::new(Title::new("Q64", 0));
+        let sitelinks = HashMap::from([("dewiki".to_string(), "Berlin".to_string())]);
+        entry.set_sitelinks(Some(sitelinks));
+        assert_eq!(
+            row(
+                "{title}: [[:de:{sitelink_dewiki}]] {sitelink_enwiki}",
+                &entry,
+                Escaping::Plain
+            ),
+            "Q64: [[:de:Berlin]] "
+        );
+    }
+
+    #[test]
+    fn test_fill_row() {
+        let mut entry = PageListEntry::new(Title::new("Foo (bar)", 1));
+        entry.set_wikidata_item(Some("Q42".to_string()));
+        assert_eq!(
+            row(
+                "{number}. [[{title}]] ({size} bytes, {wikidata_item}) {bogus}",
+                &entry,
+                Escaping::Wikitext
+            ),
+            "1. [[Talk:Foo (bar)]] ( bytes, Q42) {bogus}"
+        );
+    }
+
+    #[test]
+    fn test_fill_row_values_are_not_expanded() {
+        let entry = PageListEntry::new(Title::new("{size}", 0));
+        assert_eq!(row("{title}{", &entry, Escaping::Plain), "{size}{");
+    }
+
+    #[test]
+    fn test_fill_row_html() {
+        let mut entry = PageListEntry::new(Title::new("A", 0));
+        entry.set_wikidata_label(Some("<i>A</i>".to_string()));
+        assert_eq!(
+            row("<li>{wikidata_label}</li>", &entry, Escaping::Html),
+            "<li>&lt;i&gt;A&lt;/i&gt;</li>"
+        );
+    }
+
+    #[test]
+    fn test_fill_header() {
+        assert_eq!(
+            RenderTemplate::fill_header("{{Div col}} <!-- {count} on {wiki} -->", "12", "enwiki"),
+            "{{Div col}} <!-- 12 on enwiki -->"
+        );
+    }
+}
@@ -47,6 +47,9 @@ impl Render for RenderTSV {
                 header.push((col.to_string(), col.to_string()));
             }
         }
+        if let Some(columns) = self.get_selected_columns(&params) {
+            header = columns;
+        }
         rows.push(
             header
                 .iter()
@@ -71,6 +74,7 @@ impl Render for RenderTSV {
                 _ => ContentType::Plain, // Fallback
             },
             status: 200,
+            binary: None,
         })
     }
 
@@ -109,7 +113,7 @@ impl RenderTSV {
         })
     }
 
-    fn escape_cell(&self, s: &str) -> String {
+    pub(crate) fn escape_cell(&self, s: &str) -> String {
         if self.separator == "," {
             format!("\"{}\"", s.replace('\"', "\\\""))
         } else {
@@ -284,4 +288,24 @@ mod tests {
             "Foo_bar_baz"
         );
     }
+
+    #[test]
+    fn test_selected_columns_order_row() {
+        let r = tsv();
+        let mut params = enwiki_params();
+        assert!(r.get_selected_columns(&params).is_none());
+        params.set_columns(vec![
+            "wikidata_label".to_string(),
+            "title".to_string(),
+            "wikidata_description".to_string(),
+        ]);
+        let header = r.get_selected_columns(&params).unwrap();
+        let mut entry = PageListEntry::new(Title::new("Foo bar", 0));
+        entry.set_wikidata_label(Some("Foo".to_string()));
+        let platform = crate::test_support::make_platform(vec![]);
+        assert_eq!(
+            r.row_from_entry(&entry, &header, &params, &platform),
+            vec!["Foo", "Foo_bar", ""]
+        );
+    }
 }
@@ -0,0 +1,313 @@
+use crate::content_type::ContentType;
+use crate::form_parameters::FormParameters;
+use crate::platform::{MyResponse, Platform};
+use crate::render::quickstatements::RenderQuickStatements;
+use crate::render::tsv::RenderTSV;
+use htmlescape::encode_minimal;
+use serde_json::Value;
+
+const COMMONS_SERVER: &str = "https://commons.wikimedia.org";
+const QUICKSTATEMENTS_URL: &str = "https://quickstatements.toolforge.org/#/v1=";
+const THUMBNAIL_WIDTH: &str = "120px";
+
+/// Renders WDfist results in the standard output formats
+///
+/// WDfist results are file candidates per item, not pages, so they do not
+/// go through the `Render` trait. Candidates come from `ranked` (best
+/// first), falling back to the unranked `data`. `format=json` and unknown
+/// formats return the WDfist JSON unchanged, as before.
+#[derive(Debug, Clone)]
+pub struct RenderWDfist {
+    status: String,
+    property: String,
+    /// Items, sorted by ID, with their (file, score) candidates, best first
+    items: Vec<(String, Vec<(String, Option<f64>)>)>,
+}
+
+impl RenderWDfist {
+    pub fn response(platform: &Platform, result: &Value) -> MyResponse {
+        let render = Self::from_json(result);
+        match platform.get_param_blank("format").as_str() {
+            "html" => {
+                let interface_language = platform.get_param_default("interface_language", "en");
+                let html = platform.state().get_main_page(interface_language);
+                let html = html.replace(
+                    "<!--querystring-->",
+                    encode_minimal(&platform.form_parameters().to_string()).as_str(),
+                );
+                let html = html.replace("<!--output-->", &render.html());
+                MyResponse::ok(html, ContentType::HTML)
+            }
+            "tsv" => MyResponse::ok(render.tsv("\t"), ContentType::TSV),
+            "csv" => MyResponse::ok(render.tsv(","), ContentType::CSV),
+            "wiki" => MyResponse::ok(render.wikitext(), ContentType::Plain),
+            "quickstatements" => {
+                MyResponse::ok(render.quickstatements().join("\n"), ContentType::Plain)
+            }
+            _ => platform
+                .state()
+                .output_json(result, platform.form_parameters().params.get("callback")),
+        }
+    }
+
+    fn from_json(result: &Value) -> Self {
+        let mut items: Vec<(String, Vec<(String, Option<f64>)>)> = match result["ranked"]
+            .as_object()
+        {
+            Some(ranked) => ranked
+                .iter()
+                .map(|(q, candidates)| {
+                    let candidates = candidates
+                        .as_array()
+                        .map(|candidates| {
+                            candidates
+                                .iter()
+                                .filter_map(|c| {
+                                    Some((c["file"].as_str()?.to_string(), c["score"].as_f64()))
+                                })
+                                .collect()
+                        })
+                        .unwrap_or_default();
+                    (q.to_owned(), candidates)
+                })
+                .collect(),
+            None => result["data"]
+                .as_object()
+                .map(|data| {
+                    data.iter()
+                        .map(|(q, files)| {
+                            let mut files: Vec<(String, Option<f64>)> = files
+                                .as_object()
+                                .map(|files| files.keys().map(|f| (f.to_owned(), None)).collect())
+                                .unwrap_or_default();
+                            files.sort();
+                            (q.to_owned(), files)
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+        };
+        items.retain(|(_q, candidates)| !candidates.is_empty());
+        items.sort_by_key(|(q, _candidates)| {
+            let id = q.get(1..).and_then(|id| id.parse::<u64>().ok());
+            (id.unwrap_or(u64::MAX), q.to_owned())
+        });
+        Self {
+            status: result["status"].as_str().unwrap_or_default().to_string(),
+            property: result["property"].as_str().unwrap_or("P18").to_string(),
+            items,
+        }
+    }
+
+    fn pretty_file(file: &str) -> String {
+        file.replace('_', " ")
+    }
+
+    fn quickstatement(&self, q: &str, file: &str) -> String {
+        format!(
+            "{q}|{}|\"{}\"",
+            self.property,
+            RenderQuickStatements::sanitize(&Self::pretty_file(file))
+        )
+    }
+
+    /// One command per item, for its best candidate
+    fn quickstatements(&self) -> Vec<String> {
+        self.items
+            .iter()
+            .filter_map(|(q, candidates)| Some(self.quickstatement(q, &candidates.first()?.0)))
+            .collect()
+    }
+
+    fn quickstatements_link(commands: &[String]) -> String {
+        QUICKSTATEMENTS_URL.to_string() + &FormParameters::percent_encode(&commands.join("||"))
+    }
+
+    fn score_string(score: Option<f64>) -> String {
+        score.map(|s| format!("{s:.2}")).unwrap_or_default()
+    }
+
+    fn tsv(&self, separator: &str) -> String {
+        let renderer = RenderTSV::new(separator);
+        let escape = |s: &str| renderer.escape_cell(s);
+        let mut rows = vec![
+            ["item", "property", "rank", "file", "score"]
+                .map(escape)
+                .join(separator),
+        ];
+        for (q, candidates) in &self.items {
+            for (rank, (file, score)) in candidates.iter().enumerate() {
+                rows.push(
+                    [
+                        q.to_owned(),
+                        self.property.to_owned(),
+                        (rank + 1).to_string(),
+                        file.to_owned(),
+                        Self::score_string(*score),
+                    ]
+                    .map(|s| escape(&s))
+                    .join(separator),
+                );
+            }
+        }
+        rows.join("\n")
+    }
+
+    fn wikitext(&self) -> String {
+        let mut rows = vec![];
+        for (q, candidates) in &self.items {
+            rows.push(format!("== [[:d:{q}|{q}]] =="));
+            rows.push("<gallery>".to_string());
+            for (file, score) in candidates {
+                match score {
+                    Some(_) => rows.push(format!(
+                        "File:{}|score {}",
+                        Self::pretty_file(file),
+                        Self::score_string(*score)
+                    )),
+                    None => rows.push(format!("File:{}", Self::pretty_file(file))),
+                }
+            }
+            rows.push("</gallery>".to_string());
+        }
+        rows.join("\n")
+    }
+
+    fn html(&self) -> String {
+        let mut rows = vec!["<hr/>".to_string()];
+        if self.status != "OK" {
+            rows.push(format!(
+                "<div class='alert alert-warning' style='clear:both'>{}</div>",
+                encode_minimal(&self.status)
+            ));
+        }
+        rows.push(format!(
+            "<h2><a name='results'></a><span id='num_results' num='{}'></span></h2>",
+            self.items.len()
+        ));
+        let all_commands = self.quickstatements();
+        if !all_commands.is_empty() {
+            rows.push(format!(
+                "<div><a class='btn btn-outline-primary' target='_blank' href='{}'>Add the best candidate for all {} items as {} with QuickStatements</a></div>",
+                Self::quickstatements_link(&all_commands),
+                all_commands.len(),
+                self.property
+            ));
+        }
+        rows.push("<div style='clear:both;overflow:auto'>".to_string());
+        rows.push("<table class='table table-sm table-striped'><thead><tr><th>#</th><th>Item</th><th>Candidates</th></tr></thead><tbody>".to_string());
+        for (num, (q, candidates)) in self.items.iter().enumerate() {
+            let cards: Vec<String> = candidates
+                .iter()
+                .map(|(file, score)| self.html_card(q, file, *score))
+                .collect();
+            rows.push(format!(
+                "<tr><td>{}</td><td><a target='_blank' href='https://www.wikidata.org/wiki/{q}'>{q}</a></td><td>{}</td></tr>",
+                num + 1,
+                cards.join("")
+            ));
+        }
+        rows.push("</tbody></table></div>".to_string());
+        rows.join("\n")
+    }
+
+    fn html_card(&self, q: &str, file: &str, score: Option<f64>) -> String {
+        let encoded_file = super::escape_attribute(file);
+        let url = format!("{COMMONS_SERVER}/wiki/File:{encoded_file}");
+        let src = format!(
+            "{COMMONS_SERVER}/wiki/Special:Redirect/file/{encoded_file}?width={THUMBNAIL_WIDTH}"
+        );
+        let qs = Self::quickstatements_link(&[self.quickstatement(q, file)]);
+        let score = match score {
+            Some(_) => format!(" ({})", Self::score_string(score)),
+            None => String::new(),
+        };
+        format!(
+            "<div class='card thumbcard' style='display:inline-block' title='{}'><a target='_blank' href='{url}'><img class='card-img thumbcard-img' src='{src}' loading='lazy' /></a><div><a target='_blank' href='{qs}'>add</a>{score}</div></div>",
+            encode_minimal(&Self::pretty_file(file))
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render() -> RenderWDfist {
+        RenderWDfist::from_json(&json!({
+            "status": "OK",
+            "property": "P41",
+            "ranked": {
+                "Q20": [{"file": "Flag_of_X.svg", "score": 4.5}, {"file": "Other_flag.png", "score": 1.0}],
+                "Q3": [{"file": "Flag_of_Y.svg", "score": 2.0}],
+                "Q5": [],
+            }
+        }))
+    }
+
+    #[test]
+    fn test_from_json() {
+        let render = render();
+        assert_eq!(render.property, "P41");
+        let items: Vec<&str> = render.items.iter().map(|(q, _)| q.as_str()).collect();
+        assert_eq!(items, vec!["Q3", "Q20"]);
+    }
+
+    #[test]
+    fn test_from_json_unranked() {
+        let render = RenderWDfist::from_json(&json!({
+            "status": "OK",
+            "data": {"Q1": {"B.jpg": 1, "A.jpg": 2}}
+        }));
+        assert_eq!(render.property, "P18");
+        assert_eq!(
+            render.items,
+            vec![(
+                "Q1".to_string(),
+                vec![("A.jpg".to_string(), None), ("B.jpg".to_string(), None)]
+            )]
+        );
+    }
+
+    #[test]
+    fn test_quickstatements() {
+        assert_eq!(
+            render().quickstatements(),
+            vec!["Q3|P41|\"Flag of Y.svg\"", "Q20|P41|\"Flag of X.svg\""]
+        );
+    }
+
+    #[test]
+    fn test_quickstatement_sanitizes_file() {
+        assert_eq!(
+            render().quickstatement("Q1", "Say_\"hi\"|there.jpg"),
+            "Q1|P41|\"Say 'hi' there.jpg\""
+        );
+    }
+
+    #[test]
+    fn test_tsv() {
+        let tsv = render().tsv("\t");
+        let rows: Vec<&str> = tsv.lines().collect();
+        assert_eq!(rows[0], "item\tproperty\trank\tfile\tscore");
+        assert_eq!(rows[1], "Q3\tP41\t1\tFlag_of_Y.svg\t2.00");
+        assert_eq!(rows[3], "Q20\tP41\t2\tOther_flag.png\t1.00");
+    }
+
+    #[test]
+    fn test_wikitext() {
+        let wikitext = render().wikitext();
+        assert!(wikitext.starts_with(
+            "== [[:d:Q3|Q3]] ==\n<gallery>\nFile:Flag of Y.svg|score 2.00\n</gallery>"
+        ));
+    }
+
+    #[test]
+    fn test_html_escapes_file_names() {
+        let render = RenderWDfist::from_json(&json!({
+            "status": "OK",
+            "ranked": {"Q1": [{"file": "<script>.jpg", "score": 1.0}]}
+        }));
+        assert!(!render.html().contains("<script>"));
+    }
+}
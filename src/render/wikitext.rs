@@ -63,6 +63,9 @@ impl Render for RenderWiki {
                 header.push((col.to_string(), col.to_string()));
             }
         }
+        if let Some(columns) = self.get_selected_columns(&params) {
+            header = columns;
+        }
         rows.push(
             "!".to_string()
                 + &header
@@ -86,6 +89,7 @@ impl Render for RenderWiki {
             s: rows.join("\n"),
             content_type: ContentType::Plain,
             status: 200,
+            binary: None,
         })
     }
 
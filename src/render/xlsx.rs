@@ -0,0 +1,385 @@
+use crate::content_type::ContentType;
+use crate::form_parameters::FormParameters;
+use crate::pagelist_entry::PageListEntry;
+use crate::platform::{MyResponse, Platform};
+use crate::render::Render;
+use crate::render::params::RenderParams;
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use rust_xlsxwriter::{ExcelDateTime, Format, Url, Workbook, Worksheet};
+
+/// Columns written as numbers rather than text
+const NUMERIC_COLUMNS: &[&str] = &[
+    "number",
+    "page_id",
+    "size",
+    "incoming_links",
+    "sitelinks",
+    "redlink_count",
+    "img_size",
+    "img_width",
+    "img_height",
+];
+
+/// Columns holding `MediaWiki` timestamps
+const DATETIME_COLUMNS: &[&str] = &["timestamp", "img_timestamp"];
+
+/// Rows on a worksheet, below the header row
+const MAX_ROWS: usize = 1_048_575;
+/// Hyperlinks per worksheet; further links are written as text
+const MAX_LINKS: usize = 65_530;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Cell {
+    Empty,
+    Text(String),
+    Number(f64),
+    /// Seconds since the epoch, UTC
+    DateTime(i64),
+    /// Link target and displayed text
+    Link(String, String),
+}
+
+/// Renders an Excel workbook: the results, and the query on a second sheet
+#[derive(Clone, Copy, Debug)]
+pub struct RenderXLSX;
+
+#[async_trait]
+impl Render for RenderXLSX {
+    async fn response(
+        &self,
+        platform: &Platform,
+        wiki: &str,
+        entries: Vec<PageListEntry>,
+    ) -> Result<MyResponse> {
+        let mut params = RenderParams::new(platform, wiki).await?;
+        let server = params
+            .state()
+            .site_matrix()
+            .get_server_url_for_wiki(wiki)
+            .unwrap_or_default();
+        let columns: Vec<(String, String)> = self
+            .get_initial_columns(&params)
+            .into_iter()
+            .filter(|col| *col != "checkbox")
+            .map(|col| (col.to_string(), col.to_string()))
+            .collect();
+
+        let mut rows = vec![];
+        for entry in entries.iter().take(MAX_ROWS) {
+            *params.row_number_mut() += 1;
+            let texts = self.row_from_entry(entry, &columns, &params, platform);
+            let row: Vec<Cell> = columns
+                .iter()
+                .zip(texts)
+                .map(|((key, _), text)| Self::cell(key, text, entry, &params, &server))
+                .collect();
+            rows.push(row);
+        }
+        let headers: Vec<&str> = columns.iter().map(|(key, _)| key.as_str()).collect();
+        let bytes = Self::workbook(
+            &headers,
+            &rows,
+            entries.len(),
+            platform.psid,
+            platform.form_parameters(),
+        )?;
+        Ok(MyResponse::ok_binary(bytes, ContentType::XLSX))
+    }
+
+    fn render_cell_title(&self, entry: &PageListEntry, params: &RenderParams) -> String {
+        params
+            .ns()
+            .full_pretty(entry.title())
+            .unwrap_or_else(|| entry.title().pretty().to_string())
+    }
+
+    fn render_cell_wikidata_item(&self, entry: &PageListEntry, _params: &RenderParams) -> String {
+        entry.get_wikidata_item().unwrap_or_default()
+    }
+
+    fn render_user_name(&self, user: &str, _params: &RenderParams) -> String {
+        user.to_string()
+    }
+
+    fn render_cell_image(&self, image: &Option<String>, _params: &RenderParams) -> String {
+        image.to_owned().unwrap_or_default()
+    }
+
+    fn render_cell_namespace(&self, entry: &PageListEntry, params: &RenderParams) -> String {
+        params
+            .ns()
+            .local_namespace_name(entry.title().namespace_id())
+            .unwrap_or("UNKNOWN_NAMESPACE")
+            .to_string()
+    }
+}
+
+impl RenderXLSX {
+    pub fn new() -> Box<Self> {
+        Box::new(Self {})
+    }
+
+    /// Types the rendered text of one cell by its column.
+    fn cell(
+        key: &str,
+        text: String,
+        entry: &PageListEntry,
+        params: &RenderParams,
+        server: &str,
+    ) -> Cell {
+        if text.is_empty() {
+            return Cell::Empty;
+        }
+        match key {
+            "title" => {
+                let page = params
+                    .ns()
+                    .full_with_underscores(entry.title())
+                    .unwrap_or_else(|| entry.title().with_underscores());
+                Cell::Link(
+                    format!("{server}/wiki/{}", FormParameters::percent_encode(&page)),
+                    text,
+                )
+            }
+            "wikidata_item" => Cell::Link(format!("https://www.wikidata.org/wiki/{text}"), text),
+            key if NUMERIC_COLUMNS.contains(&key) => match text.parse::<f64>() {
+                Ok(number) => Cell::Number(number),
+                Err(_) => Cell::Text(text),
+            },
+            key if DATETIME_COLUMNS.contains(&key) => {
+                match NaiveDateTime::parse_from_str(&text, "%Y%m%d%H%M%S") {
+                    Ok(dt) => Cell::DateTime(dt.and_utc().timestamp()),
+                    Err(_) => Cell::Text(text),
+                }
+            }
+            _ => Cell::Text(text),
+        }
+    }
+
+    /// Warnings for the Query sheet, if `rows` (of `total` results) hit a
+    /// worksheet limit
+    fn limit_warnings(rows: &[Vec<Cell>], total: usize) -> Vec<String> {
+        let mut ret = vec![];
+        if total > rows.len() {
+            ret.push(format!(
+                "Only the first {} of {total} results fit on the Results sheet",
+                rows.len()
+            ));
+        }
+        let links = rows
+            .iter()
+            .flatten()
+            .filter(|cell| matches!(cell, Cell::Link(..)))
+            .count();
+        if links > MAX_LINKS {
+            ret.push(format!(
+                "Only the first {MAX_LINKS} of {links} links are clickable, the others are text"
+            ));
+        }
+        ret
+    }
+
+    /// The workbook for `rows`, the first (at most `MAX_ROWS`) of `total`
+    /// results
+    fn workbook(
+        headers: &[&str],
+        rows: &[Vec<Cell>],
+        total: usize,
+        psid: Option<u64>,
+        form_parameters: &FormParameters,
+    ) -> Result<Vec<u8>> {
+        let rows = &rows[..rows.len().min(MAX_ROWS)];
+        let bold = Format::new().set_bold();
+        let datetime = Format::new().set_num_format("yyyy-mm-dd hh:mm:ss");
+        let mut workbook = Workbook::new();
+
+        let results = workbook.add_worksheet();
+        results.set_name("Results")?;
+        for (col, header) in (0_u16..).zip(headers) {
+            results.write_string_with_format(0, col, *header, &bold)?;
+        }
+        let mut links = 0;
+        for (row, cells) in (1_u32..).zip(rows) {
+            for (col, cell) in (0_u16..).zip(cells) {
+                Self::write_cell(results, row, col, cell, &datetime, &mut links)?;
+            }
+        }
+        results.set_freeze_panes(1, 0)?;
+        if !headers.is_empty() {
+            let last_col = u16::try_from(headers.len() - 1)?;
+            let last_row = u32::try_from(rows.len())?;
+            results.autofilter(0, 0, last_row, last_col)?;
+        }
+
+        let query_sheet = workbook.add_worksheet();
+        query_sheet.set_name("Query")?;
+        query_sheet.write_string_with_format(0, 0, "parameter", &bold)?;
+        query_sheet.write_string_with_format(0, 1, "value", &bold)?;
+        let mut row = 1;
+        for warning in Self::limit_warnings(rows, total) {
+            query_sheet.write_string(row, 0, "warning")?;
+            query_sheet.write_string(row, 1, warning)?;
+            row += 1;
+        }
+        if let Some(psid) = psid {
+            query_sheet.write_string(row, 0, "psid")?;
+            query_sheet.write_number(row, 1, psid as f64)?;
+            row += 1;
+        }
+        let mut query: Vec<(&String, &String)> = form_parameters.params.iter().collect();
+        query.sort();
+        for (key, value) in query {
+            query_sheet.write_string(row, 0, key)?;
+            query_sheet.write_string(row, 1, value)?;
+            row += 1;
+        }
+
+        Ok(workbook.save_to_buffer()?)
+    }
+
+    /// Writes one cell; `links` counts the hyperlinks on the sheet, and
+    /// links past `MAX_LINKS` are written as their text.
+    fn write_cell(
+        sheet: &mut Worksheet,
+        row: u32,
+        col: u16,
+        cell: &Cell,
+        datetime: &Format,
+        links: &mut usize,
+    ) -> Result<()> {
+        match cell {
+            Cell::Empty => {}
+            Cell::Text(text) => {
+                sheet.write_string(row, col, text)?;
+            }
+            Cell::Number(number) => {
+                sheet.write_number(row, col, *number)?;
+            }
+            Cell::DateTime(seconds) => {
+                let dt = ExcelDateTime::from_timestamp(*seconds)?;
+                sheet.write_datetime_with_format(row, col, &dt, datetime)?;
+            }
+            Cell::Link(_url, text) if *links >= MAX_LINKS => {
+                sheet.write_string(row, col, text)?;
+            }
+            Cell::Link(url, text) => {
+                sheet.write_url_with_text(row, col, Url::new(url), text)?;
+                *links += 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::StubNamespaceContext;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use wikimisc::mediawiki::title::Title;
+
+    const SERVER: &str = "https://en.wikipedia.org";
+
+    fn enwiki_params() -> RenderParams {
+        RenderParams::for_tests("enwiki", Arc::new(StubNamespaceContext::enwiki()))
+    }
+
+    fn cell(key: &str, text: &str) -> Cell {
+        let entry = PageListEntry::new(Title::new("Some page", 1));
+        RenderXLSX::cell(key, text.to_string(), &entry, &enwiki_params(), SERVER)
+    }
+
+    #[test]
+    fn test_cell_types() {
+        assert_eq!(cell("size", ""), Cell::Empty);
+        assert_eq!(cell("page_id", "123"), Cell::Number(123.0));
+        assert_eq!(
+            cell("timestamp", "20240102030405"),
+            Cell::DateTime(1_704_164_645)
+        );
+        assert_eq!(
+            cell("timestamp", "garbage"),
+            Cell::Text("garbage".to_string())
+        );
+        // Leading zeros survive as text
+        assert_eq!(cell("defaultsort", "007"), Cell::Text("007".to_string()));
+    }
+
+    #[test]
+    fn test_cell_links() {
+        assert_eq!(
+            cell("title", "Talk:Some page"),
+            Cell::Link(
+                "https://en.wikipedia.org/wiki/Talk%3ASome%5Fpage".to_string(),
+                "Talk:Some page".to_string()
+            )
+        );
+        assert_eq!(
+            cell("wikidata_item", "Q42"),
+            Cell::Link(
+                "https://www.wikidata.org/wiki/Q42".to_string(),
+                "Q42".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_workbook() {
+        let rows = vec![vec![
+            Cell::Number(1.0),
+            Cell::Link("https://x/".to_string(), "X".to_string()),
+            Cell::DateTime(1_704_164_645),
+            Cell::Empty,
+        ]];
+        let form_parameters = FormParameters::new_from_pairs(HashMap::from([(
+            "language".to_string(),
+            "en".to_string(),
+        )]));
+        let bytes = RenderXLSX::workbook(
+            &["number", "title", "timestamp", "wikidata_item"],
+            &rows,
+            1,
+            Some(123),
+            &form_parameters,
+        )
+        .unwrap();
+        // XLSX files are ZIP archives
+        assert_eq!(&bytes[..2], b"PK");
+    }
+
+    #[test]
+    fn test_workbook_link_limit() {
+        let link = Cell::Link("https://x/".to_string(), "X".to_string());
+        let rows = vec![vec![link.clone(), link]; MAX_LINKS / 2 + 5];
+        assert_eq!(
+            RenderXLSX::limit_warnings(&rows, rows.len()),
+            vec![format!(
+                "Only the first {MAX_LINKS} of {} links are clickable, the others are text",
+                MAX_LINKS + 10
+            )]
+        );
+        let form_parameters = FormParameters::new_from_pairs(HashMap::new());
+        let bytes = RenderXLSX::workbook(
+            &["title", "wikidata_item"],
+            &rows,
+            rows.len(),
+            None,
+            &form_parameters,
+        )
+        .unwrap();
+        assert_eq!(&bytes[..2], b"PK");
+    }
+
+    #[test]
+    fn test_limit_warnings_rows() {
+        let rows = vec![vec![Cell::Number(1.0)]];
+        assert!(RenderXLSX::limit_warnings(&rows, 1).is_empty());
+        assert_eq!(
+            RenderXLSX::limit_warnings(&rows, 3),
+            vec!["Only the first 1 of 3 results fit on the Results sheet"]
+        );
+    }
+}
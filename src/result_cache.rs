@@ -0,0 +1,271 @@
+//! In-memory cache of sorted results, for paging through them.
+//!
+//! A request with `limit=` (and optionally `offset=`) stores its complete
+//! sorted result here and returns one page of it, plus cursors for the
+//! next and previous pages. A request with `cursor=` serves a page from
+//! the cached result without re-running the query, so the page boundaries
+//! stay stable even if the wiki changes in between. Cached results expire
+//! after [`RESULT_CACHE_TTL`]. To bound memory, results larger than
+//! [`MAX_ENTRIES_PER_RESULT`] are not cached, and the oldest results are
+//! dropped once all of them together exceed [`MAX_CACHED_ENTRIES`].
+
+use crate::pagelist_entry::PageListEntry;
+use anyhow::{Result, anyhow};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How long a cached result can be paged through.
+pub const RESULT_CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// At most this many results are cached; the oldest is dropped first.
+const MAX_CACHED_RESULTS: usize = 50;
+
+/// Results with more pages than this are not cached.
+pub const MAX_ENTRIES_PER_RESULT: usize = 200_000;
+
+/// At most this many pages are cached over all results.
+const MAX_CACHED_ENTRIES: usize = 1_000_000;
+
+/// Position in a cached result. Serialised into the `cursor=` parameter,
+/// which clients should treat as opaque.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cursor {
+    pub key: u64,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+impl Cursor {
+    pub fn from_param(s: &str) -> Result<Self> {
+        let invalid = || anyhow!("Invalid cursor '{s}'");
+        let mut parts = s.trim().split('-');
+        let mut next = || -> Result<u64> {
+            let part = parts.next().ok_or_else(invalid)?;
+            u64::from_str_radix(part, 16).map_err(|_| invalid())
+        };
+        let key = next()?;
+        let offset = usize::try_from(next()?)?;
+        let limit = usize::try_from(next()?)?;
+        if parts.next().is_some() || limit == 0 {
+            return Err(invalid());
+        }
+        Ok(Self { key, offset, limit })
+    }
+
+    pub fn to_param(&self) -> String {
+        format!("{:x}-{:x}-{:x}", self.key, self.offset, self.limit)
+    }
+}
+
+/// One page of a cached result, as reported in JSON and JSONL metadata.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pagination {
+    pub offset: usize,
+    pub limit: usize,
+    pub total: usize,
+    pub next: Option<Cursor>,
+    pub prev: Option<Cursor>,
+}
+
+impl Pagination {
+    pub fn new(key: u64, offset: usize, limit: usize, total: usize) -> Self {
+        let next = (offset.saturating_add(limit) < total).then(|| Cursor {
+            key,
+            offset: offset + limit,
+            limit,
+        });
+        let prev = (offset > 0).then(|| Cursor {
+            key,
+            offset: offset.saturating_sub(limit),
+            limit,
+        });
+        Self {
+            offset,
+            limit,
+            total,
+            next,
+            prev,
+        }
+    }
+
+    /// The entries of this page.
+    pub fn slice<'a>(&self, pages: &'a [PageListEntry]) -> &'a [PageListEntry] {
+        let start = self.offset.min(pages.len());
+        let end = self.offset.saturating_add(self.limit).min(pages.len());
+        &pages[start..end]
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CachedResult {
+    pub wiki: String,
+    /// Sorted, and already cut to `output_limit`
+    pub pages: Arc<Vec<PageListEntry>>,
+    created: Instant,
+    /// Insertion order, to find the oldest result
+    serial: u64,
+}
+
+#[derive(Debug)]
+pub struct ResultCache {
+    results: HashMap<u64, CachedResult>,
+    next_serial: u64,
+    /// Pages over all cached results
+    entries: usize,
+    max_entries_per_result: usize,
+    max_entries: usize,
+}
+
+impl Default for ResultCache {
+    fn default() -> Self {
+        Self {
+            results: HashMap::new(),
+            next_serial: 0,
+            entries: 0,
+            max_entries_per_result: MAX_ENTRIES_PER_RESULT,
+            max_entries: MAX_CACHED_ENTRIES,
+        }
+    }
+}
+
+impl ResultCache {
+    /// Caches a sorted result, and returns its key; `None` if the result
+    /// is too large to cache.
+    pub fn insert(&mut self, wiki: &str, pages: Vec<PageListEntry>) -> Option<u64> {
+        if pages.len() > self.max_entries_per_result {
+            return None;
+        }
+        self.evict_expired(Instant::now());
+        while self.results.len() >= MAX_CACHED_RESULTS
+            || self.entries + pages.len() > self.max_entries
+        {
+            let oldest = self
+                .results
+                .iter()
+                .min_by_key(|(_, result)| result.serial)
+                .map(|(key, _)| *key);
+            match oldest.and_then(|key| self.results.remove(&key)) {
+                Some(result) => self.entries -= result.pages.len(),
+                None => break,
+            }
+        }
+        self.entries += pages.len();
+        let mut key: u64 = rand::random();
+        while self.results.contains_key(&key) {
+            key = rand::random();
+        }
+        self.results.insert(
+            key,
+            CachedResult {
+                wiki: wiki.to_string(),
+                pages: Arc::new(pages),
+                created: Instant::now(),
+                serial: self.next_serial,
+            },
+        );
+        self.next_serial += 1;
+        Some(key)
+    }
+
+    pub fn get(&mut self, key: u64) -> Option<CachedResult> {
+        self.evict_expired(Instant::now());
+        self.results.get(&key).cloned()
+    }
+
+    fn evict_expired(&mut self, now: Instant) {
+        self.results
+            .retain(|_, result| now.duration_since(result.created) < RESULT_CACHE_TTL);
+        self.entries = self.results.values().map(|result| result.pages.len()).sum();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wikimisc::mediawiki::title::Title;
+
+    fn pages(n: usize) -> Vec<PageListEntry> {
+        (0..n)
+            .map(|i| PageListEntry::new(Title::new(&format!("P{i}"), 0)))
+            .collect()
+    }
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let cursor = Cursor {
+            key: 0xdead_beef,
+            offset: 500,
+            limit: 100,
+        };
+        assert_eq!(cursor.to_param(), "deadbeef-1f4-64");
+        assert_eq!(Cursor::from_param(&cursor.to_param()).unwrap(), cursor);
+    }
+
+    #[test]
+    fn test_cursor_invalid() {
+        assert!(Cursor::from_param("").is_err());
+        assert!(Cursor::from_param("1-2").is_err());
+        assert!(Cursor::from_param("1-2-3-4").is_err());
+        assert!(Cursor::from_param("x-2-3").is_err());
+        assert!(Cursor::from_param("1-2-0").is_err());
+    }
+
+    #[test]
+    fn test_pagination() {
+        let first = Pagination::new(1, 0, 10, 25);
+        assert_eq!(first.prev, None);
+        assert_eq!(first.next.unwrap().offset, 10);
+
+        let last = Pagination::new(1, 20, 10, 25);
+        assert_eq!(last.next, None);
+        assert_eq!(last.prev.unwrap().offset, 10);
+
+        let pages = pages(25);
+        assert_eq!(first.slice(&pages).len(), 10);
+        assert_eq!(last.slice(&pages).len(), 5);
+        assert!(Pagination::new(1, 30, 10, 25).slice(&pages).is_empty());
+    }
+
+    #[test]
+    fn test_cache_insert_get() {
+        let mut cache = ResultCache::default();
+        let key = cache.insert("enwiki", pages(3)).unwrap();
+        let cached = cache.get(key).unwrap();
+        assert_eq!(cached.wiki, "enwiki");
+        assert_eq!(cached.pages.len(), 3);
+        assert!(cache.get(key.wrapping_add(1)).is_none());
+    }
+
+    #[test]
+    fn test_cache_evicts_expired_and_oldest() {
+        let mut cache = ResultCache::default();
+        let key = cache.insert("enwiki", pages(1)).unwrap();
+        cache.evict_expired(Instant::now() + RESULT_CACHE_TTL);
+        assert!(cache.get(key).is_none());
+
+        let first = cache.insert("enwiki", pages(1)).unwrap();
+        for _ in 0..MAX_CACHED_RESULTS {
+            cache.insert("enwiki", pages(1));
+        }
+        assert_eq!(cache.results.len(), MAX_CACHED_RESULTS);
+        assert!(cache.get(first).is_none());
+    }
+
+    #[test]
+    fn test_cache_bounds_entries() {
+        let mut cache = ResultCache {
+            max_entries_per_result: 10,
+            max_entries: 25,
+            ..ResultCache::default()
+        };
+        assert!(cache.insert("enwiki", pages(11)).is_none());
+        let first = cache.insert("enwiki", pages(10)).unwrap();
+        let second = cache.insert("enwiki", pages(10)).unwrap();
+        let third = cache.insert("enwiki", pages(10)).unwrap();
+        assert_eq!(cache.entries, 20);
+        assert!(cache.get(first).is_none());
+        assert!(cache.get(second).is_some());
+        assert!(cache.get(third).is_some());
+    }
+}
@@ -0,0 +1,152 @@
+//! Named saved queries.
+//!
+//! PSIDs are immutable: changing a parameter yields a new PSID, and with it
+//! a new link. A saved query gives a PSID a stable, human-readable name
+//! (served at `/q/<name>`) plus a title, description and owner tag; the name
+//! can be re-pointed at a newer PSID whenever the query evolves (via the CLI;
+//! the web server only reads saved queries).
+
+use anyhow::{Result, anyhow};
+use serde_json::Value;
+
+/// Longest accepted name; matches the `saved_query.name` column.
+pub const MAX_NAME_LENGTH: usize = 100;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SavedQuery {
+    pub name: String,
+    pub title: String,
+    pub description: String,
+    /// Free-form tag (e.g. a project or user name). Once set, only
+    /// updates carrying the same tag may change the saved query. Not
+    /// authenticated: a soft lock, not access control.
+    pub owner: String,
+    /// The PSID the name currently points to.
+    pub psid: u64,
+    /// `YYYY-MM-DD HH:MM:SS` (UTC) of the last change.
+    pub updated: String,
+}
+
+impl SavedQuery {
+    /// Names are URL path segments: lowercase ASCII letters, digits, `-`
+    /// and `_`.
+    pub fn validate_name(name: &str) -> Result<()> {
+        if name.is_empty() || name.len() > MAX_NAME_LENGTH {
+            return Err(anyhow!(
+                "Saved query name must be 1-{MAX_NAME_LENGTH} characters long"
+            ));
+        }
+        if !name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+        {
+            return Err(anyhow!(
+                "Invalid saved query name '{name}': use lowercase letters, digits, '-' and '_'"
+            ));
+        }
+        Ok(())
+    }
+
+    /// Merges an update into the stored version (if any). Empty fields in
+    /// `update` keep the stored value; a stored owner must match.
+    pub fn merge_update(existing: Option<&Self>, update: Self) -> Result<Self> {
+        Self::validate_name(&update.name)?;
+        let existing = match existing {
+            Some(existing) => existing,
+            None => return Ok(update),
+        };
+        if !existing.owner.is_empty() && existing.owner != update.owner {
+            return Err(anyhow!(
+                "Saved query '{}' belongs to '{}'",
+                existing.name,
+                existing.owner
+            ));
+        }
+        let keep_if_empty = |new: String, old: &str| {
+            if new.is_empty() { old.to_string() } else { new }
+        };
+        Ok(Self {
+            title: keep_if_empty(update.title, &existing.title),
+            description: keep_if_empty(update.description, &existing.description),
+            owner: keep_if_empty(update.owner, &existing.owner),
+            ..update
+        })
+    }
+
+    pub fn as_json(&self) -> Value {
+        json!({
+            "name": self.name,
+            "title": self.title,
+            "description": self.description,
+            "owner": self.owner,
+            "psid": self.psid,
+            "updated": self.updated,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn saved(name: &str, owner: &str, psid: u64) -> SavedQuery {
+        SavedQuery {
+            name: name.to_string(),
+            title: format!("Title of {name}"),
+            description: "Some description".to_string(),
+            owner: owner.to_string(),
+            psid,
+            updated: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_validate_name() {
+        assert!(SavedQuery::validate_name("wpmed-unreferenced").is_ok());
+        assert!(SavedQuery::validate_name("list_2024").is_ok());
+        assert!(SavedQuery::validate_name("").is_err());
+        assert!(SavedQuery::validate_name("Upper").is_err());
+        assert!(SavedQuery::validate_name("with space").is_err());
+        assert!(SavedQuery::validate_name("../etc").is_err());
+        assert!(SavedQuery::validate_name(&"a".repeat(MAX_NAME_LENGTH + 1)).is_err());
+    }
+
+    #[test]
+    fn test_merge_update_new() {
+        let update = saved("new", "", 1);
+        assert_eq!(
+            SavedQuery::merge_update(None, update.clone()).unwrap(),
+            update
+        );
+    }
+
+    #[test]
+    fn test_merge_update_keeps_empty_fields() {
+        let existing = saved("q", "wpmed", 1);
+        let update = SavedQuery {
+            name: "q".to_string(),
+            owner: "wpmed".to_string(),
+            psid: 2,
+            ..Default::default()
+        };
+        let merged = SavedQuery::merge_update(Some(&existing), update).unwrap();
+        assert_eq!(merged.psid, 2);
+        assert_eq!(merged.title, existing.title);
+        assert_eq!(merged.description, existing.description);
+        assert_eq!(merged.owner, "wpmed");
+    }
+
+    #[test]
+    fn test_merge_update_owner_mismatch() {
+        let existing = saved("q", "wpmed", 1);
+        assert!(SavedQuery::merge_update(Some(&existing), saved("q", "other", 2)).is_err());
+        assert!(SavedQuery::merge_update(Some(&existing), saved("q", "", 2)).is_err());
+    }
+
+    #[test]
+    fn test_merge_update_claims_unowned() {
+        let existing = saved("q", "", 1);
+        let merged = SavedQuery::merge_update(Some(&existing), saved("q", "wpmed", 2)).unwrap();
+        assert_eq!(merged.owner, "wpmed");
+    }
+}
@@ -0,0 +1,281 @@
+//! Recurring query runner.
+//!
+//! Runs a fixed set of PSIDs on an interval and writes each rendered result
+//! to a file, so users who need a regularly refreshed result do not have to
+//! poll the public web server from external cron jobs. Queries are run one
+//! at a time, which keeps the load on the replicas predictable.
+//!
+//! The schedule comes either from a JSON file:
+//!
+//! ```json
+//! {"queries":[{"psid":123,"every":"@daily","format":"tsv","output":"/data/123.tsv"}]}
+//! ```
+//!
+//! or from the `scheduled_query` table in the tool DB (see README).
+
+use crate::app_state::AppState;
+use crate::command_line::rebase_on_psid;
+use crate::form_parameters::FormParameters;
+use crate::platform::Platform;
+use anyhow::{Context, Result, anyhow};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Upper bound for the sleep between two scheduler ticks.
+const MAX_SLEEP: Duration = Duration::from_secs(60);
+
+/// Parses a cron-like interval: the `@hourly`/`@daily`/`@weekly` macros
+/// (with or without the `@`), or a number followed by `m`, `h` or `d`
+/// (e.g. `30m`, `6h`, `2d`).
+pub fn parse_interval(s: &str) -> Result<Duration> {
+    let s = s.trim();
+    let minutes: u64 = match s.trim_start_matches('@') {
+        "hourly" => 60,
+        "daily" => 60 * 24,
+        "weekly" => 60 * 24 * 7,
+        other => {
+            let unit_start = other.char_indices().last().map_or(0, |(pos, _)| pos);
+            let (number, unit) = other.split_at(unit_start);
+            let number = number
+                .parse::<u64>()
+                .map_err(|_| anyhow!("Invalid interval '{s}'"))?;
+            let unit_minutes = match unit {
+                "m" => 1,
+                "h" => 60,
+                "d" => 60 * 24,
+                _ => return Err(anyhow!("Invalid interval unit in '{s}'")),
+            };
+            number
+                .checked_mul(unit_minutes)
+                .ok_or_else(|| anyhow!("Invalid interval '{s}'"))?
+        }
+    };
+    if minutes == 0 {
+        return Err(anyhow!("Interval '{s}' must be at least one minute"));
+    }
+    let seconds = minutes
+        .checked_mul(60)
+        .ok_or_else(|| anyhow!("Invalid interval '{s}'"))?;
+    Ok(Duration::from_secs(seconds))
+}
+
+/// One recurring query.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ScheduledQuery {
+    pub psid: u64,
+    /// Interval, see [`parse_interval`].
+    pub every: String,
+    #[serde(default = "ScheduledQuery::default_format")]
+    pub format: String,
+    /// File the rendered result is written to. Replaced atomically.
+    pub output: PathBuf,
+    /// Also store a result snapshot for the PSID on every run.
+    #[serde(default)]
+    pub snapshot: bool,
+}
+
+impl ScheduledQuery {
+    fn default_format() -> String {
+        "json".to_string()
+    }
+
+    /// Builds the form parameters for one run: the stored PSID query, with
+    /// the schedule's output format patched in.
+    pub async fn form_parameters(&self, app_state: &AppState) -> Result<FormParameters> {
+        let mut form_parameters = FormParameters::new_from_pairs(HashMap::from([
+            ("psid".to_string(), self.psid.to_string()),
+            ("format".to_string(), self.format.to_owned()),
+            ("doit".to_string(), "1".to_string()),
+        ]));
+        rebase_on_psid(app_state, &mut form_parameters).await?;
+        Ok(form_parameters)
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Schedule {
+    pub queries: Vec<ScheduledQuery>,
+}
+
+impl Schedule {
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = std::fs::File::open(path)
+            .with_context(|| format!("Cannot open schedule file at {}", path.display()))?;
+        let ret: Self = serde_json::from_reader(file).with_context(|| {
+            format!("Cannot parse JSON from schedule file at {}", path.display())
+        })?;
+        ret.validate()?;
+        Ok(ret)
+    }
+
+    pub async fn from_tool_db(app_state: &AppState) -> Result<Self> {
+        let ret = Self {
+            queries: app_state.get_scheduled_queries().await?,
+        };
+        ret.validate()?;
+        Ok(ret)
+    }
+
+    fn validate(&self) -> Result<()> {
+        for query in &self.queries {
+            parse_interval(&query.every).with_context(|| format!("PSID {}", query.psid))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct Scheduler {
+    app_state: Arc<AppState>,
+    /// Each query with the time it is next due.
+    queries: Vec<(ScheduledQuery, Duration, Instant)>,
+}
+
+impl Scheduler {
+    /// All queries are due immediately after startup.
+    pub fn new(app_state: Arc<AppState>, schedule: Schedule) -> Result<Self> {
+        let now = Instant::now();
+        let queries = schedule
+            .queries
+            .into_iter()
+            .map(|query| {
+                let interval = parse_interval(&query.every)?;
+                Ok((query, interval, now))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { app_state, queries })
+    }
+
+    /// Runs forever. A failed query is logged and retried at its next slot.
+    pub async fn run(&mut self) -> Result<()> {
+        if self.queries.is_empty() {
+            return Err(anyhow!("Scheduler: No queries scheduled"));
+        }
+        loop {
+            for (query, interval, next_run) in &mut self.queries {
+                if *next_run > Instant::now() {
+                    continue;
+                }
+                *next_run = Instant::now() + *interval;
+                match Self::run_query(&self.app_state, query).await {
+                    Ok(()) => {
+                        tracing::info!("PSID {} written to {}", query.psid, query.output.display());
+                    }
+                    Err(e) => tracing::warn!("PSID {} failed: {e}", query.psid),
+                }
+            }
+            let next = self
+                .queries
+                .iter()
+                .map(|(_, _, next_run)| *next_run)
+                .min()
+                .unwrap_or_else(Instant::now);
+            tokio::time::sleep_until(next.min(Instant::now() + MAX_SLEEP)).await;
+        }
+    }
+
+    /// Runs a single scheduled query and writes its output file.
+    pub async fn run_query(app_state: &Arc<AppState>, query: &ScheduledQuery) -> Result<()> {
+        let form_parameters = query.form_parameters(app_state).await?;
+        let mut platform = Platform::new_from_parameters(&form_parameters, app_state.clone());
+        platform.run().await?;
+        platform.psid = Some(query.psid);
+        if query.snapshot {
+            platform.create_snapshot().await?;
+        }
+        let response = platform.get_response().await?;
+        if response.status != 200 {
+            return Err(anyhow!("Status {}: {}", response.status, response.s));
+        }
+        write_atomically(&query.output, response.body()).await
+    }
+}
+
+/// Writes to a temporary sibling file first, so readers never see a
+/// half-written result.
+async fn write_atomically(path: &Path, contents: &[u8]) -> Result<()> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    tokio::fs::write(&tmp_path, contents)
+        .await
+        .with_context(|| format!("Cannot write {}", path.display()))?;
+    tokio::fs::rename(&tmp_path, path)
+        .await
+        .with_context(|| format!("Cannot move result into {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_interval_macros() {
+        assert_eq!(
+            parse_interval("@hourly").unwrap(),
+            Duration::from_secs(3600)
+        );
+        assert_eq!(parse_interval("daily").unwrap(), Duration::from_secs(86400));
+        assert_eq!(
+            parse_interval("@weekly").unwrap(),
+            Duration::from_secs(7 * 86400)
+        );
+    }
+
+    #[test]
+    fn test_parse_interval_units() {
+        assert_eq!(parse_interval("30m").unwrap(), Duration::from_secs(1800));
+        assert_eq!(
+            parse_interval(" 6h ").unwrap(),
+            Duration::from_secs(6 * 3600)
+        );
+        assert_eq!(
+            parse_interval("2d").unwrap(),
+            Duration::from_secs(2 * 86400)
+        );
+    }
+
+    #[test]
+    fn test_parse_interval_invalid() {
+        assert!(parse_interval("").is_err());
+        assert!(parse_interval("0m").is_err());
+        assert!(parse_interval("5s").is_err());
+        assert!(parse_interval("h").is_err());
+        assert!(parse_interval("* * * * *").is_err());
+        assert!(parse_interval("18446744073709551615d").is_err());
+        assert!(parse_interval("307445734561825861m").is_err());
+    }
+
+    #[test]
+    fn test_schedule_deserializes_with_defaults() {
+        let json = r#"{"queries":[
+            {"psid":123,"every":"@daily","output":"/tmp/123.json"},
+            {"psid":456,"every":"6h","format":"tsv","output":"/tmp/456.tsv","snapshot":true}
+        ]}"#;
+        let schedule: Schedule = serde_json::from_str(json).unwrap();
+        assert_eq!(schedule.queries.len(), 2);
+        assert_eq!(schedule.queries[0].format, "json");
+        assert!(!schedule.queries[0].snapshot);
+        assert_eq!(schedule.queries[1].format, "tsv");
+        assert!(schedule.queries[1].snapshot);
+        assert!(schedule.validate().is_ok());
+    }
+
+    #[test]
+    fn test_schedule_validate_rejects_bad_interval() {
+        let schedule = Schedule {
+            queries: vec![ScheduledQuery {
+                psid: 1,
+                every: "sometimes".to_string(),
+                format: "json".to_string(),
+                output: PathBuf::from("/tmp/1.json"),
+                snapshot: false,
+            }],
+        };
+        assert!(schedule.validate().is_err());
+    }
+}
@@ -0,0 +1,169 @@
+//! Stored result snapshots for PSIDs.
+//!
+//! A snapshot freezes the title list a PSID produced at one point in time,
+//! so the result can be cited and re-served (`?psid=…&snapshot=…`) without
+//! re-running the query against the replicas. Only titles and namespaces
+//! are stored; everything else (sizes, Wikidata items, …) is metadata that
+//! the renderers either fetch lazily or leave blank.
+//!
+//! The payload is a newline-separated list of `namespace<TAB>title` lines.
+//! It is compressed server-side with `MySQL`'s `COMPRESS()`, so this module
+//! only deals with the plain-text form.
+
+use crate::pagelist::PageList;
+use crate::pagelist_entry::PageListEntry;
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use wikimisc::mediawiki::api::NamespaceID;
+use wikimisc::mediawiki::title::Title;
+
+/// Most titles stored in one snapshot.
+pub const MAX_SNAPSHOT_ENTRIES: usize = 500_000;
+/// Web requests don't store another snapshot of a PSID within this many
+/// seconds of the latest one.
+pub const MIN_WEB_SNAPSHOT_INTERVAL_SECONDS: i64 = 600;
+
+/// Which snapshot of a PSID to load.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotSelector {
+    /// The most recently created snapshot.
+    Latest,
+    /// A specific snapshot ID.
+    Id(u64),
+}
+
+impl SnapshotSelector {
+    /// Parses the `snapshot=` parameter: `latest` or a numeric snapshot ID.
+    pub fn from_param(s: &str) -> Result<Self> {
+        match s.trim() {
+            "latest" => Ok(Self::Latest),
+            other => other
+                .parse::<u64>()
+                .map(Self::Id)
+                .map_err(|_| anyhow!("Invalid snapshot '{other}', expected a number or 'latest'")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    pub id: u64,
+    pub psid: u64,
+    pub wiki: String,
+    /// `YYYY-MM-DD HH:MM:SS` (UTC), matching the `query.created` column.
+    pub created: String,
+    pub titles: Vec<Title>,
+}
+
+impl Snapshot {
+    /// Serialises `titles` into the plain-text payload stored in the tool DB.
+    pub fn encode_titles(titles: &[Title]) -> String {
+        titles
+            .iter()
+            .map(|t| format!("{}\t{}", t.namespace_id(), t.with_underscores()))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    /// Inverse of [`Self::encode_titles`]. Malformed lines are skipped.
+    pub fn decode_titles(payload: &str) -> Vec<Title> {
+        payload
+            .lines()
+            .filter_map(|line| {
+                let (ns, title) = line.split_once('\t')?;
+                let ns = ns.parse::<NamespaceID>().ok()?;
+                if title.is_empty() {
+                    return None;
+                }
+                Some(Title::new(title, ns))
+            })
+            .collect()
+    }
+
+    /// Whether a snapshot created at `created` (`YYYY-MM-DD HH:MM:SS`, UTC)
+    /// is less than `seconds` old at `now`. Unparseable times count as old.
+    pub fn is_younger_than(created: &str, seconds: i64, now: DateTime<Utc>) -> bool {
+        NaiveDateTime::parse_from_str(created, "%Y-%m-%d %H:%M:%S")
+            .is_ok_and(|created| (now.naive_utc() - created).num_seconds() < seconds)
+    }
+
+    /// Builds a fresh `PageList` holding the snapshot titles.
+    pub fn to_pagelist(&self) -> PageList {
+        let ret = PageList::new_from_wiki_with_capacity(&self.wiki, self.titles.len());
+        self.titles
+            .iter()
+            .for_each(|t| ret.add_entry(PageListEntry::new(t.to_owned())));
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selector_from_param() {
+        assert_eq!(
+            SnapshotSelector::from_param("latest").unwrap(),
+            SnapshotSelector::Latest
+        );
+        assert_eq!(
+            SnapshotSelector::from_param(" 42 ").unwrap(),
+            SnapshotSelector::Id(42)
+        );
+        assert!(SnapshotSelector::from_param("yesterday").is_err());
+        assert!(SnapshotSelector::from_param("").is_err());
+    }
+
+    #[test]
+    fn test_encode_decode_round_trip() {
+        let titles = vec![
+            Title::new("Magnus Manske", 0),
+            Title::new("Some_cat", 14),
+            Title::new("Foo:bar", 2),
+        ];
+        let payload = Snapshot::encode_titles(&titles);
+        assert_eq!(payload, "0\tMagnus_Manske\n14\tSome_cat\n2\tFoo:bar");
+        assert_eq!(Snapshot::decode_titles(&payload), titles);
+    }
+
+    #[test]
+    fn test_decode_skips_malformed_lines() {
+        let payload = "0\tGood\nno-tab\nx\tBad_ns\n6\t\n\n14\tAlso_good";
+        let titles = Snapshot::decode_titles(payload);
+        assert_eq!(
+            titles,
+            vec![Title::new("Good", 0), Title::new("Also_good", 14)]
+        );
+    }
+
+    #[test]
+    fn test_is_younger_than() {
+        let now = DateTime::parse_from_rfc3339("2024-01-01T00:10:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert!(Snapshot::is_younger_than("2024-01-01 00:05:00", 600, now));
+        assert!(!Snapshot::is_younger_than("2024-01-01 00:00:00", 600, now));
+        assert!(!Snapshot::is_younger_than("", 600, now));
+    }
+
+    #[test]
+    fn test_encode_empty() {
+        assert_eq!(Snapshot::encode_titles(&[]), "");
+        assert!(Snapshot::decode_titles("").is_empty());
+    }
+
+    #[test]
+    fn test_to_pagelist() {
+        let snapshot = Snapshot {
+            id: 1,
+            psid: 123,
+            wiki: "enwiki".to_string(),
+            created: "2024-01-01 00:00:00".to_string(),
+            titles: vec![Title::new("A", 0), Title::new("B", 0)],
+        };
+        let pl = snapshot.to_pagelist();
+        assert_eq!(pl.wiki(), Some("enwiki".to_string()));
+        assert_eq!(pl.len(), 2);
+    }
+}
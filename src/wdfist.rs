@@ -8,36 +8,40 @@ use mysql_async as my;
 use mysql_async::Value as MyValue;
 use mysql_async::from_row;
 use mysql_async::prelude::Queryable;
+use property::TargetProperty;
 use rayon::prelude::*;
 use regex::Regex;
+use scoring::{Evidence, FileInfo, FollowMode};
 use serde_json::Value;
 use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, LazyLock, Mutex};
 use wikimisc::mediawiki::api::Api;
 
+mod property;
+mod scoring;
+
 static RE_FILETYPE: LazyLock<Regex> = LazyLock::new(|| {
     Regex::new(r#"^(.+)\.([^.]+)$"#).expect("WDfist::is_valid_filename RE_FILETYPE is invalid")
 });
-static RE_KEY_PHRASES: LazyLock<Regex> = LazyLock::new(|| {
-    Regex::new(r#"(Flag_of_|Crystal_Clear_|Nuvola_|Kit_)"#)
-        .expect("WDfist::is_valid_filename RE_KEY_PHRASES is invalid")
-});
-static RE_KEY_PHRASES_PNG: LazyLock<Regex> = LazyLock::new(|| {
-    Regex::new(r#"(600px_)"#).expect("WDfist::is_valid_filename RE_KEY_PHRASES_PNG is invalid")
-});
 
 pub const MIN_IGNORE_DB_FILE_COUNT: usize = 3;
 pub const MAX_FILE_COUNT_IN_RESULT_SET: usize = 5;
 pub const NEARBY_FILES_RADIUS_IN_METERS: usize = 100;
 pub const MAX_WIKI_API_THREADS: usize = 10;
+pub const MAX_COMMONS_CATEGORY_DEPTH: usize = 3;
+/// Most files taken from Commons categories per item, over all levels
+pub const MAX_COMMONS_CATEGORY_FILES_PER_ITEM: usize = 500;
+pub const WIKIDATA_API_ENTITY_BATCH_SIZE: usize = 50;
 
 #[derive(Debug, Clone)]
 pub struct WDfist {
     item2files: HashMap<String, HashMap<String, usize>>,
+    item2evidence: HashMap<String, HashMap<String, Evidence>>,
     items: Vec<String>,
     files2ignore: HashSet<String>, // Requires normailzed, valid filenames
     form_parameters: FormParameters,
     state: Arc<AppState>,
+    property: TargetProperty,
     wdf_allow_svg: bool,
     wdf_only_jpeg: bool,
 }
@@ -62,10 +66,12 @@ impl WDfist {
         items.dedup();
         Some(Self {
             item2files: HashMap::new(),
+            item2evidence: HashMap::new(),
             items,
             files2ignore: HashSet::new(),
             form_parameters: platform.form_parameters().clone(),
             state: platform.state(),
+            property: TargetProperty::Image,
             wdf_allow_svg: false,
             wdf_only_jpeg: false,
         })
@@ -73,6 +79,19 @@ impl WDfist {
 
     pub async fn run(&mut self) -> Result<Value> {
         let mut j = json!({"status":"OK","data":{}});
+        let property = self
+            .form_parameters
+            .params
+            .get("wdf_property")
+            .map_or("", |s| s.as_str());
+        self.property = match TargetProperty::from_param(property) {
+            Some(property) => property,
+            None => {
+                j["status"] = json!(format!("Unsupported property '{property}'"));
+                return Ok(j);
+            }
+        };
+        j["property"] = json!(self.property.id());
         self.wdf_allow_svg = self.bool_param("wdf_allow_svg");
         self.wdf_only_jpeg = self.bool_param("wdf_only_jpeg");
         if self.items.is_empty() {
@@ -98,12 +117,13 @@ impl WDfist {
             self.follow_search_commons().await?;
         }
         if self.bool_param("wdf_commons_cats") {
-            self.follow_commons_cats()?;
+            self.follow_commons_cats().await?;
         }
 
         self.filter_files().await?;
 
         j["data"] = json!(&self.item2files);
+        j["ranked"] = self.rank_candidates().await?;
         Ok(j)
     }
 
@@ -151,7 +171,7 @@ impl WDfist {
         wiki: &str,
         page_file: Vec<(String, String)>,
     ) -> Result<Vec<(String, String)>> {
-        if !self.bool_param("wdf_only_page_images") {
+        if !self.property.uses_page_images() || !self.bool_param("wdf_only_page_images") {
             return Ok(page_file);
         }
 
@@ -194,7 +214,7 @@ impl WDfist {
             .lock()
             .map_err(|e| anyhow!("{e}"))?
             .iter()
-            .for_each(|(q, file)| self.add_file_to_item(q, file));
+            .for_each(|(q, file)| self.add_file_to_item(q, file, FollowMode::LanguageLinks, None));
 
         Ok(())
     }
@@ -275,9 +295,9 @@ impl WDfist {
         }
 
         let add_item_file = Self::follow_coords_get_item_files(results, page_coords);
-        add_item_file
-            .iter()
-            .for_each(|(q, file)| self.add_file_to_item(q, file));
+        add_item_file.iter().for_each(|(q, file, distance)| {
+            self.add_file_to_item(q, file, FollowMode::Coordinates, *distance);
+        });
 
         Ok(())
     }
@@ -285,13 +305,13 @@ impl WDfist {
     fn follow_coords_get_item_files(
         results: Vec<Value>,
         page_coords: Vec<(String, f64, f64)>,
-    ) -> Vec<(String, String)> {
-        let add_item_file: Vec<(String, String)> = results
+    ) -> Vec<(String, String, Option<f64>)> {
+        let add_item_file: Vec<(String, String, Option<f64>)> = results
             .iter()
             .zip(page_coords)
             .filter_map(|(result, (q, _lat, _lon))| {
                 let images = result["query"]["geosearch"].as_array()?;
-                let item_file: Vec<(String, String)> = images
+                let item_file: Vec<(String, String, Option<f64>)> = images
                     .par_iter()
                     .filter_map(|j| match j["title"].as_str() {
                         Some(filename) => {
@@ -300,7 +320,7 @@ impl WDfist {
                             if filename.is_empty() {
                                 None
                             } else {
-                                Some((q.to_string(), filename))
+                                Some((q.to_string(), filename, j["dist"].as_f64()))
                             }
                         }
                         None => None,
@@ -323,19 +343,15 @@ impl WDfist {
     }
 
     fn get_commons_search_query(&self, label: &str) -> String {
-        let svg = if self.wdf_allow_svg {
-            ""
-        } else {
-            "-filemime:svg"
-        };
         format!(
-            "{} -filemime:pdf -filemime:djvu -filemime:gif {}",
-            &label, svg
+            "{} {}",
+            &label,
+            self.property.search_terms(self.wdf_allow_svg)
         )
     }
 
     async fn follow_search_commons(&mut self) -> Result<()> {
-        let batches = self.follow_search_commons_prepare_batches();
+        let batches = Self::english_label_batches(&self.items);
         let pagelist = PageList::new_from_wiki("wikidatawiki");
         let rows = pagelist
             .run_batch_queries_with_cluster(&self.state, batches, DatabaseCluster::X3)
@@ -351,16 +367,16 @@ impl WDfist {
         // Add files
         add_item_file
             .iter()
-            .for_each(|(q, file)| self.add_file_to_item(q, file));
+            .for_each(|(q, file)| self.add_file_to_item(q, file, FollowMode::SearchCommons, None));
 
         Ok(())
     }
 
-    fn follow_search_commons_prepare_batches(&mut self) -> Vec<(String, Vec<MyValue>)> {
+    fn english_label_batches(items: &[String]) -> Vec<(String, Vec<MyValue>)> {
         // Prepare batches
         // TODO uses X3 cluster, but fix wbt_type
         let mut batches: Vec<SQLtuple> = vec![];
-        self.items.chunks(PAGE_BATCH_SIZE).for_each(|chunk| {
+        items.chunks(PAGE_BATCH_SIZE).for_each(|chunk| {
             let mut sql = crate::datasource::full_entity_id_to_number(chunk);
             sql.0 = format!(
                 "SELECT concat('Q',wbit_item_id) AS term_full_entity_id, wbx_text as term_text
@@ -432,12 +448,206 @@ impl WDfist {
         add_item_file
     }
 
-    #[allow(clippy::unused_self)]
-    const fn follow_commons_cats(&mut self) -> Result<()> {
-        // TODO
+    async fn follow_commons_cats(&mut self) -> Result<()> {
+        let depth = self.commons_category_depth();
+        let mut category2items = self.get_commons_categories().await?;
+        let mut seen_categories: HashSet<String> = category2items.keys().cloned().collect();
+        let mut item2category_files: HashMap<String, HashSet<String>> = HashMap::new();
+        for level in 0..=depth {
+            if category2items.is_empty() {
+                break;
+            }
+            let members = self.get_commons_category_members(&category2items).await?;
+            let subcategory2items = Self::add_category_members(
+                members,
+                &category2items,
+                &seen_categories,
+                level < depth,
+                &mut item2category_files,
+            );
+            seen_categories.extend(subcategory2items.keys().cloned());
+            category2items = subcategory2items;
+        }
+
+        // Add files
+        item2category_files.iter().for_each(|(q, files)| {
+            files.iter().for_each(|file| {
+                self.add_file_to_item(q, file, FollowMode::CommonsCategories, None);
+            });
+        });
+
         Ok(())
     }
 
+    /// Adds the files among `members` (category, title, namespace) to the items
+    /// of their category, at most `MAX_COMMONS_CATEGORY_FILES_PER_ITEM` per
+    /// item. Returns the unseen subcategories to descend into, with the items
+    /// that still take files, if `descend` is set.
+    fn add_category_members(
+        members: Vec<(String, String, i64)>,
+        category2items: &HashMap<String, HashSet<String>>,
+        seen_categories: &HashSet<String>,
+        descend: bool,
+        item2files: &mut HashMap<String, HashSet<String>>,
+    ) -> HashMap<String, HashSet<String>> {
+        let mut subcategory2items: HashMap<String, HashSet<String>> = HashMap::new();
+        for (category, page, namespace) in members {
+            let Some(items) = category2items.get(&category) else {
+                continue;
+            };
+            match namespace {
+                6 => items.iter().for_each(|q| {
+                    let files = item2files.entry(q.to_owned()).or_default();
+                    if files.len() < MAX_COMMONS_CATEGORY_FILES_PER_ITEM {
+                        files.insert(page.to_owned());
+                    }
+                }),
+                14 if descend && !seen_categories.contains(&page) => subcategory2items
+                    .entry(page)
+                    .or_default()
+                    .extend(items.iter().cloned()),
+                _ => {}
+            }
+        }
+        subcategory2items.values_mut().for_each(|items| {
+            items.retain(|q| {
+                item2files
+                    .get(q)
+                    .is_none_or(|files| files.len() < MAX_COMMONS_CATEGORY_FILES_PER_ITEM)
+            });
+        });
+        subcategory2items.retain(|_category, items| !items.is_empty());
+        subcategory2items
+    }
+
+    fn commons_category_depth(&self) -> usize {
+        self.form_parameters
+            .params
+            .get("wdf_commons_cats_depth")
+            .and_then(|s| s.trim().parse::<usize>().ok())
+            .unwrap_or(0)
+            .min(MAX_COMMONS_CATEGORY_DEPTH)
+    }
+
+    /// Returns the Commons category (without namespace prefix) for each item, from the
+    /// Commons sitelink, or from P373 for items without a category sitelink.
+    async fn get_commons_categories(&self) -> Result<HashMap<String, HashSet<String>>> {
+        let mut batches: Vec<SQLtuple> = vec![];
+        self.items.chunks(PAGE_BATCH_SIZE).for_each(|chunk| {
+            let mut sql = crate::datasource::full_entity_id_to_number(chunk);
+            sql.0 = format!("SELECT ips_item_id,ips_site_page FROM wb_items_per_site WHERE ips_site_id='commonswiki' AND ips_item_id IN ({})",&sql.0) ;
+            batches.push(sql);
+        });
+        let pagelist = PageList::new_from_wiki("wikidatawiki");
+        let rows = pagelist.run_batch_queries(&self.state, batches).await?;
+
+        let mut category2items: HashMap<String, HashSet<String>> = HashMap::new();
+        let mut items_with_category: HashSet<String> = HashSet::new();
+        rows.iter()
+            .map(|row| my::from_row::<(u64, String)>(row.to_owned()))
+            .filter_map(|(item_id, page)| {
+                Some((
+                    format!("Q{item_id}"),
+                    Self::commons_category_from_sitelink(&page)?,
+                ))
+            })
+            .for_each(|(q, category)| {
+                items_with_category.insert(q.to_owned());
+                category2items.entry(category).or_default().insert(q);
+            });
+
+        // P373 for the remaining items
+        let items: Vec<String> = self
+            .items
+            .iter()
+            .filter(|q| !items_with_category.contains(*q))
+            .cloned()
+            .collect();
+        if items.is_empty() {
+            return Ok(category2items);
+        }
+        let api = Api::new("https://www.wikidata.org/w/api.php")
+            .await
+            .map_err(|e| anyhow!("{e}"))?;
+        for chunk in items.chunks(WIKIDATA_API_ENTITY_BATCH_SIZE) {
+            let params = api.params_into(&[
+                ("action", "wbgetentities"),
+                ("ids", &chunk.join("|")),
+                ("props", "claims"),
+            ]);
+            let result = match api.get_query_api_json(&params).await {
+                Ok(result) => result,
+                Err(e) => {
+                    tracing::warn!(
+                        "WDfist: Could not load P373 of {} items, skipping them: {e}",
+                        chunk.len()
+                    );
+                    continue;
+                }
+            };
+            Self::commons_categories_from_entities(&result)
+                .into_iter()
+                .for_each(|(q, category)| {
+                    category2items.entry(category).or_default().insert(q);
+                });
+        }
+        Ok(category2items)
+    }
+
+    fn commons_category_from_sitelink(page: &str) -> Option<String> {
+        let category = Self::normalize_filename(page.strip_prefix("Category:")?);
+        if category.is_empty() {
+            None
+        } else {
+            Some(category)
+        }
+    }
+
+    /// Extracts (item, category) pairs from the P373 claims of a `wbgetentities` result
+    fn commons_categories_from_entities(result: &Value) -> Vec<(String, String)> {
+        let Some(entities) = result["entities"].as_object() else {
+            return vec![];
+        };
+        entities
+            .iter()
+            .filter_map(|(q, entity)| Some((q, entity["claims"]["P373"].as_array()?)))
+            .flat_map(|(q, claims)| {
+                claims.iter().filter_map(move |claim| {
+                    let category = claim["mainsnak"]["datavalue"]["value"].as_str()?;
+                    let category = Self::normalize_filename(category);
+                    if category.is_empty() {
+                        None
+                    } else {
+                        Some((q.to_string(), category))
+                    }
+                })
+            })
+            .collect()
+    }
+
+    /// Returns (category, member title, member namespace) for files and subcategories
+    async fn get_commons_category_members(
+        &self,
+        category2items: &HashMap<String, HashSet<String>>,
+    ) -> Result<Vec<(String, String, i64)>> {
+        let categories: Vec<String> = category2items.keys().cloned().collect();
+        let mut batches: Vec<SQLtuple> = vec![];
+        categories.chunks(PAGE_BATCH_SIZE).for_each(|chunk| {
+            let mut sql = crate::datasource::prep_quote(chunk);
+            sql.0 = format!("SELECT lt_title,page_title,page_namespace FROM page,categorylinks,linktarget WHERE cl_from=page_id AND cl_target_id=lt_id AND lt_namespace=14 AND lt_title IN ({})",&sql.0) ;
+            sql.0 += " AND page_namespace IN (6,14) AND page_is_redirect=0" ;
+            sql.0 += " AND NOT EXISTS (SELECT * FROM categorylinks c2,linktarget l2 WHERE l2.lt_id=c2.cl_target_id AND page_id=c2.cl_from AND l2.lt_namespace=14 AND l2.lt_title='Crop_for_Wikidata')" ; // To-be-cropped
+            batches.push(sql);
+        });
+        let rows = PageList::new_from_wiki("commonswiki")
+            .run_batch_queries(&self.state, batches)
+            .await?;
+        Ok(rows
+            .par_iter()
+            .map(|row| my::from_row::<(String, String, i64)>(row.to_owned()))
+            .collect())
+    }
+
     fn bool_param(&self, key: &str) -> bool {
         match self.form_parameters.params.get(key) {
             Some(v) => !v.trim().is_empty(),
@@ -446,6 +656,9 @@ impl WDfist {
     }
 
     async fn seed_ignore_files(&mut self) -> Result<()> {
+        if !self.property.uses_ignore_lists() {
+            return Ok(());
+        }
         self.seed_ignore_files_from_wiki_page().await?;
         self.seed_ignore_files_from_ignore_database().await?;
         Ok(())
@@ -508,12 +721,15 @@ impl WDfist {
 
     async fn filter_items(&mut self) -> Result<()> {
         // To batches (all items are ns=0)
-        let wdf_only_items_without_p18 = self.bool_param("wdf_only_items_without_p18");
+        // `wdf_only_items_without_p18` predates `wdf_property`, and applies to the target property
+        let only_items_without_property = self.bool_param("wdf_only_items_without_property")
+            || self.bool_param("wdf_only_items_without_p18");
+        let property = self.property.id();
         let mut batches: Vec<SQLtuple> = vec![];
         self.items.chunks(PAGE_BATCH_SIZE).for_each(|chunk| {
             let mut sql = crate::datasource::prep_quote(chunk);
             sql.0 = format!("SELECT page_title FROM page WHERE page_namespace=0 AND page_is_redirect=0 AND page_title IN ({})",&sql.0) ;
-            if  wdf_only_items_without_p18 {sql.0 += " AND NOT EXISTS (SELECT * FROM pagelinks,linktarget WHERE pl_target_id=lt_id AND pl_from=page_id AND lt_namespace=120 AND lt_title='P18')" ;}
+            if  only_items_without_property {sql.0 += &format!(" AND NOT EXISTS (SELECT * FROM pagelinks,linktarget WHERE pl_target_id=lt_id AND pl_from=page_id AND lt_namespace=120 AND lt_title='{property}')") ;}
             sql.0 += " AND NOT EXISTS (SELECT * FROM pagelinks,linktarget WHERE pl_target_id=lt_id AND pl_from=page_id AND lt_namespace=0 AND lt_title IN ('Q13406463','Q4167410'))" ; // No list/disambig
             batches.push(sql);
         });
@@ -537,7 +753,7 @@ impl WDfist {
     }
 
     async fn filter_files_from_ignore_database(&mut self) -> Result<()> {
-        if self.items.is_empty() {
+        if self.items.is_empty() || !self.property.uses_ignore_lists() {
             return Ok(());
         }
 
@@ -722,27 +938,36 @@ impl WDfist {
         match RE_FILETYPE.captures_iter(filename).next() {
             Some(cap) => {
                 let filetype = cap[2].to_lowercase();
-                if self.wdf_only_jpeg && filetype != "jpg" && filetype != "jpeg" {
-                    return false;
-                }
-                match filetype.as_str() {
-                    "svg" => return self.wdf_allow_svg,
-                    "pdf" | "gif" | "djvu" => return false,
-                    _ => {}
-                };
-                if RE_KEY_PHRASES.is_match(filename) {
+                if self.wdf_only_jpeg
+                    && self.property.is_image()
+                    && filetype != "jpg"
+                    && filetype != "jpeg"
+                {
                     return false;
                 }
-                !(filetype == "png" && RE_KEY_PHRASES_PNG.is_match(filename))
+                self.property
+                    .is_valid_filename(filename, &filetype, self.wdf_allow_svg)
             }
             None => false,
         }
     }
 
-    fn add_file_to_item(&mut self, item: &str, filename: &str) {
+    fn add_file_to_item(
+        &mut self,
+        item: &str,
+        filename: &str,
+        mode: FollowMode,
+        distance: Option<f64>,
+    ) {
         if !self.is_valid_filename(filename) {
             return;
         }
+        self.item2evidence
+            .entry(item.to_string())
+            .or_default()
+            .entry(filename.to_string())
+            .or_default()
+            .add(mode, distance);
         match self.item2files.get_mut(item) {
             Some(ref mut files) => match files.get_mut(filename) {
                 Some(ref mut file2count) => {
@@ -760,6 +985,84 @@ impl WDfist {
         }
     }
 
+    /// Scores the remaining candidates, best first per item
+    async fn rank_candidates(&self) -> Result<Value> {
+        if self.item2files.is_empty() {
+            return Ok(json!({}));
+        }
+        let file2info = self.get_file_info().await?;
+        let items: Vec<String> = self.item2files.keys().cloned().collect();
+        let rows = PageList::new_from_wiki("wikidatawiki")
+            .run_batch_queries_with_cluster(
+                &self.state,
+                Self::english_label_batches(&items),
+                DatabaseCluster::X3,
+            )
+            .await?;
+        let item2label: HashMap<String, String> = Self::follow_search_commons_get_item2label(rows)
+            .into_iter()
+            .collect();
+        let ranked = scoring::rank_item_files(
+            &self.item2files,
+            &self.item2evidence,
+            &file2info,
+            &item2label,
+            self.property,
+            NEARBY_FILES_RADIUS_IN_METERS as f64,
+        );
+        Ok(ranked
+            .iter()
+            .map(|(q, candidates)| {
+                let candidates: Vec<Value> = candidates.iter().map(|c| c.to_json()).collect();
+                (q.to_owned(), json!(candidates))
+            })
+            .collect::<serde_json::Map<String, Value>>()
+            .into())
+    }
+
+    /// Global usage, dimensions and media type of all candidate files
+    async fn get_file_info(&self) -> Result<HashMap<String, FileInfo>> {
+        let mut files: Vec<String> = self
+            .item2files
+            .values()
+            .flat_map(|files| files.keys().cloned())
+            .collect();
+        files.par_sort();
+        files.dedup();
+
+        let mut info_batches: Vec<SQLtuple> = vec![];
+        let mut usage_batches: Vec<SQLtuple> = vec![];
+        files.chunks(PAGE_BATCH_SIZE).for_each(|chunk| {
+            let mut sql = crate::datasource::prep_quote(chunk);
+            sql.0 = format!("SELECT img_name,img_width,img_height,CONVERT(img_media_type USING utf8) FROM image WHERE img_name IN ({})",&sql.0) ;
+            info_batches.push(sql);
+            let mut sql = crate::datasource::prep_quote(chunk);
+            sql.0 = format!("SELECT gil_to,count(*) FROM globalimagelinks WHERE gil_to IN ({}) GROUP BY gil_to",&sql.0) ;
+            usage_batches.push(sql);
+        });
+
+        let pagelist = PageList::new_from_wiki("commonswiki");
+        let mut file2info: HashMap<String, FileInfo> = HashMap::new();
+        pagelist
+            .run_batch_queries(&self.state, info_batches)
+            .await?
+            .iter()
+            .map(|row| my::from_row::<(String, u64, u64, String)>(row.to_owned()))
+            .for_each(|(file, width, height, media_type)| {
+                let info = file2info.entry(file).or_default();
+                info.width = width;
+                info.height = height;
+                info.media_type = media_type;
+            });
+        pagelist
+            .run_batch_queries(&self.state, usage_batches)
+            .await?
+            .iter()
+            .map(|row| my::from_row::<(String, usize)>(row.to_owned()))
+            .for_each(|(file, usage)| file2info.entry(file).or_default().global_usage = usage);
+        Ok(file2info)
+    }
+
     async fn follow_search_commons_get_results(
         &self,
         params: Vec<HashMap<String, String>>,
@@ -843,10 +1146,12 @@ mod tests {
         };
         WDfist {
             item2files: HashMap::new(),
+            item2evidence: HashMap::new(),
             items: items.par_iter().map(|s| s.to_string()).collect(),
             files2ignore: HashSet::new(),
             form_parameters,
             state: get_state().await,
+            property: TargetProperty::Image,
             wdf_allow_svg: false,
             wdf_only_jpeg: false,
         }
@@ -959,6 +1264,61 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_commons_categories_from_entities() {
+        let result = json!({"entities":{
+            "Q1":{"claims":{"P373":[{"mainsnak":{"datavalue":{"value":"Foo bar"}}}]}},
+            "Q2":{"claims":{"P18":[{"mainsnak":{"datavalue":{"value":"Baz.jpg"}}}]}},
+        }});
+        assert_eq!(
+            WDfist::commons_categories_from_entities(&result),
+            vec![("Q1".to_string(), "Foo_bar".to_string())]
+        );
+        assert_eq!(
+            WDfist::commons_category_from_sitelink("Category:Foo bar"),
+            Some("Foo_bar".to_string())
+        );
+        assert_eq!(WDfist::commons_category_from_sitelink("Foo bar"), None);
+    }
+
+    #[test]
+    fn test_add_category_members_caps_files_per_item() {
+        let category2items = HashMap::from([
+            ("Big".to_string(), HashSet::from(["Q1".to_string()])),
+            ("Small".to_string(), HashSet::from(["Q2".to_string()])),
+        ]);
+        let mut members: Vec<(String, String, i64)> = (0..MAX_COMMONS_CATEGORY_FILES_PER_ITEM + 10)
+            .map(|n| ("Big".to_string(), format!("File_{n}.jpg"), 6))
+            .collect();
+        members.push(("Small".to_string(), "Small.jpg".to_string(), 6));
+        members.push(("Big".to_string(), "Big_sub".to_string(), 14));
+        members.push(("Small".to_string(), "Small_sub".to_string(), 14));
+        let mut item2files = HashMap::new();
+        let subcategory2items = WDfist::add_category_members(
+            members,
+            &category2items,
+            &HashSet::new(),
+            true,
+            &mut item2files,
+        );
+        assert_eq!(item2files["Q1"].len(), MAX_COMMONS_CATEGORY_FILES_PER_ITEM);
+        assert_eq!(item2files["Q2"].len(), 1);
+        // Q1 is full, so its subcategory is not followed
+        assert_eq!(
+            subcategory2items,
+            HashMap::from([("Small_sub".to_string(), HashSet::from(["Q2".to_string()]))])
+        );
+    }
+
+    #[tokio::test]
+    #[ignore = "requires live config.json + Wikimedia APIs (Wikidata/Commons); run with --ignored"]
+    async fn test_follow_commons_cats() {
+        let params: Vec<(&str, &str)> = vec![];
+        let mut wdfist = get_wdfist(params, vec!["Q1339"]).await; // Johann Sebastian Bach
+        wdfist.follow_commons_cats().await.unwrap();
+        assert!(!wdfist.item2files.get("Q1339").unwrap().is_empty());
+    }
+
     #[tokio::test]
     #[ignore = "requires live config.json + Wikimedia APIs (Wikidata/Commons); run with --ignored"]
     async fn test_seed_ignore_files() {
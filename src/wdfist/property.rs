@@ -0,0 +1,221 @@
+//! The Wikidata property WDfist proposes files for, and the filename heuristics that go with it.
+
+use regex::Regex;
+use std::sync::LazyLock;
+
+static RE_KEY_PHRASES: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(Flag_of_|Crystal_Clear_|Nuvola_|Kit_)"#)
+        .expect("TargetProperty RE_KEY_PHRASES is invalid")
+});
+static RE_KEY_PHRASES_PNG: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(600px_)"#).expect("TargetProperty RE_KEY_PHRASES_PNG is invalid")
+});
+static RE_ICON_PHRASES: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(Crystal_Clear_|Nuvola_|Kit_)"#)
+        .expect("TargetProperty RE_ICON_PHRASES is invalid")
+});
+static RE_COAT_OF_ARMS: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"(?i)(coat_of_arms|wappen|escudo|blason|armoiries|stemma|herb_|címer|vapen|våpen)"#,
+    )
+    .expect("TargetProperty RE_COAT_OF_ARMS is invalid")
+});
+// The key phrases below are whole words of the filename, so that e.g.
+// `Flagstaff.jpg` is not a flag and `Village_square.jpg` is not a map
+static RE_FLAG: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?i)(^|_)(flag|flagge|drapeau|bandera|bandiera|vlag|flaga|zastava)(_|\.)"#)
+        .expect("TargetProperty RE_FLAG is invalid")
+});
+// `X_in_Y.svg` is the usual name of locator maps on Wikipedia
+static RE_LOCATOR_MAP: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"(?i)((^|_)(locator_map|location_map|locator|lagekarte|karte|carte|mapa)(_|\.)|_in_.+\.svg$)"#,
+    )
+    .expect("TargetProperty RE_LOCATOR_MAP is invalid")
+});
+static RE_LOCATION_MAP: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?i)(^|_)(location_map|relief_map|map|karte|carte|mapa)(_|\.)"#)
+        .expect("TargetProperty RE_LOCATION_MAP is invalid")
+});
+static RE_LOGO: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?i)(^|_)logo(_|\.)"#).expect("TargetProperty RE_LOGO is invalid")
+});
+
+const DRAWING_FILE_TYPES: &[&str] = &["svg", "png", "jpg", "jpeg", "tif", "tiff"];
+const AUDIO_FILE_TYPES: &[&str] = &["ogg", "oga", "opus", "flac", "wav", "mp3"];
+
+/// The property WDfist looks for files for, set via `wdf_property` (default P18)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TargetProperty {
+    Image,
+    CoatOfArms,
+    Flag,
+    LocatorMap,
+    LocationMap,
+    Logo,
+    Audio,
+}
+
+impl TargetProperty {
+    pub fn from_param(s: &str) -> Option<Self> {
+        match s.trim().to_uppercase().as_str() {
+            "" | "P18" => Some(Self::Image),
+            "P94" => Some(Self::CoatOfArms),
+            "P41" => Some(Self::Flag),
+            "P242" => Some(Self::LocatorMap),
+            "P1943" => Some(Self::LocationMap),
+            "P154" => Some(Self::Logo),
+            "P51" => Some(Self::Audio),
+            _ => None,
+        }
+    }
+
+    pub const fn id(&self) -> &'static str {
+        match self {
+            Self::Image => "P18",
+            Self::CoatOfArms => "P94",
+            Self::Flag => "P41",
+            Self::LocatorMap => "P242",
+            Self::LocationMap => "P1943",
+            Self::Logo => "P154",
+            Self::Audio => "P51",
+        }
+    }
+
+    pub const fn is_image(&self) -> bool {
+        matches!(self, Self::Image)
+    }
+
+    /// Only photos have a page image on Wikipedia
+    pub const fn uses_page_images(&self) -> bool {
+        self.is_image()
+    }
+
+    /// The FIST_icons page and the ignore database are curated for P18 only
+    pub const fn uses_ignore_lists(&self) -> bool {
+        self.is_image()
+    }
+
+    /// Flags, coats of arms, maps and logos are widely used by design
+    pub const fn penalizes_wide_usage(&self) -> bool {
+        matches!(self, Self::Image | Self::Audio)
+    }
+
+    /// Commons media types (`img_media_type`) expected for this property, used in scoring
+    pub const fn expected_media_types(&self) -> &'static [&'static str] {
+        match self {
+            Self::Image => &["BITMAP"],
+            Self::CoatOfArms | Self::Flag | Self::LocatorMap | Self::LocationMap | Self::Logo => {
+                &["DRAWING", "BITMAP"]
+            }
+            Self::Audio => &["AUDIO"],
+        }
+    }
+
+    /// Extra Commons search terms, appended to the item label
+    pub fn search_terms(&self, allow_svg: bool) -> String {
+        match self {
+            Self::Image => {
+                let svg = if allow_svg { "" } else { "-filemime:svg" };
+                format!("-filemime:pdf -filemime:djvu -filemime:gif {svg}")
+            }
+            Self::CoatOfArms => "coat of arms -filemime:pdf -filemime:djvu".to_string(),
+            Self::Flag => "flag -filemime:pdf -filemime:djvu".to_string(),
+            Self::LocatorMap => "locator map -filemime:pdf -filemime:djvu".to_string(),
+            Self::LocationMap => "location map -filemime:pdf -filemime:djvu".to_string(),
+            Self::Logo => "logo -filemime:pdf -filemime:djvu".to_string(),
+            Self::Audio => "filetype:audio".to_string(),
+        }
+    }
+
+    /// Checks a normalized filename with its lowercase file type against the
+    /// property's heuristics. `allow_svg` only applies to P18, where SVGs are
+    /// rarely photos; the other image properties are mostly SVGs.
+    pub fn is_valid_filename(&self, filename: &str, filetype: &str, allow_svg: bool) -> bool {
+        match self {
+            Self::Image => {
+                match filetype {
+                    "svg" => return allow_svg,
+                    "pdf" | "gif" | "djvu" => return false,
+                    _ => {}
+                };
+                if RE_KEY_PHRASES.is_match(filename) {
+                    return false;
+                }
+                !(filetype == "png" && RE_KEY_PHRASES_PNG.is_match(filename))
+            }
+            Self::Audio => AUDIO_FILE_TYPES.contains(&filetype),
+            _ => {
+                if !DRAWING_FILE_TYPES.contains(&filetype) || RE_ICON_PHRASES.is_match(filename) {
+                    return false;
+                }
+                self.key_phrases().is_none_or(|re| re.is_match(filename))
+            }
+        }
+    }
+
+    /// Filenames for this property must match these phrases
+    fn key_phrases(&self) -> Option<&'static Regex> {
+        match self {
+            Self::CoatOfArms => Some(&*RE_COAT_OF_ARMS),
+            Self::Flag => Some(&*RE_FLAG),
+            Self::LocatorMap => Some(&*RE_LOCATOR_MAP),
+            Self::LocationMap => Some(&*RE_LOCATION_MAP),
+            Self::Logo => Some(&*RE_LOGO),
+            Self::Image | Self::Audio => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_param() {
+        assert_eq!(TargetProperty::from_param(""), Some(TargetProperty::Image));
+        assert_eq!(
+            TargetProperty::from_param("p41"),
+            Some(TargetProperty::Flag)
+        );
+        assert_eq!(
+            TargetProperty::from_param("P1943").map(|p| p.id()),
+            Some("P1943")
+        );
+        assert_eq!(TargetProperty::from_param("P31"), None);
+    }
+
+    #[test]
+    fn test_is_valid_filename() {
+        let flag = TargetProperty::Flag;
+        assert!(flag.is_valid_filename("Flag_of_Germany.svg", "svg", false));
+        assert!(!flag.is_valid_filename("Berlin_skyline.jpg", "jpg", false));
+        assert!(!flag.is_valid_filename("Flag_of_Germany.ogg", "ogg", false));
+        assert!(flag.is_valid_filename("Berlin_flag.svg", "svg", false));
+        assert!(!flag.is_valid_filename("Flagstaff_Arizona.jpg", "jpg", false));
+        let locator_map = TargetProperty::LocatorMap;
+        assert!(locator_map.is_valid_filename("Berlin_locator_map.svg", "svg", false));
+        assert!(locator_map.is_valid_filename("Locator_map_of_Berlin.png", "png", false));
+        assert!(locator_map.is_valid_filename("Bavaria_in_Germany.svg", "svg", false));
+        assert!(!locator_map.is_valid_filename("Church_in_Berlin.jpg", "jpg", false));
+        assert!(!locator_map.is_valid_filename("Village_square.jpg", "jpg", false));
+        let location_map = TargetProperty::LocationMap;
+        assert!(location_map.is_valid_filename("Germany_location_map.svg", "svg", false));
+        assert!(!location_map.is_valid_filename("Mapplethorpe.jpg", "jpg", false));
+        let coa = TargetProperty::CoatOfArms;
+        assert!(coa.is_valid_filename("Wappen_Berlin.svg", "svg", false));
+        assert!(coa.is_valid_filename("Coat_of_arms_of_Berlin.svg", "svg", false));
+        assert!(!coa.is_valid_filename("Nuvola_Coat_of_arms.svg", "svg", false));
+        let logo = TargetProperty::Logo;
+        assert!(logo.is_valid_filename("Wikidata_logo.svg", "svg", false));
+        assert!(logo.is_valid_filename("Logo_of_Berlin.png", "png", false));
+        assert!(!logo.is_valid_filename("Logone_river.jpg", "jpg", false));
+        assert!(!logo.is_valid_filename("Eulogos.svg", "svg", false));
+        let audio = TargetProperty::Audio;
+        assert!(audio.is_valid_filename("De-Berlin.ogg", "ogg", false));
+        assert!(!audio.is_valid_filename("Berlin.jpg", "jpg", false));
+        let image = TargetProperty::Image;
+        assert!(!image.is_valid_filename("Flag_of_Germany.jpg", "jpg", false));
+        assert!(image.is_valid_filename("Berlin.svg", "svg", true));
+    }
+}
@@ -0,0 +1,391 @@
+//! Scores WDfist file candidates, so that the most plausible image for an item
+//! comes first, and widely used icons, flags and maps sink to the bottom.
+
+use super::property::TargetProperty;
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// Points for each follow mode that found a file
+const FOLLOW_MODE_SCORE: f64 = 2.0;
+/// Files used on up to this many pages are plausibly used for the item's subject
+const MAX_SPECIFIC_USAGE: usize = 10;
+const SPECIFIC_USAGE_SCORE: f64 = 1.0;
+const MAX_WIDE_USAGE_PENALTY: f64 = 4.0;
+/// Images smaller than this (in either dimension) are thumbnails or icons
+const MIN_IMAGE_SIDE: u64 = 300;
+const SMALL_IMAGE_PENALTY: f64 = 2.0;
+const LARGE_IMAGE_PIXELS: u64 = 1_000_000;
+const LARGE_IMAGE_SCORE: f64 = 1.0;
+/// Width/height ratios beyond this are usually banners, panoramas or maps
+const MAX_ASPECT_RATIO: f64 = 3.0;
+const ASPECT_RATIO_PENALTY: f64 = 1.0;
+const MAX_DISTANCE_SCORE: f64 = 2.0;
+const MAX_LABEL_SIMILARITY_SCORE: f64 = 3.0;
+
+/// The ways WDfist can find a file candidate for an item
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum FollowMode {
+    LanguageLinks,
+    Coordinates,
+    SearchCommons,
+    CommonsCategories,
+}
+
+impl FollowMode {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::LanguageLinks => "langlinks",
+            Self::Coordinates => "coords",
+            Self::SearchCommons => "search_commons",
+            Self::CommonsCategories => "commons_cats",
+        }
+    }
+}
+
+/// What the follow modes found out about one item/file pair
+#[derive(Debug, Clone, Default)]
+pub struct Evidence {
+    pub modes: BTreeSet<FollowMode>,
+    /// Distance in meters, for files found via coordinates
+    pub distance: Option<f64>,
+}
+
+impl Evidence {
+    pub fn add(&mut self, mode: FollowMode, distance: Option<f64>) {
+        self.modes.insert(mode);
+        if let Some(distance) = distance {
+            self.distance = Some(self.distance.map_or(distance, |d| d.min(distance)));
+        }
+    }
+}
+
+/// File metadata from Commons
+#[derive(Debug, Clone, Default)]
+pub struct FileInfo {
+    pub global_usage: usize,
+    pub width: u64,
+    pub height: u64,
+    pub media_type: String,
+}
+
+/// One scoring signal, with its contribution and a human-readable reason
+#[derive(Debug, Clone)]
+pub struct Signal {
+    pub name: &'static str,
+    pub value: Value,
+    pub score: f64,
+    pub explanation: String,
+}
+
+impl Signal {
+    fn to_json(&self) -> Value {
+        json!({
+            "signal": self.name,
+            "value": self.value,
+            "score": self.score,
+            "explanation": self.explanation,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub file: String,
+    pub score: f64,
+    pub signals: Vec<Signal>,
+}
+
+impl Candidate {
+    pub fn new(
+        file: &str,
+        evidence: &Evidence,
+        info: Option<&FileInfo>,
+        label: Option<&str>,
+        property: TargetProperty,
+        radius: f64,
+    ) -> Self {
+        let mut signals = vec![follow_modes_signal(evidence)];
+        if let Some(info) = info {
+            signals.push(global_usage_signal(
+                info.global_usage,
+                property.penalizes_wide_usage(),
+            ));
+            signals.extend(dimensions_signal(info.width, info.height));
+            signals.push(media_type_signal(
+                &info.media_type,
+                property.expected_media_types(),
+            ));
+        }
+        if let Some(distance) = evidence.distance {
+            signals.push(distance_signal(distance, radius));
+        }
+        if let Some(label) = label {
+            signals.push(label_similarity_signal(label, file));
+        }
+        let score = signals.iter().map(|s| s.score).sum();
+        Self {
+            file: file.to_string(),
+            score,
+            signals,
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "file": self.file,
+            "score": self.score,
+            "signals": self.signals.iter().map(Signal::to_json).collect::<Vec<Value>>(),
+        })
+    }
+}
+
+/// Sorts candidates by descending score, then by file name
+pub fn rank(candidates: &mut [Candidate]) {
+    candidates.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.file.cmp(&b.file))
+    });
+}
+
+fn follow_modes_signal(evidence: &Evidence) -> Signal {
+    let modes: Vec<&str> = evidence.modes.iter().map(FollowMode::as_str).collect();
+    Signal {
+        name: "follow_modes",
+        score: FOLLOW_MODE_SCORE * modes.len() as f64,
+        explanation: format!("found by {} follow mode(s)", modes.len()),
+        value: json!(modes),
+    }
+}
+
+/// Wide usage is only penalized for properties like P18, where it hints at
+/// a generic image; a flag or coat of arms is meant to be used widely
+fn global_usage_signal(usage: usize, penalize_wide_usage: bool) -> Signal {
+    let (score, explanation) = if usage == 0 {
+        (0.0, "not used on any wiki page".to_string())
+    } else if usage <= MAX_SPECIFIC_USAGE {
+        (SPECIFIC_USAGE_SCORE, format!("used on {usage} page(s)"))
+    } else if !penalize_wide_usage {
+        (0.0, format!("used on {usage} pages"))
+    } else {
+        let penalty = (usage as f64 / MAX_SPECIFIC_USAGE as f64)
+            .ln()
+            .min(MAX_WIDE_USAGE_PENALTY);
+        (
+            -penalty,
+            format!("used on {usage} pages, likely a generic image"),
+        )
+    };
+    Signal {
+        name: "global_usage",
+        value: json!(usage),
+        score,
+        explanation,
+    }
+}
+
+fn dimensions_signal(width: u64, height: u64) -> Vec<Signal> {
+    if width == 0 || height == 0 {
+        return vec![];
+    }
+    let value = json!({"width": width, "height": height});
+    let mut ret = vec![];
+    if width.min(height) < MIN_IMAGE_SIDE {
+        ret.push(Signal {
+            name: "dimensions",
+            value: value.clone(),
+            score: -SMALL_IMAGE_PENALTY,
+            explanation: format!("small image ({width}x{height})"),
+        });
+    } else if width * height >= LARGE_IMAGE_PIXELS {
+        ret.push(Signal {
+            name: "dimensions",
+            value: value.clone(),
+            score: LARGE_IMAGE_SCORE,
+            explanation: format!("large image ({width}x{height})"),
+        });
+    }
+    let ratio = width.max(height) as f64 / width.min(height) as f64;
+    if ratio > MAX_ASPECT_RATIO {
+        ret.push(Signal {
+            name: "aspect_ratio",
+            value,
+            score: -ASPECT_RATIO_PENALTY,
+            explanation: format!("unusual aspect ratio ({ratio:.1}:1)"),
+        });
+    }
+    ret
+}
+
+fn media_type_signal(media_type: &str, expected: &[&str]) -> Signal {
+    let (score, explanation) = if expected.contains(&media_type) {
+        (0.5, "expected media type for the property")
+    } else if media_type == "DRAWING" {
+        (-1.5, "drawing, often a map, flag or logo")
+    } else {
+        (-2.0, "unexpected media type for the property")
+    };
+    Signal {
+        name: "media_type",
+        value: json!(media_type),
+        score,
+        explanation: explanation.to_string(),
+    }
+}
+
+fn distance_signal(distance: f64, radius: f64) -> Signal {
+    let score = if radius > 0.0 {
+        MAX_DISTANCE_SCORE * (1.0 - (distance / radius).clamp(0.0, 1.0))
+    } else {
+        0.0
+    };
+    Signal {
+        name: "distance",
+        value: json!(distance),
+        score,
+        explanation: format!("taken {distance:.0}m from the item's coordinates"),
+    }
+}
+
+fn label_similarity_signal(label: &str, file: &str) -> Signal {
+    let similarity = label_similarity(label, file);
+    Signal {
+        name: "label_similarity",
+        value: json!(similarity),
+        score: MAX_LABEL_SIMILARITY_SCORE * similarity,
+        explanation: format!(
+            "{:.0}% of the label words appear in the file name",
+            similarity * 100.0
+        ),
+    }
+}
+
+fn words(s: &str) -> HashSet<String> {
+    s.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| w.chars().count() > 1)
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Fraction of the label's words that occur in the file name (without extension)
+pub fn label_similarity(label: &str, file: &str) -> f64 {
+    let label_words = words(label);
+    if label_words.is_empty() {
+        return 0.0;
+    }
+    let file = file.rsplit_once('.').map_or(file, |(name, _ext)| name);
+    let file_words = words(file);
+    let matches = label_words.intersection(&file_words).count();
+    matches as f64 / label_words.len() as f64
+}
+
+/// Ranks the candidates of every item
+pub fn rank_item_files(
+    item2files: &HashMap<String, HashMap<String, usize>>,
+    item2evidence: &HashMap<String, HashMap<String, Evidence>>,
+    file2info: &HashMap<String, FileInfo>,
+    item2label: &HashMap<String, String>,
+    property: TargetProperty,
+    radius: f64,
+) -> HashMap<String, Vec<Candidate>> {
+    let no_evidence = Evidence::default();
+    item2files
+        .iter()
+        .map(|(q, files)| {
+            let label = item2label.get(q).map(|s| s.as_str());
+            let mut candidates: Vec<Candidate> = files
+                .keys()
+                .map(|file| {
+                    let evidence = item2evidence
+                        .get(q)
+                        .and_then(|e| e.get(file))
+                        .unwrap_or(&no_evidence);
+                    Candidate::new(file, evidence, file2info.get(file), label, property, radius)
+                })
+                .collect();
+            rank(&mut candidates);
+            (q.to_owned(), candidates)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-9, "{a} != {b}");
+    }
+
+    #[test]
+    fn test_label_similarity() {
+        assert_close(label_similarity("Walter Rüth", "Walter_Rüth_2010.jpg"), 1.0);
+        assert_close(label_similarity("Walter Rüth", "Walter_Smith.jpg"), 0.5);
+        assert_close(label_similarity("Walter Rüth", "Flag_of_Germany.svg"), 0.0);
+        assert_close(label_similarity("", "Walter.jpg"), 0.0);
+    }
+
+    #[test]
+    fn test_widely_used_drawing_ranks_below_photo() {
+        let mut evidence = Evidence::default();
+        evidence.add(FollowMode::LanguageLinks, None);
+        let flag = FileInfo {
+            global_usage: 5000,
+            width: 1000,
+            height: 600,
+            media_type: "DRAWING".to_string(),
+        };
+        let photo = FileInfo {
+            global_usage: 2,
+            width: 2000,
+            height: 1500,
+            media_type: "BITMAP".to_string(),
+        };
+        let mut candidates = vec![
+            Candidate::new(
+                "Flag_of_X.svg",
+                &evidence,
+                Some(&flag),
+                Some("X"),
+                TargetProperty::Image,
+                100.0,
+            ),
+            Candidate::new(
+                "Photo_of_X.jpg",
+                &evidence,
+                Some(&photo),
+                Some("X"),
+                TargetProperty::Image,
+                100.0,
+            ),
+        ];
+        rank(&mut candidates);
+        assert_eq!(candidates[0].file, "Photo_of_X.jpg");
+        assert!(
+            candidates[1]
+                .signals
+                .iter()
+                .any(|s| s.name == "global_usage" && s.score < 0.0)
+        );
+    }
+
+    #[test]
+    fn test_wide_usage_is_not_penalized_for_flags() {
+        let signal = global_usage_signal(5000, TargetProperty::Flag.penalizes_wide_usage());
+        assert_close(signal.score, 0.0);
+        let signal = global_usage_signal(5000, TargetProperty::Image.penalizes_wide_usage());
+        assert!(signal.score < 0.0);
+    }
+
+    #[test]
+    fn test_evidence_keeps_closest_distance() {
+        let mut evidence = Evidence::default();
+        evidence.add(FollowMode::Coordinates, Some(80.0));
+        evidence.add(FollowMode::Coordinates, Some(20.0));
+        evidence.add(FollowMode::SearchCommons, None);
+        assert_close(evidence.distance.unwrap(), 20.0);
+        assert_eq!(evidence.modes.len(), 2);
+        assert_close(distance_signal(20.0, 100.0).score, 1.6);
+    }
+}
@@ -3,6 +3,9 @@ use crate::config::Config;
 use crate::content_type::ContentType;
 use crate::form_parameters::FormParameters;
 use crate::platform::{MyResponse, Platform};
+use crate::result_cache::Cursor;
+use crate::saved_query::SavedQuery;
+use crate::snapshot::{MIN_WEB_SNAPSHOT_INTERVAL_SECONDS, Snapshot, SnapshotSelector};
 use anyhow::Result;
 use axum::Router;
 use axum::body::Bytes;
@@ -10,6 +13,7 @@ use axum::extract::{Request, State};
 use axum::http::{Method, StatusCode, header};
 use axum::response::{IntoResponse, Response};
 use axum::routing::any;
+use chrono::Utc;
 use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::sync::Arc;
@@ -102,6 +106,12 @@ impl WebServer {
         let (parts, body) = req.into_parts();
         let path = parts.uri.path().to_string();
 
+        // Named saved query
+        if let Some(name) = path.strip_prefix("/q/") {
+            let query = parts.uri.query().unwrap_or_default();
+            return self.process_saved_query(name, query).await.into_response();
+        }
+
         // URL GET query
         if let Some(query) = parts.uri.query()
             && !query.is_empty()
@@ -156,6 +166,7 @@ impl WebServer {
                     ),
                     content_type: ContentType::Plain,
                     status: StatusCode::GATEWAY_TIMEOUT.as_u16(),
+                    binary: None,
                 }
             }
         }
@@ -184,6 +195,7 @@ impl WebServer {
                 s: "Temporary maintenance".to_string(),
                 content_type: ContentType::Plain,
                 status: 200,
+                binary: None,
             };
         }
 
@@ -198,6 +210,7 @@ impl WebServer {
                 s: self.app_state.get_main_page(interface_language),
                 content_type: ContentType::HTML,
                 status: 200,
+                binary: None,
             };
         }
 
@@ -223,6 +236,11 @@ impl WebServer {
             }
         }
 
+        // "snapshot" or "cursor" parameter? Serve the stored result instead of running the query
+        if let Some(response) = self.process_stored_result(&form_parameters).await {
+            return response;
+        }
+
         // No "doit" parameter, just display the HTML form with the current query
         if form_parameters
             .params
@@ -243,9 +261,13 @@ impl WebServer {
                 s: html,
                 content_type: ContentType::HTML,
                 status: 200,
+                binary: None,
             };
         }
 
+        // Snapshot creation is a one-off request, not part of the query
+        let create_snapshot = form_parameters.params.remove("create_snapshot").is_some();
+
         let started_query_id = match self
             .app_state
             .log_query_start(&form_parameters.to_string())
@@ -307,6 +329,11 @@ impl WebServer {
         };
         Platform::profile("PSID set", None);
 
+        if create_snapshot {
+            let message = self.create_snapshot(&mut platform).await;
+            let _ = platform.warn(message);
+        }
+
         // Render response
         let response = match platform.get_response().await {
             Ok(response) => response,
@@ -316,6 +343,151 @@ impl WebServer {
         response
     }
 
+    /// `/q/<name>` runs the PSID a saved query points to; further URL
+    /// parameters (e.g. `format`) are applied on top. `/q/<name>/info`
+    /// returns the saved query itself as JSON.
+    async fn process_saved_query(&self, path: &str, query: &str) -> MyResponse {
+        let (name, info) = match path.strip_suffix("/info") {
+            Some(name) => (name, true),
+            None => (path, false),
+        };
+        let not_found = MyResponse {
+            s: format!("No saved query named '{name}'"),
+            content_type: ContentType::Plain,
+            status: StatusCode::NOT_FOUND.as_u16(),
+            binary: None,
+        };
+        if SavedQuery::validate_name(name).is_err() {
+            return not_found;
+        }
+        let saved_query = match self.app_state.get_saved_query(name).await {
+            Ok(Some(saved_query)) => saved_query,
+            Ok(None) => return not_found,
+            Err(e) => {
+                tracing::error!("Could not load saved query '{name}': {e}");
+                return MyResponse {
+                    s: "Internal Server Error".to_string(),
+                    content_type: ContentType::Plain,
+                    status: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+                    binary: None,
+                };
+            }
+        };
+        if info {
+            return MyResponse::ok(saved_query.as_json().to_string(), ContentType::JSON);
+        }
+        let parameters = Self::saved_query_parameters(saved_query.psid, query);
+        self.process_from_query(&parameters).await
+    }
+
+    /// Runs `psid` with the caller's parameters on top. `psid`, `cursor` and
+    /// `snapshot` are dropped from those, so they can't switch to another query.
+    fn saved_query_parameters(psid: u64, query: &str) -> String {
+        let mut serializer = form_urlencoded::Serializer::new(String::new());
+        serializer.append_pair("psid", &psid.to_string());
+        serializer.append_pair("doit", "1");
+        form_urlencoded::parse(query.as_bytes())
+            .filter(|(k, _)| !matches!(k.as_ref(), "psid" | "cursor" | "snapshot"))
+            .for_each(|(k, v)| {
+                serializer.append_pair(&k, &v);
+            });
+        serializer.finish()
+    }
+
+    /// Stores a snapshot of the result, unless the PSID got one in the last
+    /// `MIN_WEB_SNAPSHOT_INTERVAL_SECONDS`. Returns a message for the user.
+    async fn create_snapshot(&self, platform: &mut Platform) -> String {
+        if let Some(psid) = platform.psid {
+            match self.app_state.get_latest_snapshot_created(psid).await {
+                Ok(Some((id, created)))
+                    if Snapshot::is_younger_than(
+                        &created,
+                        MIN_WEB_SNAPSHOT_INTERVAL_SECONDS,
+                        Utc::now(),
+                    ) =>
+                {
+                    return format!(
+                        "Result snapshot #{id} from {created} is recent, not storing another"
+                    );
+                }
+                Ok(_) => {}
+                Err(e) => return format!("Could not store result snapshot: {e}"),
+            }
+        }
+        match platform.create_snapshot().await {
+            Ok(snapshot_id) => format!("Stored result snapshot #{snapshot_id}"),
+            Err(e) => format!("Could not store result snapshot: {e}"),
+        }
+    }
+
+    /// Serves a stored snapshot or a page of a cached result, if requested.
+    async fn process_stored_result(&self, form_parameters: &FormParameters) -> Option<MyResponse> {
+        if let Some(snapshot) = form_parameters.params.get("snapshot") {
+            return Some(self.process_snapshot(form_parameters, snapshot).await);
+        }
+        if let Some(cursor) = form_parameters.params.get("cursor") {
+            return Some(self.process_cursor(form_parameters, cursor).await);
+        }
+        None
+    }
+
+    /// Renders a stored result snapshot (`psid=…&snapshot=…`) in the
+    /// requested format, without running any data source.
+    async fn process_snapshot(
+        &self,
+        form_parameters: &FormParameters,
+        snapshot_param: &str,
+    ) -> MyResponse {
+        let psid = match form_parameters
+            .params
+            .get("psid")
+            .and_then(|psid| psid.trim().parse::<u64>().ok())
+        {
+            Some(psid) => psid,
+            None => {
+                return self
+                    .app_state
+                    .render_error("snapshot requires a PSID".to_string(), form_parameters);
+            }
+        };
+        let selector = match SnapshotSelector::from_param(snapshot_param) {
+            Ok(selector) => selector,
+            Err(e) => return self.app_state.render_error(e.to_string(), form_parameters),
+        };
+        let snapshot = match self.app_state.get_snapshot(psid, selector).await {
+            Ok(snapshot) => snapshot,
+            Err(e) => return self.app_state.render_error(e.to_string(), form_parameters),
+        };
+        let mut platform = Platform::new_from_parameters(form_parameters, self.app_state.clone());
+        platform.psid = Some(psid);
+        platform.load_snapshot(&snapshot);
+        let _ = platform.warn(format!(
+            "Showing result snapshot #{} from {}",
+            snapshot.id, snapshot.created
+        ));
+        match platform.get_response().await {
+            Ok(response) => response,
+            Err(e) => self.app_state.render_error(e.to_string(), form_parameters),
+        }
+    }
+
+    /// Renders a page of a result cached by an earlier request with
+    /// `limit=`, without running any data source.
+    async fn process_cursor(&self, form_parameters: &FormParameters, cursor: &str) -> MyResponse {
+        let cursor = match Cursor::from_param(cursor) {
+            Ok(cursor) => cursor,
+            Err(e) => return self.app_state.render_error(e.to_string(), form_parameters),
+        };
+        let mut platform = Platform::new_from_parameters(form_parameters, self.app_state.clone());
+        if let Err(e) = platform.load_cached_page(cursor) {
+            return self.app_state.render_error(e.to_string(), form_parameters);
+        }
+        match platform.get_response().await {
+            Ok(response) => response,
+            Err(e) => self.app_state.render_error(e.to_string(), form_parameters),
+        }
+    }
+
     /// Serve a static file from the in-memory cache populated at startup.
     /// "/" is an alias for "/index.html".
     fn serve_file_path(&self, path: &str) -> Response {
@@ -355,10 +527,14 @@ impl IntoResponse for MyResponse {
     fn into_response(self) -> Response {
         let status = StatusCode::from_u16(self.status).unwrap_or(StatusCode::OK);
         let content_type = self.content_type.as_str();
+        let body = match self.binary {
+            Some(bytes) => bytes,
+            None => self.s.into_bytes(),
+        };
         Response::builder()
             .status(status)
             .header(header::CONTENT_TYPE, content_type)
-            .body(self.s.into())
+            .body(body.into())
             .unwrap_or_else(|e| {
                 tracing::error!("Failed to build HTTP response: {e}");
                 (StatusCode::INTERNAL_SERVER_ERROR, "Internal Server Error").into_response()
@@ -376,6 +552,7 @@ mod tests {
             s: "hello".to_string(),
             content_type: ContentType::JSON,
             status: 201,
+            binary: None,
         };
         let resp = mr.into_response();
         assert_eq!(resp.status(), StatusCode::CREATED);
@@ -389,12 +566,36 @@ mod tests {
         assert_eq!(&body[..], b"hello");
     }
 
+    #[tokio::test]
+    async fn my_response_into_response_sends_binary_body() {
+        let mr = MyResponse::ok_binary(vec![0, 159, 146, 150], ContentType::Parquet);
+        let resp = mr.into_response();
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/vnd.apache.parquet"
+        );
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], &[0, 159, 146, 150]);
+    }
+
+    #[test]
+    fn saved_query_parameters_drop_psid_cursor_and_snapshot() {
+        assert_eq!(
+            WebServer::saved_query_parameters(12, "psid=1&format=json&cursor=x&snapshot=3&depth=2"),
+            "psid=12&doit=1&format=json&depth=2"
+        );
+        assert_eq!(WebServer::saved_query_parameters(12, ""), "psid=12&doit=1");
+    }
+
     #[tokio::test]
     async fn my_response_into_response_falls_back_to_200_for_invalid_status() {
         let mr = MyResponse {
             s: String::new(),
             content_type: ContentType::Plain,
             status: 0,
+            binary: None,
         };
         let resp = mr.into_response();
         assert_eq!(resp.status(), StatusCode::OK);
@@ -509,6 +710,20 @@ mod tests {
         assert_eq!(body, "Not Found");
     }
 
+    #[tokio::test]
+    async fn invalid_saved_query_name_returns_404_without_db() {
+        let server = test_server("<html></html>");
+        // Names that can never be stored are rejected before any tool-DB
+        // lookup, so this works against the default (unconnected) state.
+        let req = AxumRequest::builder()
+            .uri("/q/Not%20A%20Name/info")
+            .body(Body::empty())
+            .unwrap();
+        let (status, _ct, body) = send(&server, req).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body, "No saved query named 'Not%20A%20Name'");
+    }
+
     #[tokio::test]
     async fn oversized_post_body_is_rejected_with_413() {
         let server = test_server("<html></html>");
@@ -0,0 +1,384 @@
+//! Wikidata entity JSON, for statement values and lexeme data.
+//!
+//! The replicas only record which items and properties an item links to
+//! (`pagelinks`), not the values of its statements, and have no lemmas, so
+//! those come from the Wikidata API, or from a directory of entity JSON files
+//! (`entity_json_dir` in the config) for local development. One
+//! `EntityLoader` per query loads every entity once; API use is capped per
+//! query (`max_api_entities` in the config), entities over the cap are left
+//! out with a warning, and requests run a few at a time.
+
+pub mod filter;
+
+use crate::app_state::AppState;
+use crate::pagelist_entry::Lexeme;
+use crate::wikidata_entities::filter::ValueFilter;
+use anyhow::{Result, anyhow};
+use futures::stream::{StreamExt, iter};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use wikimisc::mediawiki::api::Api;
+
+const WIKIDATA_API_URL: &str = "https://www.wikidata.org/w/api.php";
+/// Maximum number of entities per `wbgetentities` request
+const WIKIDATA_API_ENTITY_BATCH_SIZE: usize = 50;
+/// Default for `max_api_entities` in the config
+const DEFAULT_MAX_API_ENTITIES: usize = 5000;
+/// Maximum number of concurrent `wbgetentities` requests
+const MAX_CONCURRENT_API_REQUESTS: usize = 4;
+/// Lexemes come with lemmas, language and lexical category either way
+const WIKIDATA_API_PROPS: &str = "info|claims";
+
+/// Checks for a property ID, like `P31`
+pub fn is_property_id(s: &str) -> bool {
+    s.len() > 1 && s.starts_with('P') && s[1..].chars().all(|c| c.is_ascii_digit())
+}
+
+/// Parses a comma- or pipe-separated property list like `P31,p569`,
+/// upper-casing IDs and dropping duplicates and anything that is not a property
+pub fn parse_property_list(s: &str) -> Vec<String> {
+    let mut ret: Vec<String> = vec![];
+    for property in s.split([',', '|']).map(|p| p.trim().to_uppercase()) {
+        if is_property_id(&property) && !ret.contains(&property) {
+            ret.push(property);
+        }
+    }
+    ret
+}
+
+/// Checks for an item ID, like `Q5`
+pub fn is_item_id(s: &str) -> bool {
+    s.len() > 1 && s.starts_with('Q') && s[1..].chars().all(|c| c.is_ascii_digit())
+}
+
+/// Parses a comma- or pipe-separated item list like `Q1084,q24905`,
+/// upper-casing IDs and dropping duplicates and anything that is not an item
+pub fn parse_item_list(s: &str) -> Vec<String> {
+    let mut ret: Vec<String> = vec![];
+    for item in s.split([',', '|']).map(|q| q.trim().to_uppercase()) {
+        if is_item_id(&item) && !ret.contains(&item) {
+            ret.push(item);
+        }
+    }
+    ret
+}
+
+/// Property placeholders like `{P31}` in a `row_template`, in order
+pub fn property_placeholders(template: &str) -> Vec<String> {
+    let properties: Vec<&str> = template
+        .split('{')
+        .skip(1)
+        .filter_map(|part| part.split_once('}'))
+        .map(|(property, _rest)| property)
+        .filter(|property| is_property_id(property))
+        .collect();
+    parse_property_list(&properties.join(","))
+}
+
+/// Entity JSON loaded for one query, shared by statement values, the value
+/// filter and lexeme data, so that every entity is loaded once and
+/// `max_api_entities` counts for the whole query
+#[derive(Debug, Default)]
+pub struct EntityLoader {
+    entities: HashMap<String, Value>,
+    /// Entities that could not be loaded, over the cap or after an API error
+    unloaded: HashSet<String>,
+    /// Number of entities requested from the API so far
+    api_entities: usize,
+    api: Option<Api>,
+}
+
+impl EntityLoader {
+    /// Loads those of `ids` that were not loaded before. Entities that do
+    /// not exist are left out. Returns a warning if some entities could not
+    /// be loaded; those are left out as well, see `is_unloaded`.
+    pub async fn load(&mut self, state: &AppState, ids: &[String]) -> Result<Option<String>> {
+        let mut missing: Vec<String> = ids
+            .iter()
+            .filter(|id| !self.entities.contains_key(*id) && !self.unloaded.contains(*id))
+            .cloned()
+            .collect();
+        missing.sort();
+        missing.dedup();
+        if missing.is_empty() {
+            return Ok(None);
+        }
+        if let Some(dir) = state.entity_json_dir() {
+            self.entities
+                .extend(load_entities_from_dir(dir, &missing).await?);
+            return Ok(None);
+        }
+
+        let max = state.max_api_entities().unwrap_or(DEFAULT_MAX_API_ENTITIES);
+        if let Some(warning) = api_cap_warning(self.api_entities, missing.len(), max) {
+            self.unloaded.extend(missing);
+            return Ok(Some(warning));
+        }
+        self.api_entities += missing.len();
+        let api = match self.api.take() {
+            Some(api) => api,
+            None => Api::new(WIKIDATA_API_URL)
+                .await
+                .map_err(|e| anyhow!("Wikidata API: {e}"))?,
+        };
+        let (entities, failed) = load_entities_from_api(&api, &missing).await;
+        self.api = Some(api);
+        self.entities.extend(entities);
+        if failed.is_empty() {
+            return Ok(None);
+        }
+        let warning = format!(
+            "{} Wikidata entities could not be loaded from the API, and are left out",
+            failed.len()
+        );
+        self.unloaded.extend(failed);
+        Ok(Some(warning))
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Value> {
+        self.entities.get(id)
+    }
+
+    /// Whether `id` could not be loaded, as opposed to not existing
+    pub fn is_unloaded(&self, id: &str) -> bool {
+        self.unloaded.contains(id)
+    }
+
+    /// Whether entity `id` matches `filter`. Entities that could not be
+    /// loaded pass, as they can't be checked; those that don't exist fail.
+    pub fn passes(&self, id: &str, filter: &ValueFilter) -> bool {
+        match self.entities.get(id) {
+            Some(entity) => filter.matches(entity),
+            None => self.is_unloaded(id),
+        }
+    }
+}
+
+/// A warning if loading `count` more entities from the API, after `loaded`,
+/// would exceed `max`
+fn api_cap_warning(loaded: usize, count: usize, max: usize) -> Option<String> {
+    if loaded + count <= max {
+        return None;
+    }
+    Some(format!(
+        "Too many Wikidata entities to load statement values or lexeme data for ({}, at most {max} per query); {count} of them are left out",
+        loaded + count
+    ))
+}
+
+async fn load_entities_from_dir(dir: &str, ids: &[String]) -> Result<HashMap<String, Value>> {
+    let mut ret = HashMap::new();
+    for id in ids {
+        let path = Path::new(dir).join(format!("{id}.json"));
+        let Ok(text) = tokio::fs::read_to_string(&path).await else {
+            continue; // No such entity
+        };
+        let j: Value = serde_json::from_str(&text)
+            .map_err(|e| anyhow!("Cannot parse {}: {e}", path.display()))?;
+        // Special:EntityData wraps the entity as {"entities":{"Q42":{...}}}
+        let entity = match j["entities"].get(id) {
+            Some(entity) => entity.to_owned(),
+            None => j,
+        };
+        ret.insert(id.to_owned(), entity);
+    }
+    Ok(ret)
+}
+
+/// The entities that exist, and the IDs of those in failed requests
+async fn load_entities_from_api(
+    api: &Api,
+    ids: &[String],
+) -> (HashMap<String, Value>, Vec<String>) {
+    let futures = ids.chunks(WIKIDATA_API_ENTITY_BATCH_SIZE).map(|chunk| {
+        let params = api.params_into(&[
+            ("action", "wbgetentities"),
+            ("ids", &chunk.join("|")),
+            ("props", WIKIDATA_API_PROPS),
+        ]);
+        async move { (chunk, api.get_query_api_json(&params).await) }
+    });
+    let results: Vec<_> = iter(futures)
+        .buffered(MAX_CONCURRENT_API_REQUESTS)
+        .collect()
+        .await;
+    let mut entities = HashMap::new();
+    let mut failed = vec![];
+    for (chunk, result) in results {
+        let result = match result {
+            Ok(result) => result,
+            Err(e) => {
+                tracing::warn!("Wikidata API: {e}");
+                failed.extend_from_slice(chunk);
+                continue;
+            }
+        };
+        if let Some(result_entities) = result["entities"].as_object() {
+            result_entities
+                .iter()
+                .filter(|(_id, entity)| entity.get("missing").is_none())
+                .for_each(|(id, entity)| {
+                    entities.insert(id.to_owned(), entity.to_owned());
+                });
+        }
+    }
+    (entities, failed)
+}
+
+/// Values of the non-deprecated statements of an entity for `property`,
+/// as plain strings. "No value" and "unknown value" statements are skipped.
+pub fn claim_values(entity: &Value, property: &str) -> Vec<String> {
+    entity["claims"][property]
+        .as_array()
+        .map(|claims| {
+            claims
+                .iter()
+                .filter(|claim| claim["rank"].as_str() != Some("deprecated"))
+                .filter_map(|claim| datavalue_to_string(&claim["mainsnak"]["datavalue"]))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Lemma, language and lexical category of a lexeme entity; `None` for
+/// other entities
+pub fn lexeme_from_entity(entity: &Value) -> Option<Lexeme> {
+    let lemmas: Vec<&str> = entity["lemmas"]
+        .as_object()?
+        .values()
+        .filter_map(|lemma| lemma["value"].as_str())
+        .collect();
+    Some(Lexeme {
+        lemma: lemmas.join(" / "),
+        language: entity["language"].as_str().map(|s| s.to_string()),
+        lexical_category: entity["lexicalCategory"].as_str().map(|s| s.to_string()),
+    })
+}
+
+/// Item and property values become IDs, times keep their Wikidata notation
+/// without the leading `+`, coordinates become `lat/lon`, and quantities
+/// get their unit item appended, if any.
+fn datavalue_to_string(datavalue: &Value) -> Option<String> {
+    let value = &datavalue["value"];
+    match datavalue["type"].as_str()? {
+        "wikibase-entityid" => value["id"].as_str().map(|s| s.to_string()),
+        "string" => value.as_str().map(|s| s.to_string()),
+        "monolingualtext" => value["text"].as_str().map(|s| s.to_string()),
+        "time" => value["time"]
+            .as_str()
+            .map(|s| s.trim_start_matches('+').to_string()),
+        "quantity" => {
+            let amount = value["amount"].as_str()?.trim_start_matches('+');
+            match value["unit"].as_str() {
+                Some(unit) if unit != "1" => {
+                    let unit = unit.rsplit('/').next().unwrap_or(unit);
+                    Some(format!("{amount} {unit}"))
+                }
+                _ => Some(amount.to_string()),
+            }
+        }
+        "globecoordinate" => Some(format!(
+            "{}/{}",
+            value["latitude"].as_f64()?,
+            value["longitude"].as_f64()?
+        )),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entity() -> Value {
+        json!({
+            "id": "Q42",
+            "claims": {
+                "P31": [
+                    {"rank": "normal", "mainsnak": {"datavalue": {"type": "wikibase-entityid", "value": {"id": "Q5"}}}},
+                    {"rank": "deprecated", "mainsnak": {"datavalue": {"type": "wikibase-entityid", "value": {"id": "Q1"}}}},
+                    {"rank": "normal", "mainsnak": {"snaktype": "novalue"}}
+                ],
+                "P569": [{"rank": "preferred", "mainsnak": {"datavalue": {"type": "time", "value": {"time": "+1952-03-11T00:00:00Z"}}}}],
+                "P625": [{"rank": "normal", "mainsnak": {"datavalue": {"type": "globecoordinate", "value": {"latitude": 52.5, "longitude": 13.25}}}}],
+                "P2048": [{"rank": "normal", "mainsnak": {"datavalue": {"type": "quantity", "value": {"amount": "+1.96", "unit": "http://www.wikidata.org/entity/Q11573"}}}}],
+                "P1082": [{"rank": "normal", "mainsnak": {"datavalue": {"type": "quantity", "value": {"amount": "+3500000", "unit": "1"}}}}],
+                "P1477": [{"rank": "normal", "mainsnak": {"datavalue": {"type": "monolingualtext", "value": {"text": "Douglas Noel Adams", "language": "en"}}}}]
+            }
+        })
+    }
+
+    #[test]
+    fn test_claim_values() {
+        let entity = entity();
+        assert_eq!(claim_values(&entity, "P31"), vec!["Q5"]);
+        assert_eq!(claim_values(&entity, "P569"), vec!["1952-03-11T00:00:00Z"]);
+        assert_eq!(claim_values(&entity, "P625"), vec!["52.5/13.25"]);
+        assert_eq!(claim_values(&entity, "P2048"), vec!["1.96 Q11573"]);
+        assert_eq!(claim_values(&entity, "P1082"), vec!["3500000"]);
+        assert_eq!(claim_values(&entity, "P1477"), vec!["Douglas Noel Adams"]);
+        assert!(claim_values(&entity, "P18").is_empty());
+    }
+
+    #[test]
+    fn test_parse_property_list() {
+        assert_eq!(
+            parse_property_list("P31, p569,Q5,P31|P625,"),
+            vec!["P31", "P569", "P625"]
+        );
+        assert!(parse_property_list("").is_empty());
+        assert!(!is_property_id("P"));
+        assert!(!is_property_id("P3a"));
+    }
+
+    #[test]
+    fn test_lexeme_from_entity() {
+        let lexeme = lexeme_from_entity(&json!({
+            "id": "L7",
+            "lemmas": {"en-gb": {"language": "en-gb", "value": "colour"}, "en-us": {"language": "en-us", "value": "color"}},
+            "language": "Q1860",
+            "lexicalCategory": "Q1084"
+        }))
+        .unwrap();
+        assert_eq!(lexeme.lemma, "colour / color");
+        assert_eq!(lexeme.language, Some("Q1860".to_string()));
+        assert_eq!(lexeme.lexical_category, Some("Q1084".to_string()));
+        assert_eq!(lexeme_from_entity(&entity()), None);
+        assert_eq!(
+            parse_item_list("Q1084, q24905,P31,Q1084"),
+            vec!["Q1084", "Q24905"]
+        );
+    }
+
+    #[test]
+    fn test_entity_loader_passes() {
+        let loader = EntityLoader {
+            entities: HashMap::from([("Q42".to_string(), entity())]),
+            unloaded: HashSet::from(["Q1".to_string()]),
+            ..Default::default()
+        };
+        let filter = ValueFilter::parse("has no P18").unwrap();
+        assert!(loader.passes("Q42", &filter));
+        assert!(loader.passes("Q1", &filter));
+        assert!(!loader.passes("Q2", &filter));
+        assert!(!loader.passes("Q42", &ValueFilter::parse("P31 != Q5").unwrap()));
+    }
+
+    #[test]
+    fn test_api_cap_warning() {
+        assert_eq!(api_cap_warning(0, 5000, 5000), None);
+        assert_eq!(api_cap_warning(4000, 1000, 5000), None);
+        assert!(api_cap_warning(4000, 1001, 5000).is_some());
+        assert_eq!(api_cap_warning(0, 0, 0), None);
+        assert!(api_cap_warning(0, 1, 0).is_some());
+    }
+
+    #[test]
+    fn test_property_placeholders() {
+        assert_eq!(
+            property_placeholders("* {title} {P31} {p17} {P569}{P31} {P"),
+            vec!["P31", "P569"]
+        );
+    }
+}
@@ -0,0 +1,476 @@
+//! Filters on Wikidata statement values, as given in `wikidata_value_filter`.
+//!
+//! Conditions are
+//! - `has P18`, `has no P18`
+//! - `P31 = Q5`, `P31 != Q5`
+//! - `P17 in {Q183, Q40}`, `P17 not in {Q183, Q40}`
+//! - `P569 before 1900`, `P569 after 1900-05` (compared at the given precision;
+//!   a date known only to the year does not match `before 1952-05`)
+//! - `P1082 > 1000000`, also `<`, `<=` and `>=`
+//!
+//! combined with `AND`, `OR` and parentheses; `AND` binds more tightly.
+//! A condition holds if any non-deprecated statement matches; `!=` and
+//! `not in` hold if none does. Strings with spaces need double quotes.
+
+use super::{claim_values, is_property_id};
+use anyhow::{Result, anyhow};
+use serde_json::Value;
+use std::cmp::Ordering;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    OpenSet,
+    CloseSet,
+    Comma,
+    Operator(String),
+    Word(String),
+    Quoted(String),
+}
+
+impl Token {
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self, Self::Word(word) if word.eq_ignore_ascii_case(keyword))
+    }
+}
+
+/// A date, with month and day only if given
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PartialDate {
+    year: i64,
+    month: Option<u32>,
+    day: Option<u32>,
+}
+
+impl PartialDate {
+    /// Parses `1900`, `1900-05`, `1900-05-01` and Wikidata times like
+    /// `1952-03-11T00:00:00Z`; a leading `-` is a year BCE
+    fn parse(s: &str) -> Option<Self> {
+        let s = s.split('T').next()?;
+        let (negative, s) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s.trim_start_matches('+')),
+        };
+        let mut parts = s.split('-');
+        let year = parts.next()?.parse::<i64>().ok()?;
+        let month = parts.next().map(str::parse::<u32>).transpose().ok()?;
+        let day = parts.next().map(str::parse::<u32>).transpose().ok()?;
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(Self {
+            year: if negative { -year } else { year },
+            month,
+            day,
+        })
+    }
+
+    /// Compares with `bound`, only down to the precision of `bound`. Missing
+    /// or zero months and days (as in year-precision Wikidata times like
+    /// `1952-00-00`) are unknown, so the result is `None` if it depends on them.
+    fn cmp_at_precision_of(&self, bound: &Self) -> Option<Ordering> {
+        let known = |part: Option<u32>| part.filter(|part| *part != 0);
+        match self.year.cmp(&bound.year) {
+            Ordering::Equal => {}
+            other => return Some(other),
+        }
+        let Some(month) = bound.month else {
+            return Some(Ordering::Equal);
+        };
+        match known(self.month)?.cmp(&month) {
+            Ordering::Equal => {}
+            other => return Some(other),
+        }
+        let Some(day) = bound.day else {
+            return Some(Ordering::Equal);
+        };
+        Some(known(self.day)?.cmp(&day))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl Comparison {
+    const fn holds(self, value: f64, bound: f64) -> bool {
+        match self {
+            Self::Less => value < bound,
+            Self::LessOrEqual => value <= bound,
+            Self::Greater => value > bound,
+            Self::GreaterOrEqual => value >= bound,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Test {
+    Exists,
+    Missing,
+    OneOf(Vec<String>),
+    NoneOf(Vec<String>),
+    Before(PartialDate),
+    After(PartialDate),
+    Compare(Comparison, f64),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValueFilter {
+    And(Vec<ValueFilter>),
+    Or(Vec<ValueFilter>),
+    Condition { property: String, test: Test },
+}
+
+impl ValueFilter {
+    pub fn parse(s: &str) -> Result<Self> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            pos: 0,
+        };
+        let ret = parser.expression()?;
+        match parser.tokens.get(parser.pos) {
+            Some(token) => Err(anyhow!("unexpected {token:?}")),
+            None => Ok(ret),
+        }
+    }
+
+    /// Checks an entity (as JSON) against the filter
+    pub fn matches(&self, entity: &Value) -> bool {
+        match self {
+            Self::And(parts) => parts.iter().all(|part| part.matches(entity)),
+            Self::Or(parts) => parts.iter().any(|part| part.matches(entity)),
+            Self::Condition { property, test } => {
+                let values = claim_values(entity, property);
+                match test {
+                    Test::Exists => !values.is_empty(),
+                    Test::Missing => values.is_empty(),
+                    Test::OneOf(wanted) => values.iter().any(|v| wanted.contains(v)),
+                    Test::NoneOf(unwanted) => !values.iter().any(|v| unwanted.contains(v)),
+                    Test::Before(bound) => values.iter().any(|v| {
+                        PartialDate::parse(v).is_some_and(|date| {
+                            date.cmp_at_precision_of(bound) == Some(Ordering::Less)
+                        })
+                    }),
+                    Test::After(bound) => values.iter().any(|v| {
+                        PartialDate::parse(v).is_some_and(|date| {
+                            date.cmp_at_precision_of(bound) == Some(Ordering::Greater)
+                        })
+                    }),
+                    Test::Compare(comparison, bound) => values.iter().any(|v| {
+                        // Quantities are "<amount> <unit>"
+                        v.split(' ')
+                            .next()
+                            .and_then(|amount| amount.parse::<f64>().ok())
+                            .is_some_and(|amount| comparison.holds(amount, *bound))
+                    }),
+                }
+            }
+        }
+    }
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>> {
+    let mut ret = vec![];
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '(' => ret.push(Token::Open),
+            ')' => ret.push(Token::Close),
+            '{' => ret.push(Token::OpenSet),
+            '}' => ret.push(Token::CloseSet),
+            ',' => ret.push(Token::Comma),
+            '=' => ret.push(Token::Operator("=".to_string())),
+            '!' | '<' | '>' => {
+                let mut operator = c.to_string();
+                if chars.peek() == Some(&'=') {
+                    chars.next();
+                    operator.push('=');
+                }
+                if operator == "!" {
+                    return Err(anyhow!("'!' must be followed by '='"));
+                }
+                ret.push(Token::Operator(operator));
+            }
+            '"' => {
+                let mut quoted = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(quoted_char) => quoted.push(quoted_char),
+                        None => return Err(anyhow!("unterminated quote")),
+                    }
+                }
+                ret.push(Token::Quoted(quoted));
+            }
+            c => {
+                let mut word = c.to_string();
+                while let Some(&word_char) = chars.peek() {
+                    if word_char.is_whitespace() || "(){},=!<>\"".contains(word_char) {
+                        break;
+                    }
+                    word.push(word_char);
+                    chars.next();
+                }
+                ret.push(Token::Word(word));
+            }
+        }
+    }
+    Ok(ret)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let ret = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        ret
+    }
+
+    fn next_is_keyword(&self, keyword: &str) -> bool {
+        self.tokens
+            .get(self.pos)
+            .is_some_and(|token| token.is_keyword(keyword))
+    }
+
+    fn expect(&mut self, expected: &Token) -> Result<()> {
+        match self.next() {
+            Some(token) if token == *expected => Ok(()),
+            Some(token) => Err(anyhow!("expected {expected:?}, found {token:?}")),
+            None => Err(anyhow!("expected {expected:?}, found end of filter")),
+        }
+    }
+
+    fn expression(&mut self) -> Result<ValueFilter> {
+        let mut parts = vec![self.conjunction()?];
+        while self.next_is_keyword("or") {
+            self.pos += 1;
+            parts.push(self.conjunction()?);
+        }
+        Ok(match parts.len() {
+            1 => parts.remove(0),
+            _ => ValueFilter::Or(parts),
+        })
+    }
+
+    fn conjunction(&mut self) -> Result<ValueFilter> {
+        let mut parts = vec![self.term()?];
+        while self.next_is_keyword("and") {
+            self.pos += 1;
+            parts.push(self.term()?);
+        }
+        Ok(match parts.len() {
+            1 => parts.remove(0),
+            _ => ValueFilter::And(parts),
+        })
+    }
+
+    fn term(&mut self) -> Result<ValueFilter> {
+        if self.tokens.get(self.pos) == Some(&Token::Open) {
+            self.pos += 1;
+            let ret = self.expression()?;
+            self.expect(&Token::Close)?;
+            return Ok(ret);
+        }
+        self.condition()
+    }
+
+    fn condition(&mut self) -> Result<ValueFilter> {
+        if self.next_is_keyword("has") {
+            self.pos += 1;
+            let test = if self.next_is_keyword("no") {
+                self.pos += 1;
+                Test::Missing
+            } else {
+                Test::Exists
+            };
+            let property = self.property()?;
+            return Ok(ValueFilter::Condition { property, test });
+        }
+        let property = self.property()?;
+        let test = match self.next() {
+            Some(Token::Operator(operator)) => match operator.as_str() {
+                "=" => Test::OneOf(vec![self.value()?]),
+                "!=" => Test::NoneOf(vec![self.value()?]),
+                "<" => Test::Compare(Comparison::Less, self.number()?),
+                "<=" => Test::Compare(Comparison::LessOrEqual, self.number()?),
+                ">" => Test::Compare(Comparison::Greater, self.number()?),
+                ">=" => Test::Compare(Comparison::GreaterOrEqual, self.number()?),
+                other => return Err(anyhow!("unknown operator '{other}'")),
+            },
+            Some(Token::Word(word)) => match word.to_lowercase().as_str() {
+                "in" => Test::OneOf(self.set()?),
+                "not" => {
+                    if !self.next_is_keyword("in") {
+                        return Err(anyhow!("expected 'in' after 'not'"));
+                    }
+                    self.pos += 1;
+                    Test::NoneOf(self.set()?)
+                }
+                "before" => Test::Before(self.date()?),
+                "after" => Test::After(self.date()?),
+                _ => return Err(anyhow!("unknown condition '{word}' for {property}")),
+            },
+            Some(token) => return Err(anyhow!("unexpected {token:?} after {property}")),
+            None => return Err(anyhow!("incomplete condition for {property}")),
+        };
+        Ok(ValueFilter::Condition { property, test })
+    }
+
+    fn property(&mut self) -> Result<String> {
+        match self.next() {
+            Some(Token::Word(word)) if is_property_id(&word.to_uppercase()) => {
+                Ok(word.to_uppercase())
+            }
+            Some(token) => Err(anyhow!("expected a property, found {token:?}")),
+            None => Err(anyhow!("expected a property, found end of filter")),
+        }
+    }
+
+    /// Entity IDs are upper-cased, anything else is taken as it is
+    fn value(&mut self) -> Result<String> {
+        match self.next() {
+            Some(Token::Word(word)) => {
+                let upper = word.to_uppercase();
+                let is_entity_id = upper.len() > 1
+                    && upper.starts_with(['Q', 'P', 'L'])
+                    && upper[1..].chars().all(|c| c.is_ascii_digit());
+                Ok(if is_entity_id { upper } else { word })
+            }
+            Some(Token::Quoted(quoted)) => Ok(quoted),
+            Some(token) => Err(anyhow!("expected a value, found {token:?}")),
+            None => Err(anyhow!("expected a value, found end of filter")),
+        }
+    }
+
+    fn set(&mut self) -> Result<Vec<String>> {
+        self.expect(&Token::OpenSet)?;
+        let mut ret = vec![self.value()?];
+        while self.tokens.get(self.pos) == Some(&Token::Comma) {
+            self.pos += 1;
+            ret.push(self.value()?);
+        }
+        self.expect(&Token::CloseSet)?;
+        Ok(ret)
+    }
+
+    fn number(&mut self) -> Result<f64> {
+        let value = self.value()?;
+        value
+            .parse::<f64>()
+            .map_err(|_| anyhow!("expected a number, found '{value}'"))
+    }
+
+    fn date(&mut self) -> Result<PartialDate> {
+        let value = self.value()?;
+        PartialDate::parse(&value).ok_or_else(|| anyhow!("expected a date, found '{value}'"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn claim(datavalue: Value) -> Value {
+        json!({"rank": "normal", "mainsnak": {"datavalue": datavalue}})
+    }
+
+    fn item(id: &str) -> Value {
+        claim(json!({"type": "wikibase-entityid", "value": {"id": id}}))
+    }
+
+    fn entity() -> Value {
+        json!({
+            "id": "Q42",
+            "claims": {
+                "P31": [item("Q5")],
+                "P27": [item("Q145"), item("Q174193")],
+                "P569": [claim(json!({"type": "time", "value": {"time": "+1952-03-11T00:00:00Z"}}))],
+                "P2048": [claim(json!({"type": "quantity", "value": {"amount": "+1.96", "unit": "http://www.wikidata.org/entity/Q11573"}}))],
+                "P1477": [claim(json!({"type": "monolingualtext", "value": {"text": "Douglas Noel Adams", "language": "en"}}))]
+            }
+        })
+    }
+
+    fn matches(filter: &str) -> bool {
+        ValueFilter::parse(filter).unwrap().matches(&entity())
+    }
+
+    #[test]
+    fn test_conditions() {
+        assert!(matches("P31 = Q5"));
+        assert!(matches("p31 = q5"));
+        assert!(!matches("P31 = Q515"));
+        assert!(matches("P31 != Q515"));
+        assert!(matches("P27 in {Q183, Q145}"));
+        assert!(!matches("P27 not in {Q183, Q145}"));
+        assert!(matches("has P31"));
+        assert!(matches("has no P18"));
+        assert!(!matches("has P18"));
+        assert!(matches("P569 before 1953"));
+        assert!(!matches("P569 before 1952"));
+        assert!(matches("P569 after 1952-02"));
+        assert!(!matches("P569 after 1952-03-11"));
+        assert!(matches("P2048 > 1.5"));
+        assert!(!matches("P2048 <= 1.5"));
+        assert!(matches("P1477 = \"Douglas Noel Adams\""));
+    }
+
+    #[test]
+    fn test_and_or() {
+        assert!(matches("P31 = Q5 AND P569 before 1900 OR has P1477"));
+        assert!(!matches("P31 = Q5 AND (P569 before 1900 OR has P18)"));
+        assert!(matches("(P31 = Q515 or P31 = Q5) and has no P18"));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(ValueFilter::parse("").is_err());
+        assert!(ValueFilter::parse("Q5 = P31").is_err());
+        assert!(ValueFilter::parse("P31 = Q5 AND").is_err());
+        assert!(ValueFilter::parse("P31 in {Q5").is_err());
+        assert!(ValueFilter::parse("P569 before yesterday").is_err());
+        assert!(ValueFilter::parse("(P31 = Q5").is_err());
+        assert!(ValueFilter::parse("P31 = Q5 P17 = Q183").is_err());
+    }
+
+    #[test]
+    fn test_partial_date() {
+        let date = PartialDate::parse("-0500-00-00T00:00:00Z").unwrap();
+        assert_eq!(date.year, -500);
+        assert_eq!(
+            date.cmp_at_precision_of(&PartialDate::parse("1").unwrap()),
+            Some(Ordering::Less)
+        );
+        assert!(PartialDate::parse("19a0").is_none());
+
+        // Known only to the year
+        let year = PartialDate::parse("+1952-00-00T00:00:00Z").unwrap();
+        let bound = |s: &str| PartialDate::parse(s).unwrap();
+        assert_eq!(
+            year.cmp_at_precision_of(&bound("1952")),
+            Some(Ordering::Equal)
+        );
+        assert_eq!(
+            year.cmp_at_precision_of(&bound("1953-05")),
+            Some(Ordering::Less)
+        );
+        assert_eq!(year.cmp_at_precision_of(&bound("1952-05")), None);
+        assert_eq!(year.cmp_at_precision_of(&bound("1952-05-01")), None);
+        let month = bound("1952-03");
+        assert_eq!(
+            month.cmp_at_precision_of(&bound("1952-02-28")),
+            Some(Ordering::Greater)
+        );
+        assert_eq!(month.cmp_at_precision_of(&bound("1952-03-28")), None);
+    }
+}